@@ -4,11 +4,15 @@
 
 use std::path::PathBuf;
 
-use clap::{ArgGroup, Parser, ValueEnum};
+use clap::{ArgGroup, Args as ClapArgs, Parser, Subcommand, ValueEnum};
+use tracing_subscriber::filter::LevelFilter;
+
+use crate::plugins::Plugin;
 
 /// Defines command line arguments.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(subcommand_negates_reqs = true)]
 #[command(group(
     ArgGroup::new("source")
         .required(true)
@@ -16,41 +20,710 @@ use clap::{ArgGroup, Parser, ValueEnum};
 ))]
 #[command(group(
     ArgGroup::new("logging")
-        .args(["log_level", "silent"])
+        .args(["log_level", "silent", "quiet"])
+))]
+#[command(group(
+    ArgGroup::new("cache")
+        .args(["offline", "prefer_cache", "refresh"])
 ))]
 pub(crate) struct Args {
-    /// Configuration file
+    /// Subcommand to run instead of building
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Configuration file, or - to read it from stdin
     pub config: Option<PathBuf>,
 
+    /// Format of the configuration file, detected from its extension or contents by default
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub config_format: Option<ConfigFormat>,
+
     /// Output file
     ///
     /// Use -o- to output to stdout and log to stderr.
     #[arg(short, long, value_name = "FILE", default_value = "newtabgen.html")]
     pub output: PathBuf,
 
+    /// Kind of output to build
+    ///
+    /// static-list builds a plain list of every link with inline styles only, for emailing or
+    /// archiving.
+    #[arg(long, value_enum, value_name = "TARGET", default_value_t = Target::Page)]
+    pub target: Target,
+
     /// Preview output in default browser
     #[arg(long)]
     pub open: bool,
 
-    /// Override default template with provided HTML file
+    /// Build only this profile of the config, to the output file
+    ///
+    /// If the config has profiles and none is selected, each profile is built to a file named
+    /// after it, next to the output file.
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Build the touch-friendly mobile layout, with a single column and larger tap targets
+    ///
+    /// Overrides build.layout in the config. To build a mobile variant alongside the desktop
+    /// page, set build.mobile_variant in the config instead.
+    #[arg(long)]
+    pub mobile: bool,
+
+    /// Build with a built-in or installed template pack, see list-templates
+    ///
+    /// --html and --scss override the pack's template and styles respectively.
+    #[arg(long, value_name = "NAME")]
+    pub template: Option<String>,
+
+    /// Override default template with provided HTML file or URL
+    ///
+    /// URLs are downloaded and cached for an hour, like other downloads.
     #[arg(long, value_name = "FILE")]
     pub html: Option<PathBuf>,
 
-    /// Override default styles with provided SCSS file
+    /// Override default styles with provided SCSS file or URL
+    ///
+    /// URLs are downloaded and cached for an hour, like other downloads.
     #[arg(long, value_name = "FILE")]
     pub scss: Option<PathBuf>,
 
-    /// Build using an example config
+    /// Build with a theme bundle: a directory or zip archive, or the URL of a zip archive
+    ///
+    /// A theme contains a theme.toml manifest, and optionally an index.html template, a
+    /// styles.scss template, and an assets directory whose files templates can embed from
+    /// theme_assets. --html and --scss override the theme's template and styles respectively.
+    #[arg(long, value_name = "PATH")]
+    pub theme: Option<PathBuf>,
+
+    /// Add a template filter implemented by a shell command, as NAME=COMMAND
+    ///
+    /// The command is run for each use of the filter, with {"value": ..., "args": {...}} as JSON
+    /// on stdin. Its output is parsed as JSON, or used as a string if it isn't valid JSON. May be
+    /// repeated.
+    #[arg(long = "filter", value_name = "NAME=COMMAND")]
+    pub filters: Vec<Plugin>,
+
+    /// Add a template function implemented by a shell command, as NAME=COMMAND
+    ///
+    /// Like --filter, but the command receives only {"args": {...}}. May be repeated.
+    #[arg(long = "function", value_name = "NAME=COMMAND")]
+    pub functions: Vec<Plugin>,
+
+    /// Ignore --html and --scss, building with the default template and styles
+    ///
+    /// Helps tell whether a failing build is caused by the config or by a custom template.
+    #[arg(long)]
+    pub safe: bool,
+
+    /// Build using a built-in example config, see list-examples
+    #[arg(long, value_name = "NAME", num_args = 0..=1, default_missing_value = "default")]
+    pub example: Option<String>,
+
+    /// Fail the build on problems that are otherwise only warned about, like low contrast icons
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Fetch the icons repository, even if it was updated recently or the pinned revision is
+    /// already available locally
+    #[arg(long, conflicts_with = "no_icon_update")]
+    pub update_icons: bool,
+
+    /// Don't update the icons repository, use it as checked out
+    #[arg(long)]
+    pub no_icon_update: bool,
+
+    /// Never use the network, failing on anything that isn't cached
+    ///
+    /// Applies to the icons repository, site icons, feeds, and remote resources. Cached items
+    /// never expire.
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Use cached downloads until they expire, downloading anything else (default)
+    #[arg(long)]
+    pub prefer_cache: bool,
+
+    /// Ignore cached downloads and download everything again
+    ///
+    /// Applies to the icons repository, site icons, feeds, and remote resources.
+    #[arg(long)]
+    pub refresh: bool,
+
+    /// Exit right away if another invocation is writing the same output or using the cache,
+    /// instead of waiting for it to finish
+    ///
+    /// Exits with code 75, so scheduled builds can tell an overlap apart from a failure.
+    #[arg(long)]
+    pub no_wait: bool,
+
+    /// Produce byte-identical output for identical inputs
+    ///
+    /// Icons are emitted in a stable order, the icons repository isn't updated, and cached site
+    /// icons never expire.
+    #[arg(long)]
+    pub reproducible: bool,
+
+    /// Keep this many previous versions of the output file, as FILE.1 (newest) to FILE.N
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub backups: usize,
+
+    /// Keep previous versions of the output file in this directory, named after when they were
+    /// replaced
+    ///
+    /// All versions are kept unless limited with --backups.
+    #[arg(long, value_name = "DIR")]
+    pub backup_dir: Option<PathBuf>,
+
+    /// Also write the links, with their site icons, as JSON for other tools to consume
+    ///
+    /// Written to links.json next to the output file, unless FILE is provided.
+    #[arg(long, value_name = "FILE", num_args = 0..=1)]
+    pub links_json: Option<Option<PathBuf>>,
+
+    /// Warn when a single inline style block exceeds this many bytes, failing with --strict
+    #[arg(long, value_name = "BYTES", default_value_t = newtabgen::DEFAULT_MAX_STYLE_BYTES)]
+    pub max_style_bytes: usize,
+
+    /// Warn when a single data URL, like the embedded background image, exceeds this many bytes,
+    /// failing with --strict
+    #[arg(long, value_name = "BYTES", default_value_t = newtabgen::DEFAULT_MAX_DATA_URL_BYTES)]
+    pub max_data_url_bytes: usize,
+
+    /// Wait at least this many milliseconds between site icon requests to the same host
+    #[arg(long, value_name = "MS", default_value_t = newtabgen::DEFAULT_ICON_HOST_INTERVAL.as_millis() as u64)]
+    pub icon_host_delay: u64,
+
+    /// Fetch at most this many site icons at the same time
+    #[arg(long, value_name = "COUNT", default_value_t = newtabgen::DEFAULT_MAX_ICON_CONNECTIONS)]
+    pub max_icon_connections: usize,
+
+    /// How aggressively to minify the output
+    #[arg(long, value_name = "LEVEL", value_enum, default_value_t = Minify::Standard)]
+    pub minify: Minify,
+
+    /// Skip minification and emit formatted HTML and CSS, for debugging custom templates
+    #[arg(long, conflicts_with = "minify")]
+    pub pretty: bool,
+
+    /// Write the rendered SCSS, compiled CSS, and rendered HTML to a directory before they're
+    /// minified, for debugging custom templates
+    ///
+    /// Written to newtabgen-debug in the system's temporary directory, unless DIR is provided.
+    #[arg(long, value_name = "DIR", num_args = 0..=1)]
+    pub debug_dir: Option<Option<PathBuf>>,
+
+    /// Always render the templates, instead of reusing cached output when neither the templates
+    /// nor their inputs changed
+    #[arg(long)]
+    pub no_render_cache: bool,
+
+    /// Drop links whose URL already appears earlier in the config, keeping the first occurrence
+    ///
+    /// Duplicate links are warned about either way, failing with --strict unless dropped.
     #[arg(long)]
-    pub example: bool,
+    pub dedupe: bool,
+
+    /// Warn about accessibility problems in the output, failing with --strict
+    ///
+    /// Reports links with only an icon and no label, images without alt text, and text colors
+    /// with low contrast against their background.
+    #[arg(long)]
+    pub a11y_check: bool,
+
+    /// Skip fetching icons from websites whose robots.txt disallows loading the linked page
+    ///
+    /// Those icons come from icon_fallback instead, if configured.
+    #[arg(long, conflicts_with = "icon_dir")]
+    pub respect_robots: bool,
+
+    /// Read site icons from this directory instead of fetching them from each website
+    ///
+    /// Icons are named after the website's host, e.g. github.com.png.
+    #[arg(long, value_name = "DIR")]
+    pub icon_dir: Option<PathBuf>,
+
+    /// Print the context the HTML template is rendered with as JSON, instead of building
+    ///
+    /// Shows every value available to custom templates, such as config and include_styles.
+    #[arg(long)]
+    pub dump_context: bool,
+
+    /// Print every network operation the build would perform, instead of building
+    ///
+    /// Lists the icons repository fetch, each site icon, the background image, feeds, and remote
+    /// templates and themes, leaving out anything that would be read from the cache.
+    #[arg(long, conflicts_with = "dump_context")]
+    pub plan: bool,
+
+    /// After building, check whether a newer release is available and print how to upgrade
+    ///
+    /// Only release metadata is downloaded, nothing is installed. The metadata is cached for an
+    /// hour, or used as cached with --offline.
+    #[arg(long)]
+    pub check_update: bool,
+
+    /// Embed a comment recording how the page was built, allowing it to be verified later
+    #[arg(long)]
+    pub provenance: bool,
+
+    /// Write a machine-readable build report to stderr, or to the report file
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub report: Option<ReportFormat>,
+
+    /// Write the build report to this file instead of stderr
+    #[arg(long, value_name = "FILE", requires = "report")]
+    pub report_file: Option<PathBuf>,
+
+    /// Show build progress on stderr
+    ///
+    /// Defaults to a progress bar when building to a file from a terminal. Use json to emit one
+    /// JSON event per line, for wrappers that track progress.
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub progress: Option<ProgressFormat>,
+
+    /// Don't show a progress bar when building to a file from a terminal
+    #[arg(long, conflicts_with = "progress")]
+    pub no_progress: bool,
 
     /// Log level
     #[arg(short, long, value_enum, value_name = "LEVEL", default_value_t = LogLevel::Info)]
     pub log_level: LogLevel,
 
-    /// Disable all logging
+    /// Log format
+    ///
+    /// Defaults to pretty when building to a file, and to a single line per event with timestamps
+    /// when building to stdout.
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub log_format: Option<LogFormat>,
+
+    /// Also append debug logs to this file, regardless of the log level, for attaching to bug
+    /// reports
+    ///
+    /// Rotated to FILE.1 once it grows past 10 MB, keeping the 3 most recent rotated logs.
+    #[arg(long, value_name = "FILE")]
+    pub log_file: Option<PathBuf>,
+
+    /// Disable all logging, reporting failure only through the exit code
     #[arg(short, long)]
     pub silent: bool,
+
+    /// Only log errors, and print the output file path once built
+    #[arg(short, long)]
+    pub quiet: bool,
+}
+
+impl Args {
+    /// Returns the most verbose level that should be logged, taking `--silent` and `--quiet` into
+    /// account.
+    pub fn max_log_level(&self) -> LevelFilter {
+        if self.silent {
+            LevelFilter::OFF
+        } else if self.quiet {
+            LevelFilter::ERROR
+        } else {
+            self.log_level.as_tracing_level().into()
+        }
+    }
+}
+
+/// Subcommands, used instead of building a new tab page.
+#[derive(Subcommand, Debug)]
+pub(crate) enum Command {
+    /// Rebuild a page that has an embedded provenance comment and confirm the output matches
+    ///
+    /// The page is rebuilt reproducibly, from the cached icons, so only pages built with
+    /// --reproducible are expected to match.
+    Verify(VerifyArgs),
+
+    /// Answer a few questions to create a commented starter config
+    Init(InitArgs),
+
+    /// List the built-in example configs, usable with --example
+    ListExamples,
+
+    /// List the built-in and installed template packs, usable with --template
+    ListTemplates,
+
+    /// Install, list, or remove template packs shared by others
+    #[command(subcommand)]
+    Template(TemplateCommand),
+
+    /// Check a custom HTML template or SCSS styles for undefined variables, functions, and
+    /// filters, without building
+    LintTemplate(LintTemplateArgs),
+
+    /// Build every built-in example to check that the templates, styles, and icon sources still
+    /// work
+    Selftest(SelftestArgs),
+
+    /// Export the links to another tool, such as an app launcher
+    Export(ExportArgs),
+
+    /// Build two configs and show the pages side by side, to evaluate changes before committing
+    /// to them
+    Compare(CompareArgs),
+}
+
+/// Subcommands of the `template` subcommand.
+#[derive(Subcommand, Debug)]
+pub(crate) enum TemplateCommand {
+    /// Install a template pack from a zip archive, a directory, or the URL of a zip archive
+    ///
+    /// Downloaded archives are verified against --sha256, or against the checksum published next
+    /// to them as <URL>.sha256 if it's not provided.
+    Install(TemplateInstallArgs),
+
+    /// List the installed template packs
+    List,
+
+    /// Remove an installed template pack
+    Remove(TemplateRemoveArgs),
+}
+
+/// Arguments for the `template install` subcommand.
+#[derive(ClapArgs, Debug)]
+pub(crate) struct TemplateInstallArgs {
+    /// Path or URL of the template pack
+    pub source: PathBuf,
+
+    /// Expected SHA-256 checksum of the archive, as hexadecimal
+    #[arg(long, value_name = "HEX")]
+    pub sha256: Option<String>,
+
+    /// Name to install the template pack as, instead of the name in its pack.toml
+    #[arg(long, value_name = "NAME")]
+    pub name: Option<String>,
+
+    /// Replace an installed template pack with the same name
+    #[arg(long)]
+    pub force: bool,
+}
+
+/// Arguments for the `template remove` subcommand.
+#[derive(ClapArgs, Debug)]
+pub(crate) struct TemplateRemoveArgs {
+    /// Name of the installed template pack
+    pub name: String,
+}
+
+/// Arguments for the `verify` subcommand.
+#[derive(ClapArgs, Debug)]
+#[command(group(
+    ArgGroup::new("source")
+        .required(true)
+        .args(["config", "example"])
+))]
+pub(crate) struct VerifyArgs {
+    /// Previously built page to verify
+    pub page: PathBuf,
+
+    /// Configuration file the page was built from
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Built-in template pack the page was built with, if not the default
+    #[arg(long, value_name = "NAME")]
+    pub template: Option<String>,
+
+    /// HTML template the page was built with, if not the default
+    #[arg(long, value_name = "FILE")]
+    pub html: Option<PathBuf>,
+
+    /// SCSS styles the page was built with, if not the default
+    #[arg(long, value_name = "FILE")]
+    pub scss: Option<PathBuf>,
+
+    /// Theme bundle the page was built with, if any
+    #[arg(long, value_name = "PATH")]
+    pub theme: Option<PathBuf>,
+
+    /// Template filter the page was built with, as NAME=COMMAND, see --filter
+    #[arg(long = "filter", value_name = "NAME=COMMAND")]
+    pub filters: Vec<Plugin>,
+
+    /// Template function the page was built with, as NAME=COMMAND, see --function
+    #[arg(long = "function", value_name = "NAME=COMMAND")]
+    pub functions: Vec<Plugin>,
+
+    /// Kind of output the page was built as
+    #[arg(long, value_enum, value_name = "TARGET", default_value_t = Target::Page)]
+    pub target: Target,
+
+    /// Profile of the config the page was built from, if any
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// The page was built with the mobile layout, see --mobile
+    #[arg(long)]
+    pub mobile: bool,
+
+    /// How aggressively the page was minified
+    #[arg(long, value_name = "LEVEL", value_enum, default_value_t = Minify::Standard)]
+    pub minify: Minify,
+
+    /// The page was built with --pretty
+    #[arg(long, conflicts_with = "minify")]
+    pub pretty: bool,
+
+    /// The page was built with --dedupe
+    #[arg(long)]
+    pub dedupe: bool,
+
+    /// Directory the page's site icons were read from, see --icon-dir
+    #[arg(long, value_name = "DIR")]
+    pub icon_dir: Option<PathBuf>,
+
+    /// Verify against a built-in example config
+    #[arg(long, value_name = "NAME", num_args = 0..=1, default_missing_value = "default")]
+    pub example: Option<String>,
+}
+
+/// Arguments for the `selftest` subcommand.
+#[derive(ClapArgs, Debug)]
+pub(crate) struct SelftestArgs {
+    /// Directory to build the examples in, defaults to a new temporary directory
+    #[arg(long, value_name = "DIR")]
+    pub dir: Option<PathBuf>,
+
+    /// Keep the temporary directory, even if every build succeeds
+    #[arg(long)]
+    pub keep: bool,
+}
+
+/// Arguments for the `export` subcommand.
+#[derive(ClapArgs, Debug)]
+#[command(group(
+    ArgGroup::new("source")
+        .required(true)
+        .args(["config", "example"])
+))]
+pub(crate) struct ExportArgs {
+    /// Configuration file to export the links of
+    pub config: Option<PathBuf>,
+
+    /// Export the links of a built-in example config
+    #[arg(long, value_name = "NAME", num_args = 0..=1, default_missing_value = "default")]
+    pub example: Option<String>,
+
+    /// Tool to export the links to
+    #[arg(long, value_enum, value_name = "TOOL")]
+    pub to: ExportTarget,
+
+    /// Output file, defaults to stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+/// Arguments for the `compare` subcommand.
+#[derive(ClapArgs, Debug)]
+pub(crate) struct CompareArgs {
+    /// Configuration file shown on the left
+    pub a: PathBuf,
+
+    /// Configuration file shown on the right
+    pub b: PathBuf,
+
+    /// Output file
+    #[arg(short, long, value_name = "FILE", default_value = "compare.html")]
+    pub out: PathBuf,
+
+    /// Built-in template pack to build both configs with, see list-templates
+    #[arg(long, value_name = "NAME")]
+    pub template: Option<String>,
+
+    /// Preview the comparison in default browser
+    #[arg(long)]
+    pub open: bool,
+}
+
+/// 1:1 with [`newtabgen::export::ExportFormat`] to aid in argument parsing.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ExportTarget {
+    /// Raycast quicklinks JSON, imported from Raycast's settings.
+    Raycast,
+
+    /// Alfred Script Filter JSON, printed by a workflow's script filter.
+    Alfred,
+}
+
+impl ExportTarget {
+    /// Converts the [`ExportTarget`] to the corresponding [`newtabgen::export::ExportFormat`].
+    pub fn as_export_format(&self) -> newtabgen::export::ExportFormat {
+        match self {
+            ExportTarget::Raycast => newtabgen::export::ExportFormat::Raycast,
+            ExportTarget::Alfred => newtabgen::export::ExportFormat::Alfred,
+        }
+    }
+}
+
+/// Arguments for the `lint-template` subcommand.
+#[derive(ClapArgs, Debug)]
+pub(crate) struct LintTemplateArgs {
+    /// Template to check
+    pub template: PathBuf,
+
+    /// Kind of template, guessed from the file extension if not provided
+    #[arg(long, value_enum, value_name = "KIND")]
+    pub kind: Option<TemplateKind>,
+
+    /// Configuration file the template will be built with, needed to check custom theme keys
+    #[arg(long, value_name = "FILE", conflicts_with = "example")]
+    pub config: Option<PathBuf>,
+
+    /// Check against a built-in example config
+    #[arg(long, value_name = "NAME", num_args = 0..=1, default_missing_value = "default")]
+    pub example: Option<String>,
+
+    /// Template filter the template will be built with, as NAME=COMMAND, see --filter
+    #[arg(long = "filter", value_name = "NAME=COMMAND")]
+    pub filters: Vec<Plugin>,
+
+    /// Template function the template will be built with, as NAME=COMMAND, see --function
+    #[arg(long = "function", value_name = "NAME=COMMAND")]
+    pub functions: Vec<Plugin>,
+}
+
+/// 1:1 with [`newtabgen::lint::TemplateKind`] to aid in argument parsing.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TemplateKind {
+    /// An HTML template, like --html.
+    Html,
+
+    /// SCSS styles, like --scss.
+    Scss,
+}
+
+impl TemplateKind {
+    /// Converts the [`TemplateKind`] to the corresponding [`newtabgen::lint::TemplateKind`].
+    pub fn as_lint_kind(&self) -> newtabgen::lint::TemplateKind {
+        match self {
+            TemplateKind::Html => newtabgen::lint::TemplateKind::Html,
+            TemplateKind::Scss => newtabgen::lint::TemplateKind::Scss,
+        }
+    }
+}
+
+/// Arguments for the `init` subcommand.
+#[derive(ClapArgs, Debug)]
+pub(crate) struct InitArgs {
+    /// Where to write the config, defaults to newtabgen.<format> in the current directory
+    pub output: Option<PathBuf>,
+
+    /// Config format, guessed from the output file extension or asked for if not provided
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub format: Option<ConfigFormat>,
+
+    /// Overwrite the output file if it already exists
+    #[arg(long)]
+    pub force: bool,
+}
+
+/// 1:1 with [`newtabgen::Target`] to aid in argument parsing.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Target {
+    /// The interactive new tab page.
+    Page,
+
+    /// A plain list of every link, without scripts or icons.
+    StaticList,
+}
+
+impl Target {
+    /// Converts the [`Target`] to the corresponding [`newtabgen::Target`].
+    pub fn as_build_target(&self) -> newtabgen::Target {
+        match self {
+            Target::Page => newtabgen::Target::Page,
+            Target::StaticList => newtabgen::Target::StaticList,
+        }
+    }
+}
+
+/// Levels of minification, like [`newtabgen::Minify`] without `Pretty`, which is selected with
+/// --pretty instead.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Minify {
+    /// Keep the output spec compliant and easy for other tools to parse.
+    Safe,
+
+    /// Omit anything browsers don't need.
+    Standard,
+
+    /// Also minify inline scripts and styles.
+    Aggressive,
+}
+
+impl Minify {
+    /// Converts the [`Minify`] to the corresponding [`newtabgen::Minify`].
+    pub fn as_build_minify(&self) -> newtabgen::Minify {
+        match self {
+            Minify::Safe => newtabgen::Minify::Safe,
+            Minify::Standard => newtabgen::Minify::Standard,
+            Minify::Aggressive => newtabgen::Minify::Aggressive,
+        }
+    }
+}
+
+/// Formats a config can be written in, when creating a starter config or reading one.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ConfigFormat {
+    /// YAML, with comments.
+    Yaml,
+
+    /// TOML, with comments.
+    Toml,
+
+    /// JSON, which doesn't support comments.
+    Json,
+}
+
+impl ConfigFormat {
+    /// Returns the file extension used by the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ConfigFormat::Yaml => "yaml",
+            ConfigFormat::Toml => "toml",
+            ConfigFormat::Json => "json",
+        }
+    }
+
+    /// Converts the [`ConfigFormat`] to the corresponding [`newtabgen::resources::ConfigFormat`].
+    pub fn as_resource_format(&self) -> newtabgen::resources::ConfigFormat {
+        match self {
+            ConfigFormat::Yaml => newtabgen::resources::ConfigFormat::Yaml,
+            ConfigFormat::Toml => newtabgen::resources::ConfigFormat::Toml,
+            ConfigFormat::Json => newtabgen::resources::ConfigFormat::Json,
+        }
+    }
+}
+
+/// Formats build progress can be shown in.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ProgressFormat {
+    /// An interactive progress bar.
+    Bar,
+
+    /// Newline-delimited JSON events.
+    Json,
+}
+
+/// Formats the build report can be written in.
+#[derive(ValueEnum, Clone, Debug)]
+pub(crate) enum ReportFormat {
+    /// Pretty-printed JSON.
+    Json,
+}
+
+/// Formats logs can be written in.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum LogFormat {
+    /// Multiple lines per event, for reading in a terminal.
+    Pretty,
+
+    /// A single short line per event.
+    Compact,
+
+    /// A JSON object per line, for log aggregators.
+    Json,
 }
 
 /// 1:1 with [`tracing::Level`] to aid in argument parsing, since tracing's levels are structs.
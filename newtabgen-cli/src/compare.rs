@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Implements the `compare` subcommand, which builds two configs and shows the pages side by side,
+//! to evaluate layout and theme changes before committing to them.
+
+use std::{fs, path::Path};
+
+use newtabgen::{resources::Resources, BuildOptions};
+use tracing::info;
+
+use crate::{args::CompareArgs, error::CliError};
+
+/// Builds both configs described by `args` and writes a page showing them side by side, each in
+/// its own frame. Downloads and rendered templates are cached as in any other build, so comparing
+/// a small change mostly reuses the previous builds.
+///
+/// # Errors
+///
+/// Returns an error if building either config, writing the comparison, or opening it in a browser
+/// fails.
+pub(crate) async fn compare(args: CompareArgs) -> Result<(), CliError> {
+    let options = BuildOptions::default();
+    let mut pages = Vec::with_capacity(2);
+    for config in [&args.a, &args.b] {
+        let resources = Resources {
+            config: Some(config.clone()),
+            template: args.template.clone(),
+            ..Default::default()
+        };
+        let mut page = Vec::new();
+        newtabgen::build(resources, &options, &mut page).await?;
+        pages.push((
+            config.as_path(),
+            String::from_utf8_lossy(&page).into_owned(),
+        ));
+    }
+
+    let html = comparison_html(&pages);
+    fs::write(&args.out, html).map_err(|e| CliError::CompareWrite(e, args.out.clone()))?;
+    info!(path = args.out.to_str(), "wrote comparison");
+
+    if args.open {
+        let canon =
+            fs::canonicalize(&args.out).map_err(|e| CliError::PageLocate(e, args.out.clone()))?;
+        let path = canon
+            .to_str()
+            .ok_or_else(|| CliError::PagePath(canon.clone()))?;
+        webbrowser::open(path).map_err(CliError::Browser)?;
+    }
+    Ok(())
+}
+
+/// Creates the comparison page, with a frame for each built page labelled with its config.
+fn comparison_html(pages: &[(&Path, String)]) -> String {
+    let mut columns = String::new();
+    for (config, page) in pages {
+        let label = escape(&config.display().to_string());
+        columns.push_str(&format!(
+            "<figure><figcaption>{label}</figcaption>\
+             <iframe title=\"{label}\" srcdoc=\"{}\"></iframe></figure>",
+            escape(page)
+        ));
+    }
+    format!(
+        "<!DOCTYPE html>\
+         <html><head><meta charset=\"UTF-8\" /><title>newtabgen comparison</title><style>\
+         body{{margin:0;display:flex;height:100vh;font-family:sans-serif;background:#222}}\
+         figure{{flex:1;display:flex;flex-direction:column;margin:0;min-width:0}}\
+         figure+figure{{border-left:2px solid #444}}\
+         figcaption{{padding:.4em .8em;color:#eee;overflow:hidden;text-overflow:ellipsis;\
+         white-space:nowrap}}\
+         iframe{{flex:1;border:0;background:#fff}}\
+         </style></head><body>{columns}</body></html>\n"
+    )
+}
+
+/// Escapes text for use within HTML, including attribute values.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides [`CliError`], for failures around a build rather than within it.
+
+use std::{io, path::PathBuf};
+
+use newtabgen::TemplateDiagnostic;
+use thiserror::Error;
+use tokio::task::JoinError;
+
+/// Errors that may occur when running the CLI.
+#[derive(Error, Debug)]
+pub(crate) enum CliError {
+    /// Occurs when building the page fails.
+    #[error(transparent)]
+    Build(#[from] newtabgen::Error),
+
+    /// Occurs when reading a previously built page fails.
+    #[error("failed to read page @ {1} ({0})")]
+    PageRead(#[source] io::Error, PathBuf),
+
+    /// Occurs when the built page can't be located to open it in a browser.
+    #[error("failed to locate built page @ {1} ({0})")]
+    PageLocate(#[source] io::Error, PathBuf),
+
+    /// Occurs when the built page's path isn't valid UTF-8, so it can't be opened in a browser.
+    #[error("path of built page isn't valid utf-8: {0}")]
+    PagePath(PathBuf),
+
+    /// Occurs when opening the built page in a browser fails, such as when none is installed.
+    #[error("failed to open browser ({0})")]
+    Browser(#[source] io::Error),
+
+    /// Occurs when writing the build report fails.
+    #[error("failed to write report @ {1} ({0})")]
+    ReportWrite(#[source] io::Error, PathBuf),
+
+    /// Occurs when writing the comparison page fails.
+    #[error("failed to write comparison @ {1} ({0})")]
+    CompareWrite(#[source] io::Error, PathBuf),
+
+    /// Occurs when opening or locking a lock file fails.
+    #[error("failed to lock @ {1} ({0})")]
+    Lock(#[source] io::Error, PathBuf),
+
+    /// Occurs when another invocation holds a lock and `--no-wait` is set.
+    #[error("another invocation holds the lock @ {0}")]
+    Locked(PathBuf),
+
+    /// Occurs when the task showing build progress panics.
+    #[error("progress task failed ({0})")]
+    Progress(#[from] JoinError),
+}
+
+impl CliError {
+    /// Explains where in a template the build failed, see [`newtabgen::Error::diagnostic`].
+    pub(crate) fn diagnostic(&self) -> Option<&TemplateDiagnostic> {
+        match self {
+            Self::Build(e) => e.diagnostic(),
+            _ => None,
+        }
+    }
+}
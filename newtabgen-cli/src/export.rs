@@ -0,0 +1,38 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Implements the `export` subcommand, which exports the links to another tool.
+
+use std::{
+    fs,
+    io::{self, Write},
+};
+
+use newtabgen::resources::Resources;
+use tracing::info;
+
+use crate::args::ExportArgs;
+
+/// Resolves the config described by `args` and writes its links in the requested format.
+///
+/// # Errors
+///
+/// Returns an error if resolving the config, or writing the exported links, fails.
+pub(crate) fn export(args: ExportArgs) -> Result<(), newtabgen::Error> {
+    let resources = Resources {
+        config: args.config,
+        example: args.example,
+        ..Default::default()
+    };
+    let config = newtabgen::resolve_config(&resources)?;
+    let mut exported = newtabgen::export::export(&config, args.to.as_export_format())?;
+    exported.push('\n');
+
+    match args.output {
+        Some(path) => {
+            fs::write(&path, exported)?;
+            info!(path = path.to_str(), "exported links");
+        }
+        None => io::stdout().lock().write_all(exported.as_bytes())?,
+    }
+    Ok(())
+}
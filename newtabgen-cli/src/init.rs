@@ -0,0 +1,234 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Implements the `init` subcommand, which asks a few questions and writes a starter config.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+
+use serde_json::json;
+use tracing::info;
+
+use crate::args::{ConfigFormat, InitArgs};
+
+/// A link entered by the user.
+struct StarterLink {
+    /// Display name.
+    name: String,
+
+    /// Website URL.
+    url: String,
+}
+
+/// Answers to the questions asked by [`init`].
+struct Answers {
+    /// Title of the new tab page.
+    title: String,
+
+    /// Indicates if the dark theme should be used.
+    dark: bool,
+
+    /// Links for the first section.
+    links: Vec<StarterLink>,
+}
+
+/// Asks the user about their new tab page on stderr, then writes a commented starter config.
+///
+/// # Errors
+///
+/// Returns an error if reading the answers or writing the config fails, or if the config file
+/// already exists and `args.force` isn't set.
+///
+/// # Returns
+///
+/// The path the config was written to.
+pub(crate) fn init(args: InitArgs) -> io::Result<PathBuf> {
+    let mut input = io::stdin().lock();
+
+    let format = match args.format {
+        Some(format) => format,
+        None => match args.output.as_ref().and_then(|p| p.extension()?.to_str()) {
+            Some("toml") => ConfigFormat::Toml,
+            Some("json") => ConfigFormat::Json,
+            Some("yaml" | "yml") => ConfigFormat::Yaml,
+            _ => {
+                let answer = ask(&mut input, "Config format (yaml, toml, json)", "yaml")?;
+                match answer.to_lowercase().as_str() {
+                    "toml" => ConfigFormat::Toml,
+                    "json" => ConfigFormat::Json,
+                    _ => ConfigFormat::Yaml,
+                }
+            }
+        },
+    };
+    let output = args
+        .output
+        .unwrap_or_else(|| PathBuf::from(format!("newtabgen.{}", format.extension())));
+    if output.exists() && !args.force {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!(
+                "{} already exists, use --force to overwrite it",
+                output.display()
+            ),
+        ));
+    }
+
+    let title = ask(&mut input, "Page title", "New Tab")?;
+    let dark = ask(&mut input, "Use the dark theme? (y/n)", "y")?;
+    let dark = !dark.to_lowercase().starts_with('n');
+
+    eprintln!("Add a few starter links, leave the name empty when done.");
+    let mut links = Vec::new();
+    loop {
+        let name = ask(&mut input, "Link name", "")?;
+        if name.is_empty() {
+            break;
+        }
+        let url = ask(&mut input, "Link URL", "")?;
+        links.push(StarterLink { name, url });
+    }
+
+    let answers = Answers { title, dark, links };
+    let config = match format {
+        ConfigFormat::Yaml => to_yaml(&answers),
+        ConfigFormat::Toml => to_toml(&answers),
+        ConfigFormat::Json => to_json(&answers),
+    };
+    fs::write(&output, config)?;
+    info!(path = output.to_str(), "wrote starter config");
+    Ok(output)
+}
+
+/// Asks a question on stderr and reads the answer from `input`.
+///
+/// # Returns
+///
+/// The trimmed answer, or `default` if the answer is empty.
+fn ask(input: &mut impl BufRead, question: &str, default: &str) -> io::Result<String> {
+    if default.is_empty() {
+        eprint!("{question}: ");
+    } else {
+        eprint!("{question} [{default}]: ");
+    }
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_owned())
+}
+
+/// Quotes a string. JSON strings are also valid YAML and TOML strings.
+fn quote(s: &str) -> String {
+    serde_json::to_string(s).expect("failed to quote string")
+}
+
+/// Header comment shared by the formats that support comments.
+const HEADER: &str = "\
+newtabgen config. Build it with: newtabgen <this file>
+See the newtabgen documentation for every available option.";
+
+/// Writes the answers as a commented YAML config.
+fn to_yaml(answers: &Answers) -> String {
+    let mut out = String::new();
+    for line in HEADER.lines() {
+        let _ = writeln!(out, "# {line}");
+    }
+    let _ = writeln!(
+        out,
+        "
+# Title of the new tab page, shown in the browser tab
+title: {title}
+
+# Theming preferences
+theme:
+  # Indicates if the dark theme should be used
+  dark: {dark}
+
+# Pages are shown as tabs at the top of the new tab page
+pages:
+  - name: Home
+    # Material design icon shown on the tab
+    icon: home
+    # Sections group related links
+    sections:
+      - name: Links",
+        title = quote(&answers.title),
+        dark = answers.dark,
+    );
+    if answers.links.is_empty() {
+        out.push_str("        links: []\n");
+    } else {
+        out.push_str("        links:\n");
+        for link in &answers.links {
+            let _ = writeln!(out, "          - name: {}", quote(&link.name));
+            let _ = writeln!(out, "            url: {}", quote(&link.url));
+        }
+    }
+    out
+}
+
+/// Writes the answers as a commented TOML config.
+fn to_toml(answers: &Answers) -> String {
+    let mut out = String::new();
+    for line in HEADER.lines() {
+        let _ = writeln!(out, "# {line}");
+    }
+    let _ = writeln!(
+        out,
+        "
+# Title of the new tab page, shown in the browser tab
+title = {title}
+
+# Theming preferences
+[theme]
+# Indicates if the dark theme should be used
+dark = {dark}
+
+# Pages are shown as tabs at the top of the new tab page
+[[pages]]
+name = \"Home\"
+# Material design icon shown on the tab
+icon = \"home\"
+
+# Sections group related links
+[[pages.sections]]
+name = \"Links\"",
+        title = quote(&answers.title),
+        dark = answers.dark,
+    );
+    if answers.links.is_empty() {
+        out.push_str("links = []\n");
+    }
+    for link in &answers.links {
+        let _ = writeln!(out, "\n[[pages.sections.links]]");
+        let _ = writeln!(out, "name = {}", quote(&link.name));
+        let _ = writeln!(out, "url = {}", quote(&link.url));
+    }
+    out
+}
+
+/// Writes the answers as a JSON config. JSON doesn't support comments, so none are included.
+fn to_json(answers: &Answers) -> String {
+    let links = answers
+        .links
+        .iter()
+        .map(|l| json!({ "name": l.name, "url": l.url }))
+        .collect::<Vec<_>>();
+    let config = json!({
+        "title": answers.title,
+        "theme": { "dark": answers.dark },
+        "pages": [{
+            "name": "Home",
+            "icon": "home",
+            "sections": [{ "name": "Links", "links": links }],
+        }],
+    });
+    let mut out = serde_json::to_string_pretty(&config).expect("failed to serialize config");
+    out.push('\n');
+    out
+}
@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Implements the `lint-template` subcommand, which checks a custom template without building.
+
+use std::fs;
+
+use newtabgen::{config::Config, lint, resources::Resources};
+use tracing::{info, warn};
+
+use crate::{
+    args::{LintTemplateArgs, TemplateKind},
+    plugins,
+};
+
+/// Checks the template described by `args`, logging each problem found.
+///
+/// # Errors
+///
+/// Returns an error if reading the template, loading the config, or parsing the template fails.
+///
+/// # Returns
+///
+/// `true` if no problems were found.
+pub(crate) fn lint_template(args: LintTemplateArgs) -> Result<bool, newtabgen::Error> {
+    let src = fs::read_to_string(&args.template)?;
+    let kind =
+        args.kind.unwrap_or_else(
+            || match args.template.extension().and_then(|e| e.to_str()) {
+                Some("scss" | "sass" | "css") => TemplateKind::Scss,
+                _ => TemplateKind::Html,
+            },
+        );
+    let config = if args.config.is_some() || args.example.is_some() {
+        let resources = Resources {
+            config: args.config,
+            example: args.example,
+            ..Default::default()
+        };
+        newtabgen::resolve_config(&resources)?
+    } else {
+        Config::default()
+    };
+
+    let hooks = plugins::hooks(&args.filters, &args.functions);
+    let issues = lint::lint_template(&src, kind.as_lint_kind(), &config, &hooks)?;
+    for issue in &issues {
+        warn!(kind = issue.kind.to_string(), name = issue.name, "{issue}");
+    }
+    if issues.is_empty() {
+        info!("no problems found");
+    }
+    Ok(issues.is_empty())
+}
@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Advisory locks that keep simultaneous invocations, such as overlapping scheduled builds, from
+//! writing the same output or the cache at the same time.
+
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    path::{Path, PathBuf},
+};
+
+use tracing::{debug, info};
+
+use crate::error::CliError;
+
+/// Exit code used when a lock is held by another invocation and `--no-wait` is set. Matches
+/// `EX_TEMPFAIL` from `sysexits.h`, since trying again later is expected to succeed.
+pub(crate) const LOCKED_EXIT_CODE: i32 = 75;
+
+/// A held lock, released when dropped. Lock files are left in place, since removing them could
+/// let another invocation lock a file that's about to be replaced.
+#[derive(Debug)]
+pub(crate) struct Lock {
+    /// The locked file, which must stay open to keep the lock.
+    _file: File,
+}
+
+/// Finds the lock file guarding an output file, which is kept next to it, e.g. `.newtab.html.lock`
+/// for `newtab.html`.
+pub(crate) fn output_lock_path(output: &Path) -> PathBuf {
+    let file_name = output
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("newtab");
+    output.with_file_name(format!(".{file_name}.lock"))
+}
+
+/// Finds the lock file guarding the cache, which holds the icons repository, site icons, feeds,
+/// and rendered templates.
+///
+/// # Errors
+///
+/// Returns an error if a suitable cache directory cannot be found.
+pub(crate) fn cache_lock_path() -> Result<PathBuf, CliError> {
+    let dir = newtabgen::util::cache_dir().map_err(|e| CliError::Lock(e, "cache".into()))?;
+    Ok(dir.join("lock"))
+}
+
+/// Locks a file exclusively, creating it if needed.
+///
+/// # Arguments
+///
+/// * `path` - The lock file.
+/// * `wait` - Indicates if a lock held by another invocation should be waited for, instead of
+///   failing with [`CliError::Locked`].
+///
+/// # Errors
+///
+/// Returns an error if the lock file can't be opened or locked, or if it's locked by another
+/// invocation and `wait` isn't set.
+pub(crate) fn acquire(path: &Path, wait: bool) -> Result<Lock, CliError> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .map_err(|e| CliError::Lock(e, path.into()))?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) if wait => {
+            info!(
+                path = path.to_str(),
+                "waiting for another invocation to finish"
+            );
+            file.lock().map_err(|e| CliError::Lock(e, path.into()))?;
+        }
+        Err(TryLockError::WouldBlock) => return Err(CliError::Locked(path.into())),
+        Err(TryLockError::Error(e)) => return Err(CliError::Lock(e, path.into())),
+    }
+    debug!(path = path.to_str(), "acquired lock");
+    Ok(Lock { _file: file })
+}
@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Opens the file requested with `--log-file`, which keeps debug logs of every invocation so they
+//! can be attached to bug reports, even when a scheduled build failed unattended.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Size in bytes after which the log file is rotated when opened.
+const MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Number of rotated log files kept, as `FILE.1` (newest) to `FILE.N`.
+const KEEP: usize = 3;
+
+/// Opens the log file for appending, rotating it first if it has grown past [`MAX_BYTES`].
+///
+/// # Errors
+///
+/// Returns an error if rotating or opening the log file fails.
+pub(crate) fn open(path: &Path) -> io::Result<File> {
+    if fs::metadata(path).is_ok_and(|m| m.len() >= MAX_BYTES) {
+        rotate(path)?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Finds the path of a rotated log file, e.g. `build.log.1`.
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+/// Shifts each rotated log file up by one, dropping the oldest, then moves the log file to
+/// `FILE.1`.
+fn rotate(path: &Path) -> io::Result<()> {
+    for n in (1..KEEP).rev() {
+        let from = rotated_path(path, n);
+        if from.exists() {
+            fs::rename(&from, rotated_path(path, n + 1))?;
+        }
+    }
+    fs::rename(path, rotated_path(path, 1))
+}
@@ -7,72 +7,621 @@
 #![warn(clippy::missing_docs_in_private_items)]
 
 mod args;
+mod compare;
+mod error;
+mod export;
+mod init;
+mod lint;
+mod lock;
+mod log_file;
+mod plan;
+mod plugins;
+mod progress;
+mod selftest;
+mod template;
+mod verify;
 
-use args::Args;
-use newtabgen::resources::Resources;
+use args::{Args, Command, LogFormat, ProgressFormat, ReportFormat};
+use error::CliError;
+use newtabgen::{
+    config::BuildLayout,
+    resources::{
+        remote_url, LayoutResources, ProfileResources, RemoteTemplates, ResourceSet, Resources,
+        ThemeBundle, EXAMPLES, TEMPLATE_PACKS,
+    },
+    BuildOptions, BuildReport, CacheMode, LocalIconFetcher, ProgressSender, RemoteIconFetcher,
+    TemplateDiagnostic,
+};
 
 use clap::Parser;
-use tracing::error;
-use tracing_subscriber::FmtSubscriber;
+use tokio::{sync::mpsc, task::JoinHandle};
+use tracing::{error, info, warn};
+use tracing_subscriber::{
+    filter::LevelFilter,
+    fmt::{self, MakeWriter},
+    prelude::*,
+};
 
 use std::{
-    fs::{self, File},
-    io, process,
+    fs,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 
 #[tokio::main]
 async fn main() {
-    let args = Args::parse();
+    let mut args = Args::parse();
+
+    match args.command.take() {
+        Some(Command::Verify(verify_args)) => {
+            init_stderr_logging(&args);
+            match verify::verify(verify_args).await {
+                Ok(true) => return,
+                Ok(false) => process::exit(1),
+                Err(e) => {
+                    error!(error = format!("{}", e), "verification failed");
+                    process::exit(1);
+                }
+            }
+        }
+        Some(Command::Init(init_args)) => {
+            init_stderr_logging(&args);
+            match init::init(init_args) {
+                Ok(path) => {
+                    eprintln!("Build your new tab page with: newtabgen {}", path.display());
+                    return;
+                }
+                Err(e) => {
+                    error!(error = format!("{}", e), "failed to create config");
+                    process::exit(1);
+                }
+            }
+        }
+        Some(Command::Export(export_args)) => {
+            init_stderr_logging(&args);
+            if let Err(e) = export::export(export_args) {
+                error!(error = format!("{}", e), "export failed");
+                process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Compare(compare_args)) => {
+            init_stderr_logging(&args);
+            if let Err(e) = compare::compare(compare_args).await {
+                error!(error = format!("{}", e), "comparison failed");
+                print_diagnostic(&args, e.diagnostic());
+                process::exit(1);
+            }
+            return;
+        }
+        Some(Command::LintTemplate(lint_args)) => {
+            init_stderr_logging(&args);
+            match lint::lint_template(lint_args) {
+                Ok(true) => return,
+                Ok(false) => process::exit(1),
+                Err(e) => {
+                    error!(error = format!("{}", e), "lint failed");
+                    process::exit(1);
+                }
+            }
+        }
+        Some(Command::Selftest(selftest_args)) => {
+            init_stderr_logging(&args);
+            match selftest::selftest(selftest_args).await {
+                Ok(true) => return,
+                Ok(false) => process::exit(1),
+                Err(e) => {
+                    error!(error = format!("{}", e), "self-test failed");
+                    process::exit(1);
+                }
+            }
+        }
+        Some(Command::ListExamples) => {
+            for example in EXAMPLES {
+                println!("{:<14} {}", example.name, example.description);
+            }
+            return;
+        }
+        Some(Command::ListTemplates) => {
+            for template in TEMPLATE_PACKS {
+                println!("{:<14} {}", template.name, template.description);
+            }
+            // Installed packs are only listed if there are any, so errors are ignored
+            for pack in newtabgen::template_store::list().unwrap_or_default() {
+                let description = pack.manifest.description.unwrap_or_default();
+                println!("{:<14} {} (installed)", pack.name, description);
+            }
+            return;
+        }
+        Some(Command::Template(command)) => {
+            init_stderr_logging(&args);
+            if let Err(e) = template::template(command).await {
+                error!(error = format!("{}", e), "template command failed");
+                process::exit(1);
+            }
+            return;
+        }
+        None => {}
+    }
+
+    if args.plan {
+        init_stderr_logging(&args);
+        if let Err(e) = plan::plan(&args).await {
+            error!(error = format!("{}", e), "failed to plan build");
+            print_diagnostic(&args, e.diagnostic());
+            process::exit(1);
+        }
+        return;
+    }
+
+    if args.dump_context {
+        init_stderr_logging(&args);
+        let context = match resources(&args).await {
+            Ok(resources) => newtabgen::template_context(resources, &build_options(&args))
+                .await
+                .map_err(CliError::from),
+            Err(e) => Err(e),
+        };
+        match context {
+            Ok(context) => {
+                let json = serde_json::to_string_pretty(&context).expect("failed to serialize");
+                println!("{json}");
+                return;
+            }
+            Err(e) => {
+                error!(error = format!("{}", e), "failed to build template context");
+                print_diagnostic(&args, e.diagnostic());
+                process::exit(1);
+            }
+        }
+    }
+
+    let result = match args.output.clone().to_str() {
+        Some("-") | None => build_to_stdout(&args).await,
+        Some(file) => build_to_file(&args, file).await,
+    };
+
+    let result = result.and_then(|reports| {
+        print_warnings(&args, &reports);
+        write_report(&args, &reports)
+    });
+    match result {
+        Ok(()) => {}
+        Err(CliError::Build(newtabgen::Error::Cancelled)) => {
+            warn!("build cancelled");
+            process::exit(130);
+        }
+        Err(e @ CliError::Locked(_)) => {
+            error!(error = format!("{}", e), "build skipped");
+            process::exit(lock::LOCKED_EXIT_CODE);
+        }
+        Err(e) => {
+            error!(error = format!("{}", e), "build failed");
+            print_diagnostic(&args, e.diagnostic());
+            process::exit(1);
+        }
+    }
+    if args.check_update {
+        check_update(&args).await;
+    }
+}
 
-    let resources = Resources {
+/// Warns if a newer release is available, for `--check-update`. Failing to check is only logged,
+/// since the page was built regardless.
+async fn check_update(args: &Args) {
+    let crate_name = env!("CARGO_PKG_NAME");
+    let current = env!("CARGO_PKG_VERSION");
+    match newtabgen::update::check(crate_name, current, cache_mode(args)).await {
+        Ok(Some(update)) => warn!("{}", update.instructions()),
+        Ok(None) => info!(version = current, "newtabgen is up to date"),
+        Err(e) => warn!(error = format!("{}", e), "failed to check for updates"),
+    }
+}
+
+/// Prints where in a template a build failed, if it did. Diagnostics span several lines, so
+/// they're printed after the error is logged instead of in a log field, unless logs are silenced
+/// or meant for machines.
+fn print_diagnostic(args: &Args, diagnostic: Option<&TemplateDiagnostic>) {
+    let Some(diagnostic) = diagnostic else {
+        return;
+    };
+    if args.silent || args.log_format == Some(LogFormat::Json) {
+        return;
+    }
+    eprintln!("\n{diagnostic}");
+}
+
+/// Prints the warnings of each build in a summary block once everything is built, so they don't
+/// scroll away with the logs. Skipped if warnings aren't logged, or logs are meant for machines.
+fn print_warnings(args: &Args, reports: &[BuildReport]) {
+    if args.max_log_level() < LevelFilter::WARN || args.log_format == Some(LogFormat::Json) {
+        return;
+    }
+    let mut warnings = Vec::new();
+    for report in reports {
+        let name = report.profile.clone();
+        warnings.extend(report.warnings.iter().map(|w| (name.clone(), w)));
+        if let Some(mobile) = &report.mobile_variant {
+            let name = match &name {
+                Some(name) => format!("{name}, mobile"),
+                None => "mobile".into(),
+            };
+            warnings.extend(mobile.warnings.iter().map(|w| (Some(name.clone()), w)));
+        }
+    }
+    if warnings.is_empty() {
+        return;
+    }
+
+    let plural = if warnings.len() == 1 { "" } else { "s" };
+    eprintln!("\n{} warning{plural}:", warnings.len());
+    for (name, warning) in warnings {
+        match name {
+            Some(name) => eprintln!("  - [{name}] {}", warning.message),
+            None => eprintln!("  - {}", warning.message),
+        }
+    }
+}
+
+/// Logs to stderr, leaving stdout free for output.
+fn init_stderr_logging(args: &Args) {
+    init_logging(
+        args.log_format,
+        args.max_log_level(),
+        io::stderr,
+        args.log_file.as_deref(),
+    );
+}
+
+/// Sets up the global tracing subscriber.
+///
+/// # Arguments
+///
+/// * `format` - Format to log in. If `None`, each event is logged on a single line with a
+///   timestamp.
+/// * `max_level` - The most verbose level that should be logged.
+/// * `writer` - Where to write logs.
+/// * `log_file` - A file to also write debug logs to, regardless of `max_level`.
+fn init_logging<W>(
+    format: Option<LogFormat>,
+    max_level: LevelFilter,
+    writer: W,
+    log_file: Option<&Path>,
+) where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let console = fmt::layer().with_writer(writer);
+    let console = match format {
+        None => console.boxed(),
+        Some(LogFormat::Pretty) => console.pretty().without_time().boxed(),
+        Some(LogFormat::Compact) => console.compact().boxed(),
+        Some(LogFormat::Json) => console.json().boxed(),
+    };
+
+    // Failing to open the log file is only logged once logging is set up
+    let (file, file_error) = match log_file.map(log_file::open) {
+        Some(Ok(file)) => (Some(file), None),
+        Some(Err(e)) => (None, Some(e)),
+        None => (None, None),
+    };
+    let file = file.map(|file| {
+        fmt::layer()
+            .with_writer(Mutex::new(file))
+            .with_ansi(false)
+            .with_filter(LevelFilter::DEBUG)
+    });
+
+    let subscriber = tracing_subscriber::registry()
+        .with(console.with_filter(max_level))
+        .with(file);
+    tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
+
+    if let Some(e) = file_error {
+        warn!(
+            path = log_file.and_then(Path::to_str),
+            error = %e,
+            "failed to open log file"
+        );
+    }
+}
+
+/// Converts the command line arguments into [`Resources`], built for the layout selected with
+/// `--mobile`. Templates given as URLs are downloaded up front.
+///
+/// # Errors
+///
+/// Returns an error if downloading a template fails.
+async fn resources(args: &Args) -> Result<LayoutResources<RemoteTemplates<Resources>>, CliError> {
+    let mut resources = Resources {
         config: args.config.clone(),
+        config_format: args.config_format.map(|f| f.as_resource_format()),
+        example: args.example.clone(),
+        template: args.template.clone(),
         scss: args.scss.clone(),
         html: args.html.clone(),
+        theme: None,
     };
+    if args.safe {
+        let html = resources.html.take();
+        let scss = resources.scss.take();
+        if html.is_some() || scss.is_some() || args.theme.is_some() {
+            warn!("safe mode, ignoring custom template and styles");
+        }
+    } else if let Some(theme) = &args.theme {
+        resources.theme = Some(load_theme(theme, cache_mode(args)).await?);
+    }
+    let html_url = resources.html.as_deref().and_then(remote_url);
+    let scss_url = resources.scss.as_deref().and_then(remote_url);
+    if html_url.is_some() {
+        resources.html = None;
+    }
+    if scss_url.is_some() {
+        resources.scss = None;
+    }
+    let resources = RemoteTemplates::fetch(
+        resources,
+        html_url.as_ref(),
+        scss_url.as_ref(),
+        cache_mode(args),
+    )
+    .await
+    .map_err(newtabgen::Error::from)?;
+    Ok(LayoutResources {
+        resources,
+        layout: args.mobile.then_some(BuildLayout::Mobile),
+    })
+}
 
-    let result = match args.output.clone().to_str() {
-        Some("-") | None => build_to_stdout(args, resources).await,
-        Some(file) => build_to_file(args, resources, file).await,
+/// Loads the theme bundle given with `--theme`, downloading it if it's a URL.
+///
+/// # Errors
+///
+/// Returns an error if loading or downloading the theme fails.
+pub(crate) async fn load_theme(path: &Path, cache: CacheMode) -> Result<ThemeBundle, CliError> {
+    let theme = match remote_url(path) {
+        Some(url) => ThemeBundle::fetch(&url, cache).await,
+        None => ThemeBundle::load(path),
     };
+    Ok(theme.map_err(newtabgen::Error::from)?)
+}
 
-    if let Err(e) = result {
-        error!(error = format!("{}", e), "build failed");
-        process::exit(1);
+/// Selects how cached downloads are used, from `--offline` and `--refresh`.
+fn cache_mode(args: &Args) -> CacheMode {
+    if args.offline {
+        CacheMode::Offline
+    } else if args.refresh {
+        CacheMode::Refresh
+    } else {
+        CacheMode::PreferCache
     }
 }
 
+/// Converts the command line arguments into [`BuildOptions`].
+fn build_options(args: &Args) -> BuildOptions {
+    let mut options = BuildOptions {
+        target: args.target.as_build_target(),
+        strict: args.strict,
+        update_icons: args.update_icons,
+        skip_icon_update: args.no_icon_update,
+        reproducible: args.reproducible,
+        provenance: args.provenance,
+        backups: args.backups,
+        backup_dir: args.backup_dir.clone(),
+        links_json: args.links_json.as_ref().map(|file| {
+            file.clone()
+                .unwrap_or_else(|| args.output.with_file_name("links.json"))
+        }),
+        max_style_bytes: args.max_style_bytes,
+        max_data_url_bytes: args.max_data_url_bytes,
+        icon_host_interval: Duration::from_millis(args.icon_host_delay),
+        max_icon_connections: args.max_icon_connections,
+        cache: cache_mode(args),
+        minify: if args.pretty {
+            newtabgen::Minify::Pretty
+        } else {
+            args.minify.as_build_minify()
+        },
+        render_cache: !args.no_render_cache,
+        debug_dir: args.debug_dir.as_ref().map(|dir| {
+            dir.clone()
+                .unwrap_or_else(|| std::env::temp_dir().join("newtabgen-debug"))
+        }),
+        dedupe: args.dedupe,
+        a11y_check: args.a11y_check,
+        tera_hooks: plugins::hooks(&args.filters, &args.functions),
+        ..Default::default()
+    };
+    if let Some(dir) = &args.icon_dir {
+        options.icon_fetcher = Arc::new(LocalIconFetcher::new(dir));
+    } else if args.respect_robots {
+        options.icon_fetcher = Arc::new(RemoteIconFetcher::respecting_robots());
+    }
+
+    // Cancel the build on Ctrl-C
+    let cancel = options.cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel.cancel();
+        }
+    });
+
+    options
+}
+
+/// Spawns a task that shows build progress in the provided format, if any.
+fn spawn_progress(
+    format: Option<ProgressFormat>,
+    options: &mut BuildOptions,
+) -> Option<JoinHandle<()>> {
+    let format = format?;
+    let (sender, receiver) = mpsc::unbounded_channel();
+    options.progress = ProgressSender::new(sender);
+    Some(match format {
+        ProgressFormat::Bar => progress::spawn_progress_bar(receiver),
+        ProgressFormat::Json => progress::spawn_progress_json(receiver),
+    })
+}
+
 /// Builds to stdout and logs to stderr.
-async fn build_to_stdout(args: Args, resources: Resources) -> Result<(), newtabgen::Error> {
-    let subscriber = FmtSubscriber::builder()
-        .with_writer(io::stderr)
-        .with_max_level(args.log_level.as_tracing_level())
-        .finish();
-    tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
+async fn build_to_stdout(args: &Args) -> Result<Vec<BuildReport>, CliError> {
+    init_stderr_logging(args);
+    let _cache_lock = lock::acquire(&lock::cache_lock_path()?, !args.no_wait)?;
+    let resources = resources(args).await?;
 
+    let mut options = build_options(args);
+    let progress_task = spawn_progress(args.progress, &mut options);
     let mut output = io::stdout().lock();
-    newtabgen::build(resources, &mut output).await
+    let result = match &args.profile {
+        Some(profile) => {
+            let resources = ProfileResources {
+                resources,
+                profile: profile.clone(),
+            };
+            newtabgen::build(resources, &options, &mut output).await
+        }
+        None => newtabgen::build(resources, &options, &mut output).await,
+    };
+
+    // Dropping the options closes the progress channel, letting the progress task finish
+    drop(options);
+    if let Some(progress_task) = progress_task {
+        progress_task.await?;
+    }
+    Ok(vec![result?])
 }
 
-/// Builds to the provided file path.
-async fn build_to_file(
-    args: Args,
-    resources: Resources,
-    file: &str,
-) -> Result<(), newtabgen::Error> {
-    let event_format = tracing_subscriber::fmt::format().without_time().pretty();
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(args.log_level.as_tracing_level())
-        .event_format(event_format)
-        .finish();
-    tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
+/// Builds to the provided file path, or each profile to a file next to it.
+async fn build_to_file(args: &Args, file: &str) -> Result<Vec<BuildReport>, CliError> {
+    // The progress bar replaces informational logs, so only show it by default at the default log
+    // level
+    let progress_format = args.progress.or_else(|| {
+        let show_bar = !args.no_progress
+            && args.max_log_level() == LevelFilter::INFO
+            && io::stderr().is_terminal();
+        show_bar.then_some(ProgressFormat::Bar)
+    });
+    let max_level = if progress_format == Some(ProgressFormat::Bar) {
+        LevelFilter::WARN
+    } else {
+        args.max_log_level()
+    };
+
+    let log_format = args.log_format.unwrap_or(LogFormat::Pretty);
+    init_logging(
+        Some(log_format),
+        max_level,
+        io::stdout,
+        args.log_file.as_deref(),
+    );
+
+    // Locks are always taken in this order, so invocations can't wait on each other
+    let _output_lock = lock::acquire(&lock::output_lock_path(Path::new(file)), !args.no_wait)?;
+    let _cache_lock = lock::acquire(&lock::cache_lock_path()?, !args.no_wait)?;
+
+    let resources = resources(args).await?;
+    let mut options = build_options(args);
+    let progress_task = spawn_progress(progress_format, &mut options);
 
-    let mut output = File::create(file).expect("failed to create output file");
-    newtabgen::build(resources, &mut output).await?;
+    // Without a profile selected, a config with profiles builds each of them next to the output
+    let build_all_profiles = match args.profile {
+        Some(_) => Ok(false),
+        None => resources.config().map(|c| !c.profiles.is_empty()),
+    };
+
+    // The previous page is kept intact if the build fails
+    let result = match build_all_profiles {
+        Ok(true) => {
+            let dir = Path::new(file).parent().unwrap_or(Path::new(""));
+            newtabgen::build_profiles(resources, &options, dir)
+                .await
+                .map(|reports| {
+                    let files = reports
+                        .iter()
+                        .filter_map(|r| r.profile.as_ref())
+                        .map(|name| dir.join(format!("{name}.html")))
+                        .collect();
+                    (files, reports)
+                })
+        }
+        Ok(false) => {
+            let report = match &args.profile {
+                Some(profile) => {
+                    let resources = ProfileResources {
+                        resources,
+                        profile: profile.clone(),
+                    };
+                    newtabgen::build_to_path(resources, &options, file).await
+                }
+                None => newtabgen::build_to_path(resources, &options, file).await,
+            };
+            report.map(|report| (vec![PathBuf::from(file)], vec![report]))
+        }
+        Err(e) => Err(e.into()),
+    };
+
+    // Dropping the options closes the progress channel, letting the progress task finish
+    drop(options);
+    if let Some(progress_task) = progress_task {
+        progress_task.await?;
+    }
+    let (mut files, reports) = result?;
+
+    // Mobile variants are written next to their pages
+    let mobile_files = files
+        .iter()
+        .zip(&reports)
+        .filter(|(_, report)| report.mobile_variant.is_some())
+        .map(|(file, _)| newtabgen::mobile_variant_path(file))
+        .collect::<Vec<_>>();
+    files.extend(mobile_files);
+
+    if args.quiet {
+        for file in &files {
+            println!("{}", file.display());
+        }
+    }
 
     if args.open {
-        let canon = fs::canonicalize(file).expect("failed to canonicalize file");
-        webbrowser::open(canon.to_str().expect("invalid path")).expect("failed to open browser");
+        if let Some(file) = files.first() {
+            let canon =
+                fs::canonicalize(file).map_err(|e| CliError::PageLocate(e, file.clone()))?;
+            let path = canon
+                .to_str()
+                .ok_or_else(|| CliError::PagePath(canon.clone()))?;
+            webbrowser::open(path).map_err(CliError::Browser)?;
+        }
     }
 
+    Ok(reports)
+}
+
+/// Writes the build report to stderr or the report file, if requested. When several profiles were
+/// built, their reports are written as an array.
+///
+/// # Errors
+///
+/// Returns an error if writing the report file fails.
+fn write_report(args: &Args, reports: &[BuildReport]) -> Result<(), CliError> {
+    let Some(format) = &args.report else {
+        return Ok(());
+    };
+    let serialized = match (format, reports) {
+        (ReportFormat::Json, [report]) => {
+            serde_json::to_string_pretty(report).expect("failed to serialize report")
+        }
+        (ReportFormat::Json, reports) => {
+            serde_json::to_string_pretty(reports).expect("failed to serialize report")
+        }
+    };
+
+    match &args.report_file {
+        Some(file) => {
+            fs::write(file, serialized).map_err(|e| CliError::ReportWrite(e, file.clone()))?
+        }
+        None => eprintln!("{serialized}"),
+    }
     Ok(())
 }
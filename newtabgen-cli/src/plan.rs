@@ -0,0 +1,69 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Implements `--plan`, which lists the network operations a build would perform without
+//! performing them.
+
+use newtabgen::{
+    config::BuildLayout,
+    resources::{remote_url, LayoutResources, Resources},
+};
+use tracing::info;
+
+use crate::{args::Args, build_options, error::CliError, load_theme};
+
+/// Prints every network operation building with `args` would perform, one per line, without
+/// performing them. Templates and themes given as URLs are listed as downloads, and the built-in
+/// templates are planned with in their place.
+///
+/// # Errors
+///
+/// Returns an error if loading the config, templates, or a local theme fails.
+pub(crate) async fn plan(args: &Args) -> Result<(), CliError> {
+    let options = build_options(args);
+    let mut operations = Vec::new();
+    let mut resources = Resources {
+        config: args.config.clone(),
+        config_format: args.config_format.map(|f| f.as_resource_format()),
+        example: args.example.clone(),
+        template: args.template.clone(),
+        ..Default::default()
+    };
+    if !args.safe {
+        for (path, local) in [
+            (&args.html, &mut resources.html),
+            (&args.scss, &mut resources.scss),
+        ] {
+            let Some(path) = path else {
+                continue;
+            };
+            match remote_url(path) {
+                Some(url) => operations.extend(newtabgen::plan_resource(&url, &options)),
+                None => *local = Some(path.clone()),
+            }
+        }
+        if let Some(theme) = &args.theme {
+            match remote_url(theme) {
+                Some(url) => operations.extend(newtabgen::plan_resource(&url, &options)),
+                None => resources.theme = Some(load_theme(theme, options.cache).await?),
+            }
+        }
+    }
+    let resources = LayoutResources {
+        resources,
+        layout: args.mobile.then_some(BuildLayout::Mobile),
+    };
+    operations.extend(newtabgen::plan_network(resources, &options).await?);
+
+    if operations.is_empty() {
+        info!("the build wouldn't use the network");
+    }
+    for operation in operations {
+        println!(
+            "{:<11} {:<25} {}",
+            operation.kind.to_string(),
+            operation.action,
+            operation.url
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,145 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Registers template filters and functions implemented by external commands, given with
+//! `--filter` and `--function`, so templates can use helpers that aren't built in.
+//!
+//! Each call runs the command with the shell, writing its input to stdin as JSON: the filtered
+//! value and the arguments for filters (`{"value": ..., "args": {...}}`), and only the arguments
+//! for functions (`{"args": {...}}`). Its output is parsed as JSON, or used as a string if it isn't
+//! valid JSON, without a trailing newline.
+
+use std::{
+    collections::HashMap,
+    io::Write,
+    process::{Command, Stdio},
+    str::FromStr,
+    thread,
+};
+
+use newtabgen::{
+    tera::{self, Tera, Value},
+    TeraHook,
+};
+use serde_json::json;
+
+/// A template helper implemented by an external command, given as `NAME=COMMAND`.
+#[derive(Debug, Clone)]
+pub(crate) struct Plugin {
+    /// Name templates call the helper by.
+    pub name: String,
+
+    /// Shell command run for each call.
+    pub command: String,
+}
+
+impl FromStr for Plugin {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, command) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected NAME=COMMAND, got '{s}'"))?;
+        let valid_name = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_name {
+            return Err(format!("'{name}' isn't a valid template identifier"));
+        }
+        if command.trim().is_empty() {
+            return Err(format!("no command provided for '{name}'"));
+        }
+        Ok(Self {
+            name: name.to_owned(),
+            command: command.to_owned(),
+        })
+    }
+}
+
+/// Creates the hooks that register the provided filters and functions.
+///
+/// # Returns
+///
+/// A single hook, or none if no filters or functions were provided.
+pub(crate) fn hooks(filters: &[Plugin], functions: &[Plugin]) -> Vec<TeraHook> {
+    if filters.is_empty() && functions.is_empty() {
+        return Vec::new();
+    }
+    let (filters, functions) = (filters.to_vec(), functions.to_vec());
+    vec![TeraHook::new(move |tera| {
+        register(tera, &filters, &functions)
+    })]
+}
+
+/// Registers each filter and function with a template renderer.
+fn register(tera: &mut Tera, filters: &[Plugin], functions: &[Plugin]) {
+    for plugin in filters {
+        let plugin = plugin.clone();
+        tera.register_filter(
+            &plugin.name.clone(),
+            move |value: &Value, args: &HashMap<String, Value>| {
+                run(&plugin, &json!({ "value": value, "args": args }))
+            },
+        );
+    }
+    for plugin in functions {
+        let plugin = plugin.clone();
+        tera.register_function(
+            &plugin.name.clone(),
+            move |args: &HashMap<String, Value>| run(&plugin, &json!({ "args": args })),
+        );
+    }
+}
+
+/// Runs the command of a plugin with the provided input.
+///
+/// # Errors
+///
+/// Returns an error if the command can't be started or exits unsuccessfully.
+///
+/// # Returns
+///
+/// The output of the command, parsed as JSON if possible.
+fn run(plugin: &Plugin, input: &Value) -> tera::Result<Value> {
+    let name = &plugin.name;
+    let mut child = shell(&plugin.command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| tera::Error::msg(format!("failed to run '{name}' ({e})")))?;
+
+    // Written from another thread so a command with a lot of output can't block on a full pipe
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = input.to_string();
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|e| tera::Error::msg(format!("failed to run '{name}' ({e})")))?;
+    // Commands that don't read their input close the pipe early, which isn't a problem
+    let _ = writer.join();
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let mut msg = format!("'{name}' failed ({})", output.status);
+        if !stderr.trim().is_empty() {
+            msg = format!("{msg}: {}", stderr.trim());
+        }
+        return Err(tera::Error::msg(msg));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stdout = stdout.strip_suffix('\n').unwrap_or(&stdout);
+    let stdout = stdout.strip_suffix('\r').unwrap_or(stdout);
+    Ok(serde_json::from_str(stdout).unwrap_or_else(|_| Value::String(stdout.to_owned())))
+}
+
+/// Creates a command that runs a command line with the platform's shell.
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    }
+}
@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Displays build progress on stderr, either as a progress bar or as machine-readable events.
+
+use std::time::Duration;
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use newtabgen::{Phase, Progress};
+use serde_json::json;
+use tokio::{sync::mpsc::UnboundedReceiver, task::JoinHandle};
+
+/// Spawns a task that draws a progress bar for each running phase, driven by the received
+/// [`Progress`] events. Phases can run concurrently, so each gets its own bar, which is cleared
+/// once the phase finishes. The task finishes and clears any remaining bars once all senders have
+/// been dropped.
+pub(crate) fn spawn_progress_bar(mut receiver: UnboundedReceiver<Progress>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let style = ProgressStyle::with_template("{spinner} {msg:<10} [{bar:30}] {pos}/{len}")
+            .expect("invalid progress bar template")
+            .progress_chars("=> ");
+        let multi = MultiProgress::new();
+        let mut bars = Vec::<(Phase, ProgressBar)>::new();
+        let bar_for = |phase: Phase, bars: &mut Vec<(Phase, ProgressBar)>| {
+            if let Some((_, bar)) = bars.iter().find(|(p, _)| *p == phase) {
+                return bar.clone();
+            }
+            let bar = multi.add(
+                ProgressBar::new(0)
+                    .with_style(style.clone())
+                    .with_message(phase.to_string()),
+            );
+            bar.enable_steady_tick(Duration::from_millis(100));
+            bars.push((phase, bar.clone()));
+            bar
+        };
+
+        while let Some(event) = receiver.recv().await {
+            match event {
+                Progress::PhaseStarted(phase) => {
+                    bar_for(phase, &mut bars);
+                }
+                Progress::Item {
+                    phase,
+                    current,
+                    total,
+                } => {
+                    let bar = bar_for(phase, &mut bars);
+                    bar.set_length(total as u64);
+                    bar.set_position(current as u64);
+                }
+                Progress::PhaseFinished(phase) => {
+                    if let Some(i) = bars.iter().position(|(p, _)| *p == phase) {
+                        bars.remove(i).1.finish_and_clear();
+                    }
+                }
+                Progress::Finished { .. } => {}
+            }
+        }
+
+        for (_, bar) in bars {
+            bar.finish_and_clear();
+        }
+    })
+}
+
+/// Spawns a task that writes each received [`Progress`] event as a line of JSON (NDJSON). The task
+/// finishes once all senders have been dropped.
+///
+/// Each event is an object with an `event` key, one of `phase_started`, `item`,
+/// `phase_finished`, or `finished`, along with the event's fields.
+pub(crate) fn spawn_progress_json(mut receiver: UnboundedReceiver<Progress>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        while let Some(event) = receiver.recv().await {
+            let line = match event {
+                Progress::PhaseStarted(phase) => {
+                    json!({ "event": "phase_started", "phase": phase.as_str() })
+                }
+                Progress::Item {
+                    phase,
+                    current,
+                    total,
+                } => json!({
+                    "event": "item",
+                    "phase": phase.as_str(),
+                    "current": current,
+                    "total": total,
+                }),
+                Progress::PhaseFinished(phase) => {
+                    json!({ "event": "phase_finished", "phase": phase.as_str() })
+                }
+                Progress::Finished { output_bytes } => {
+                    json!({ "event": "finished", "output_bytes": output_bytes })
+                }
+            };
+            eprintln!("{line}");
+        }
+    })
+}
@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Implements the `selftest` subcommand, which builds every built-in example to confirm the
+//! templates, styles, and icon sources still work together.
+
+use std::{fs, io, process};
+
+use newtabgen::{
+    resources::{Resources, EXAMPLES},
+    BuildOptions,
+};
+use tracing::{error, info};
+
+use crate::args::SelftestArgs;
+
+/// Builds every built-in example config with the default templates, logging the outcome of each
+/// build. Unless a directory is provided, pages are built in a temporary directory, which is
+/// removed afterwards unless `args.keep` is set or a build failed, so the pages can be inspected.
+///
+/// # Errors
+///
+/// Returns an error if creating or removing the temporary directory fails.
+///
+/// # Returns
+///
+/// `true` if every example built successfully.
+pub(crate) async fn selftest(args: SelftestArgs) -> io::Result<bool> {
+    let temporary = args.dir.is_none();
+    let dir = args.dir.unwrap_or_else(|| {
+        std::env::temp_dir().join(format!("newtabgen-selftest-{}", process::id()))
+    });
+    fs::create_dir_all(&dir)?;
+
+    let mut passed = true;
+    for example in EXAMPLES {
+        let resources = Resources {
+            example: Some(example.name.into()),
+            ..Default::default()
+        };
+        let path = dir.join(format!("{}.html", example.name));
+        match newtabgen::build_to_path(resources, &BuildOptions::default(), &path).await {
+            Ok(report) => info!(
+                example = example.name,
+                elapsed_ms = report.elapsed_ms,
+                output_bytes = report.output_bytes,
+                "example built"
+            ),
+            Err(e) => {
+                error!(
+                    example = example.name,
+                    error = format!("{}", e),
+                    "example failed to build"
+                );
+                passed = false;
+            }
+        }
+    }
+
+    if temporary && passed && !args.keep {
+        fs::remove_dir_all(&dir)?;
+    } else {
+        info!(dir = dir.to_str(), "kept built examples");
+    }
+    Ok(passed)
+}
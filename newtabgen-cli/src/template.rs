@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Implements the `template` subcommand, which manages installed template packs.
+
+use newtabgen::template_store::{self, TemplateStoreError};
+use tracing::info;
+
+use crate::args::TemplateCommand;
+
+/// Runs a `template` subcommand.
+///
+/// # Errors
+///
+/// Returns an error if installing, listing, or removing template packs fails.
+pub(crate) async fn template(command: TemplateCommand) -> Result<(), TemplateStoreError> {
+    match command {
+        TemplateCommand::Install(args) => {
+            let pack = template_store::install(
+                &args.source,
+                args.sha256.as_deref(),
+                args.name.as_deref(),
+                args.force,
+            )
+            .await?;
+            info!(
+                path = pack.path.to_str(),
+                "installed template pack, build with: --template {}", pack.name
+            );
+        }
+        TemplateCommand::List => {
+            for pack in template_store::list()? {
+                let description = pack.manifest.description.unwrap_or_default();
+                println!("{:<14} {}", pack.name, description);
+            }
+        }
+        TemplateCommand::Remove(args) => {
+            template_store::remove(&args.name)?;
+            info!(name = args.name, "removed template pack");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,102 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Implements the `verify` subcommand, which rebuilds a page and compares it to the original.
+
+use std::{fs, sync::Arc};
+
+use newtabgen::{
+    config::BuildLayout,
+    resources::{LayoutResources, ProfileResources, Resources},
+    BuildOptions, CacheMode, LocalIconFetcher, Provenance,
+};
+use tracing::{error, info, warn};
+
+use crate::{args::VerifyArgs, error::CliError};
+
+/// Rebuilds the page described by `args` reproducibly with provenance enabled, using the same
+/// options that affect the output as the original build, and compares the result to the existing
+/// page, logging any differences found in the provenance records.
+///
+/// # Errors
+///
+/// Returns an error if reading the existing page or rebuilding it fails.
+///
+/// # Returns
+///
+/// `true` if the rebuilt page is byte-identical to the existing page.
+pub(crate) async fn verify(args: VerifyArgs) -> Result<bool, CliError> {
+    let original = fs::read(&args.page).map_err(|e| CliError::PageRead(e, args.page.clone()))?;
+    let Some(expected) = Provenance::from_html(&original) else {
+        error!("page has no provenance comment, rebuild it with --provenance");
+        return Ok(false);
+    };
+
+    let theme = match &args.theme {
+        Some(theme) => Some(crate::load_theme(theme, CacheMode::PreferCache).await?),
+        None => None,
+    };
+    let resources = LayoutResources {
+        resources: Resources {
+            config: args.config,
+            config_format: None,
+            example: args.example,
+            template: args.template,
+            scss: args.scss,
+            html: args.html,
+            theme,
+        },
+        layout: args.mobile.then_some(BuildLayout::Mobile),
+    };
+    let mut options = BuildOptions {
+        target: args.target.as_build_target(),
+        // Neither pull the icons repository nor expire cached site icons, which would change the
+        // output
+        reproducible: true,
+        provenance: true,
+        minify: if args.pretty {
+            newtabgen::Minify::Pretty
+        } else {
+            args.minify.as_build_minify()
+        },
+        dedupe: args.dedupe,
+        tera_hooks: crate::plugins::hooks(&args.filters, &args.functions),
+        ..Default::default()
+    };
+    if let Some(dir) = &args.icon_dir {
+        options.icon_fetcher = Arc::new(LocalIconFetcher::new(dir));
+    }
+    let mut rebuilt = Vec::<u8>::new();
+    match args.profile {
+        Some(profile) => {
+            let resources = ProfileResources { resources, profile };
+            newtabgen::build(resources, &options, &mut rebuilt).await?
+        }
+        None => newtabgen::build(resources, &options, &mut rebuilt).await?,
+    };
+
+    if let Some(actual) = Provenance::from_html(&rebuilt) {
+        let fields = [
+            ("version", &expected.version, &actual.version),
+            ("config", &expected.config_hash, &actual.config_hash),
+            ("lockfile", &expected.lockfile_hash, &actual.lockfile_hash),
+            (
+                "icon_repo",
+                &expected.icon_repo_commit,
+                &actual.icon_repo_commit,
+            ),
+        ];
+        for (field, expected, actual) in fields {
+            if expected != actual {
+                warn!(field, expected, actual, "provenance differs");
+            }
+        }
+    }
+
+    if original == rebuilt {
+        info!("rebuilt page matches");
+        Ok(true)
+    } else {
+        error!("rebuilt page does not match");
+        Ok(false)
+    }
+}
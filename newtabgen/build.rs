@@ -0,0 +1,24 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Records a hash of the workspace lockfile, which is embedded in build provenance records.
+
+use std::{env, fs, path::PathBuf};
+
+use sha1::{Digest, Sha1};
+
+fn main() {
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let lockfile = manifest_dir.join("..").join("Cargo.lock");
+    println!("cargo:rerun-if-changed={}", lockfile.display());
+
+    let hash = match fs::read(&lockfile) {
+        Ok(bytes) => {
+            let mut hasher = Sha1::new();
+            hasher.update(bytes);
+            let hash_base32 = data_encoding::BASE32HEX_NOPAD.encode(&hasher.finalize());
+            hash_base32.to_lowercase()[..8].to_string()
+        }
+        Err(_) => "unknown".into(),
+    };
+    println!("cargo:rustc-env=NEWTABGEN_LOCKFILE_HASH={hash}");
+}
@@ -1,25 +1,77 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-//! Provides the `build` function.
+//! Provides the `build` function, and the [phases] it's made of.
 
+pub(crate) mod a11y;
+pub(crate) mod background;
+pub(crate) mod backup;
+pub(crate) mod debug_files;
+pub(crate) mod diagnostic;
+pub(crate) mod duplicates;
+pub(crate) mod embeds;
+pub(crate) mod feeds;
+pub(crate) mod icon_fetcher;
+pub(crate) mod limits;
+pub(crate) mod links_json;
+pub(crate) mod mixed_content;
+pub mod phases;
+pub(crate) mod plan;
+pub(crate) mod progress;
+pub(crate) mod provenance;
+pub(crate) mod pwa;
+pub(crate) mod rate_limit;
+pub(crate) mod render_cache;
+pub(crate) mod report;
+pub(crate) mod resolve;
+pub(crate) mod resource_hints;
+#[cfg(feature = "remote-icons")]
+pub(crate) mod robots;
 pub(crate) mod site_icons;
 pub(crate) mod svg_icons;
+pub(crate) mod theme_assets;
+pub(crate) mod theme_contrast;
+pub(crate) mod theme_schedule;
 
 use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    future::Future,
     io::{self, Write},
-    str::{from_utf8, Utf8Error},
+    path::{Path, PathBuf},
+    process,
+    str::Utf8Error,
+    sync::Arc,
+    time::{Duration, SystemTime},
 };
-use tera::{Context, Tera};
+use tera::Tera;
 use thiserror::Error;
-use tokio::time::Instant;
-use tracing::{debug, info, span, Level};
+use tokio::{sync::Notify, task, time::Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, span, warn, Level, Span};
 
 use crate::{
-    resources::{ResourceError, Resources},
-    tera_filters, tera_functions,
+    config::{BuildLayout, Config},
+    resources::{LayoutResources, ProfileResources, ResourceError, ResourceSet},
+    rewrite::RewriteError,
+    sort::SortError,
+    tera_ast,
 };
 
-use self::{site_icons::SiteIconError, svg_icons::SvgIconError};
+use self::{
+    background::BackgroundError,
+    diagnostic::{TemplateDiagnostic, HTML_TEMPLATE, SCSS_TEMPLATE},
+    feeds::FeedError,
+    icon_fetcher::IconFetcher,
+    links_json::LinksJson,
+    phases::{Artifacts, SiteIconCss},
+    progress::{Phase, Progress, ProgressSender},
+    provenance::Provenance,
+    pwa::PwaFiles,
+    report::{BuildReport, WarningKind},
+    resolve::ResolveOptions,
+    site_icons::SiteIconError,
+    svg_icons::SvgIconError,
+};
 
 /// Errors that may occur when building a new tab page.
 #[derive(Error, Debug)]
@@ -28,6 +80,11 @@ pub enum BuildError {
     #[error(transparent)]
     Output(#[from] io::Error),
 
+    /// Occurs when writing the built output to a file fails, such as when its directory doesn't
+    /// exist or isn't writable.
+    #[error("failed to write output @ {1} ({0})")]
+    OutputWrite(#[source] io::Error, PathBuf),
+
     /// Occurs when encoding to UTF-8 fails.
     #[error(transparent)]
     EncodeUtf8(#[from] Utf8Error),
@@ -36,13 +93,20 @@ pub enum BuildError {
     #[error("failed to load resource ({0})")]
     Resource(#[from] ResourceError),
 
-    /// Occurs when the template renderer encounters an error.
+    /// Occurs when the template renderer encounters an error outside of the HTML and SCSS
+    /// templates, such as when linting a template.
     #[error("failed to render template ({0})")]
     Template(#[from] tera::Error),
 
-    /// Occurs when the SCSS compiler encounters an error.
-    #[error("failed to compile scss ({0})")]
-    ScssCompile(#[from] rsass::Error),
+    /// Occurs when parsing or rendering the HTML or SCSS template fails. Also explains where in
+    /// the template it failed, see [`BuildError::diagnostic`].
+    #[error("failed to render template ({})", .1.summary())]
+    Render(#[source] tera::Error, Box<TemplateDiagnostic>),
+
+    /// Occurs when the SCSS compiler encounters an error. Also explains where in the rendered
+    /// SCSS it failed, and which template line that came from, see [`BuildError::diagnostic`].
+    #[error("failed to compile scss ({})", .1.summary())]
+    ScssCompile(#[source] rsass::Error, Box<TemplateDiagnostic>),
 
     /// Occurs when building the site icons fails.
     #[error("failed to build site icons ({0})")]
@@ -51,6 +115,338 @@ pub enum BuildError {
     /// Occurs when building the svg icons fails.
     #[error("failed to build svg icons ({0})")]
     SvgIcon(#[from] SvgIconError),
+
+    /// Occurs when loading the background image fails.
+    #[error("failed to load background image ({0})")]
+    Background(#[from] BackgroundError),
+
+    /// Occurs when building the feeds fails.
+    #[error("failed to build feeds ({0})")]
+    Feed(#[from] FeedError),
+
+    /// Occurs when theme colors have low contrast against the background and
+    /// [`BuildOptions::strict`] is set.
+    #[error("theme colors have low contrast: {0}")]
+    ThemeContrast(String),
+
+    /// Occurs when custom theme keys aren't used by any template and
+    /// [`BuildOptions::strict`] is set.
+    #[error("custom theme keys aren't used by any template: {0}")]
+    UnusedThemeKeys(String),
+
+    /// Occurs when a URL is linked more than once and [`BuildOptions::strict`] is set, unless
+    /// [`BuildOptions::dedupe`] is set.
+    #[error("links appear more than once: {0}")]
+    DuplicateLinks(String),
+
+    /// Occurs when an inline style, a data URL, or the output as a whole exceeds its size limit
+    /// and [`BuildOptions::strict`] is set.
+    #[error("output contains oversized content: {0}")]
+    Oversized(String),
+
+    /// Occurs when resources are loaded over plain HTTP by a page hosted over HTTPS and
+    /// [`BuildOptions::strict`] is set.
+    #[error("page hosted over https loads resources over http: {0}")]
+    MixedContent(String),
+
+    /// Occurs when [`BuildOptions::a11y_check`] finds accessibility problems in the output and
+    /// [`BuildOptions::strict`] is set.
+    #[error("output has accessibility problems: {0}")]
+    A11y(String),
+
+    /// Occurs when sorting the config fails.
+    #[error("failed to sort config ({0})")]
+    Sort(#[from] SortError),
+
+    /// Occurs when a time in `Theme.schedule` isn't in 24-hour "HH:MM" format.
+    #[error("invalid theme schedule time: '{0}', expected HH:MM")]
+    ThemeSchedule(String),
+
+    /// Occurs when an embed in `Section.embeds` is invalid.
+    #[error("invalid embed: {0}")]
+    Embed(String),
+
+    /// Occurs when `Config.default_page` doesn't match any page.
+    #[error("default page doesn't exist: {0}")]
+    DefaultPage(String),
+
+    /// Occurs when rewriting link URLs fails.
+    #[error("failed to rewrite link urls ({0})")]
+    Rewrite(#[from] RewriteError),
+
+    /// Occurs when serializing build data fails.
+    #[error("failed to serialize ({0})")]
+    Serialize(#[from] serde_json::Error),
+
+    /// Occurs when a build task panics or is aborted.
+    #[error("build task failed ({0})")]
+    Task(#[from] task::JoinError),
+
+    /// Occurs when the build is cancelled through [`BuildOptions::cancel`].
+    #[error("build cancelled")]
+    Cancelled,
+}
+
+impl BuildError {
+    /// Explains where in a template the build failed, for errors caused by the HTML or SCSS
+    /// template. Its [`Display`][std::fmt::Display] implementation shows the template source
+    /// around the error.
+    pub fn diagnostic(&self) -> Option<&TemplateDiagnostic> {
+        match self {
+            Self::Render(_, diagnostic) | Self::ScssCompile(_, diagnostic) => Some(diagnostic),
+            _ => None,
+        }
+    }
+}
+
+/// Kinds of output that can be built.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Target {
+    /// The interactive new tab page, rendered from the HTML template and styles.
+    #[default]
+    Page,
+
+    /// A plain HTML list of every link, using only inline styles, without scripts or icons.
+    /// Suitable for emailing or archiving a snapshot of the links. Only the config is used, and
+    /// provenance isn't embedded.
+    StaticList,
+}
+
+/// How cached downloads are used, for the icons repository, site icons, feeds, and remote
+/// resources alike.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Never use the network. Anything that isn't cached, or can't be used from the cache, fails
+    /// instead of being downloaded. Cached items never expire.
+    Offline,
+
+    /// Use cached items until they expire, downloading anything else.
+    #[default]
+    PreferCache,
+
+    /// Ignore cached items and download everything again, replacing the cached copies.
+    Refresh,
+}
+
+/// How aggressively the output is minified. Minification can also be turned off for HTML and CSS
+/// individually with `Build.minify_html` and `Build.minify_css` in the config.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Minify {
+    /// Skip minification entirely, emitting formatted HTML and CSS. Useful when debugging custom
+    /// templates.
+    Pretty,
+
+    /// Minify, but keep the output spec compliant and easy for other tools to parse. Closing
+    /// tags, optional opening tags, and quotes around attribute values are kept.
+    Safe,
+
+    /// Minify, omitting anything browsers don't need.
+    #[default]
+    Standard,
+
+    /// Also minify inline scripts and styles, and remove bangs and processing instructions.
+    Aggressive,
+}
+
+/// Default value for [`BuildOptions::max_style_bytes`].
+pub const DEFAULT_MAX_STYLE_BYTES: usize = 1024 * 1024;
+
+/// Default value for [`BuildOptions::max_data_url_bytes`].
+pub const DEFAULT_MAX_DATA_URL_BYTES: usize = 2 * 1024 * 1024;
+
+/// Default value for [`BuildOptions::icon_host_interval`].
+pub const DEFAULT_ICON_HOST_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Default value for [`BuildOptions::max_icon_connections`].
+pub const DEFAULT_MAX_ICON_CONNECTIONS: usize = 4;
+
+/// Registers additional filters, functions, and tests with the template renderer before each
+/// template is rendered, see [`BuildOptions::with_tera`].
+#[derive(Clone)]
+pub struct TeraHook(Arc<dyn Fn(&mut Tera) + Send + Sync>);
+
+impl TeraHook {
+    /// Wraps a function that registers additional template helpers.
+    pub fn new(hook: impl Fn(&mut Tera) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(hook))
+    }
+
+    /// Registers the additional template helpers with a template renderer.
+    pub fn apply(&self, tera: &mut Tera) {
+        (self.0)(tera);
+    }
+}
+
+impl fmt::Debug for TeraHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TeraHook(..)")
+    }
+}
+
+/// Options that change how a new tab page is built.
+#[derive(Debug, Clone)]
+pub struct BuildOptions {
+    /// Kind of output to build.
+    pub target: Target,
+
+    /// Indicates if problems that would otherwise only be reported, such as site icons with low
+    /// contrast, should fail the build.
+    pub strict: bool,
+
+    /// Indicates if the icons repository should be fetched even if the configured revision is
+    /// already available locally.
+    pub update_icons: bool,
+
+    /// Indicates if updating the icons repository should be skipped, using it as checked out.
+    pub skip_icon_update: bool,
+
+    /// Indicates if identical inputs should produce byte-identical output. Icons are emitted in a
+    /// stable order, the icons repository isn't pulled, and cached site icons never expire.
+    pub reproducible: bool,
+
+    /// Indicates if a [`Provenance`] record should be embedded in the output as an HTML comment.
+    pub provenance: bool,
+
+    /// Number of previous versions of the output file to keep when building with
+    /// [`build_to_path`]. If [`BuildOptions::backup_dir`] is provided, 0 keeps every version.
+    pub backups: usize,
+
+    /// Directory to keep previous versions of the output file in, instead of next to it.
+    pub backup_dir: Option<PathBuf>,
+
+    /// Where to write a [`LinksJson`] export of the resolved links, for other tools to consume.
+    /// Nothing is exported if not provided.
+    pub links_json: Option<PathBuf>,
+
+    /// Largest size, in bytes, of a single `<style>` block in the output before a warning is
+    /// reported. Some browsers slow down considerably when parsing very large inline styles.
+    pub max_style_bytes: usize,
+
+    /// Largest size, in bytes, of a single data URL in the output before a warning is reported.
+    /// Some browsers refuse to load data URLs beyond a few megabytes.
+    pub max_data_url_bytes: usize,
+
+    /// How cached downloads are used, see [`CacheMode`].
+    pub cache: CacheMode,
+
+    /// How aggressively the output is minified, see [`Minify`].
+    pub minify: Minify,
+
+    /// Indicates if rendered templates should be cached, keyed by a hash of the template and its
+    /// context. Rebuilds where only site icons or feeds expired then skip rendering entirely. The
+    /// cache isn't used while [`BuildOptions::tera_hooks`] are registered, since their helpers
+    /// can't be hashed.
+    pub render_cache: bool,
+
+    /// Directory to write the intermediate rendered SCSS, compiled CSS, and rendered HTML to before
+    /// they're minified, for debugging custom templates. The render cache isn't used while set, so
+    /// the files are always written.
+    pub debug_dir: Option<PathBuf>,
+
+    /// Indicates if links whose URL already appears earlier in the config should be dropped,
+    /// keeping the first occurrence. Duplicates are reported either way.
+    pub dedupe: bool,
+
+    /// Indicates if the output should be audited for accessibility problems, such as links with
+    /// only an icon and no label, and text colors with low contrast against their background.
+    pub a11y_check: bool,
+
+    /// Receives [`Progress`] events while building.
+    pub progress: ProgressSender,
+
+    /// Shortest time between two site icon requests to the same host. Icons for links on the same
+    /// website are fetched one after another, spaced out by this interval.
+    pub icon_host_interval: Duration,
+
+    /// Largest number of site icons fetched at the same time. 0 is treated as 1.
+    pub max_icon_connections: usize,
+
+    /// With [`CacheMode::Refresh`], icons downloaded after this time are considered refreshed
+    /// already and reused. [`build_profiles`] sets it to when it started, so icons shared by
+    /// several profiles are downloaded once.
+    pub refreshed_after: Option<SystemTime>,
+
+    /// Prefix of the file names of the web app manifest and service worker written by
+    /// [`build_to_path`], e.g. `work.webmanifest` and `work.sw.js` for `work`. Pages built into the
+    /// same directory need different prefixes. Defaults to `manifest.webmanifest` and `sw.js` if
+    /// not provided.
+    pub pwa_file_prefix: Option<String>,
+
+    /// Obtains site icons that aren't cached. Defaults to
+    /// [`RemoteIconFetcher`][crate::RemoteIconFetcher] if the `remote-icons` feature is enabled.
+    pub icon_fetcher: Arc<dyn IconFetcher>,
+
+    /// Register additional filters, functions, and tests with the template renderer, in order, so
+    /// templates can use helpers that aren't built in. Helpers registered with the name of a
+    /// built-in one replace it.
+    pub tera_hooks: Vec<TeraHook>,
+
+    /// Cancels the build when triggered. The current phase is abandoned as soon as possible and
+    /// nothing is written to the output.
+    pub cancel: CancellationToken,
+}
+
+impl Default for BuildOptions {
+    fn default() -> Self {
+        Self {
+            target: Target::Page,
+            strict: false,
+            update_icons: false,
+            skip_icon_update: false,
+            reproducible: false,
+            provenance: false,
+            backups: 0,
+            backup_dir: None,
+            links_json: None,
+            max_style_bytes: DEFAULT_MAX_STYLE_BYTES,
+            max_data_url_bytes: DEFAULT_MAX_DATA_URL_BYTES,
+            cache: CacheMode::default(),
+            minify: Minify::default(),
+            render_cache: true,
+            dedupe: false,
+            a11y_check: false,
+            debug_dir: None,
+            progress: ProgressSender::default(),
+            icon_host_interval: DEFAULT_ICON_HOST_INTERVAL,
+            max_icon_connections: DEFAULT_MAX_ICON_CONNECTIONS,
+            refreshed_after: None,
+            pwa_file_prefix: None,
+            #[cfg(feature = "remote-icons")]
+            icon_fetcher: Arc::new(icon_fetcher::RemoteIconFetcher::default()),
+            #[cfg(not(feature = "remote-icons"))]
+            icon_fetcher: Arc::new(icon_fetcher::DisabledIconFetcher),
+            tera_hooks: Vec::new(),
+            cancel: CancellationToken::new(),
+        }
+    }
+}
+
+impl BuildOptions {
+    /// Adds a hook that registers additional filters, functions, or tests with the template
+    /// renderer, see [`BuildOptions::tera_hooks`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use newtabgen::{tera::Value, BuildOptions};
+    ///
+    /// let options = BuildOptions::default().with_tera(|tera| {
+    ///     tera.register_filter("shout", |value: &Value, _: &_| {
+    ///         Ok(Value::from(value.as_str().unwrap_or_default().to_uppercase()))
+    ///     });
+    /// });
+    /// ```
+    #[must_use]
+    pub fn with_tera(mut self, hook: impl Fn(&mut Tera) + Send + Sync + 'static) -> Self {
+        self.tera_hooks.push(TeraHook::new(hook));
+        self
+    }
+
+    /// Indicates if rendered templates can be reused from and stored in the render cache, see
+    /// [`BuildOptions::render_cache`].
+    pub(crate) fn uses_render_cache(&self) -> bool {
+        self.render_cache && self.debug_dir.is_none() && self.tera_hooks.is_empty()
+    }
 }
 
 /**
@@ -58,137 +454,840 @@ Builds a new tab page.
 
 # Arguments
 
-* `resources` - External [resources][Resources] used to build the new tab page.
+* `resources` - External [resources][ResourceSet] used to build the new tab page.
+* `options` - [Options][BuildOptions] that change how the new tab page is built.
 * `output` - Where to write the build output. Generally stdout or a file.
 
 # Errors
 
 Returns an error if any step in the build process fails.
 
+# Returns
+
+A [report][BuildReport] summarizing the build.
+
 # Example
 
 ```rust
-use newtabgen::resources::Resources;
+use newtabgen::{resources::Resources, BuildOptions};
 
 #[tokio::main]
 async fn main() {
     let resources = Resources::default(); // Use example config and default templates
+    let options = BuildOptions::default();
     let mut output = std::io::stdout().lock();
-    let res = newtabgen::build(resources, &mut output).await;
+    let res = newtabgen::build(resources, &options, &mut output).await;
 }
 ```
 */
-pub async fn build(resources: Resources, output: &mut impl Write) -> Result<(), BuildError> {
+pub async fn build(
+    resources: impl ResourceSet,
+    options: &BuildOptions,
+    output: &mut impl Write,
+) -> Result<BuildReport, BuildError> {
     let _span = span!(Level::INFO, "build").entered();
+    let sw = Instant::now();
+    let mut report = BuildReport::default();
 
     // Load and preprocess resources
-    let config = resources.config()?;
+    let mut config = resolve_config(&resources)?;
+    report.layout = config.build.layout;
+
+    // The same link in several places is usually left over from reorganizing
+    let duplicate_links = duplicates::find(&config);
+    for item in &duplicate_links {
+        let locations = item.locations.join(", ");
+        warn!(
+            url = item.url,
+            locations,
+            deduped = options.dedupe,
+            "link appears more than once"
+        );
+        let fix = if options.dedupe {
+            "only the first is kept"
+        } else {
+            "remove the extras, or enable deduplication to keep only the first"
+        };
+        report.warning(
+            WarningKind::DuplicateLink,
+            format!("{} is linked in {locations}, {fix}", item.url),
+        );
+    }
+    if options.dedupe {
+        let dropped = duplicates::dedupe(&mut config);
+        debug!(dropped, "dropped duplicate links");
+    } else if options.strict && !duplicate_links.is_empty() {
+        let urls = duplicate_links
+            .iter()
+            .map(|d| d.url.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(BuildError::DuplicateLinks(urls));
+    }
+    report.duplicate_links = duplicate_links;
+
+    // Pages hosted over HTTPS can't load resources over plain HTTP
+    let hosted_securely = mixed_content::hosted_securely(&config);
+    let mut mixed_content = if hosted_securely {
+        mixed_content::inline_config(&mut config)
+    } else {
+        Vec::new()
+    };
+
+    if options.target == Target::StaticList {
+        let phase_sw = start_phase(options, Phase::Html)?;
+        let out_html = phases::render_static_list(&config)?.bytes;
+        finish_phase(
+            options,
+            &mut report,
+            Phase::Html,
+            phase_sw.elapsed(),
+            out_html.len(),
+        );
+        if options.cancel.is_cancelled() {
+            return Err(BuildError::Cancelled);
+        }
+        output.write_all(out_html.as_slice())?;
+        report.output_bytes = out_html.len();
+        report.elapsed_ms = sw.elapsed().as_millis();
+        options.progress.send(Progress::Finished {
+            output_bytes: report.output_bytes,
+        });
+        return Ok(report);
+    }
+
     let src_html = resources.html()?;
     let src_scss = resources.scss()?;
 
-    // Setup tera
-    let mut tera = Tera::default();
-    tera.register_filter("hash", tera_filters::Hash);
-    tera.register_filter("site_icon", tera_filters::SiteIcon);
-    tera.register_function("len", tera_functions::Len);
-    tera.register_function("svg_icon_href", tera_functions::SvgIconHref);
-    tera.register_function(
-        "count_links_in_page",
-        tera_functions::CountLinksInPage(config.clone()),
+    let template_names = template_names(&src_html, &src_scss)?;
+    let theme_assets = theme_assets::data_urls(&resources.theme_assets()?);
+    let artifacts = build_artifacts(
+        &config,
+        &src_scss,
+        &template_names,
+        theme_assets,
+        options,
+        &mut report,
+    )
+    .await?;
+
+    // Text and accents must stay readable against the background
+    let low_contrast_colors =
+        theme_contrast::check(&config, artifacts.background.derived_colors.as_ref());
+    for item in &low_contrast_colors {
+        warn!(
+            pair = item.pair.as_str(),
+            foreground = item.foreground,
+            background = item.background,
+            contrast = item.contrast,
+            minimum = item.minimum,
+            "theme color has low contrast against the background"
+        );
+        report.warning(
+            WarningKind::LowContrastColor,
+            format!(
+                "{} color {} has low contrast against the background {} ({:.2}:1, at least \
+                 {:.2}:1 is needed)",
+                item.pair.as_str(),
+                item.foreground,
+                item.background,
+                item.contrast,
+                item.minimum
+            ),
+        );
+    }
+    if options.strict && !low_contrast_colors.is_empty() {
+        let summary = low_contrast_colors
+            .iter()
+            .map(|item| {
+                format!(
+                    "{} ({} on {}, {:.2}:1)",
+                    item.pair.as_str(),
+                    item.foreground,
+                    item.background,
+                    item.contrast
+                )
+            })
+            .collect::<Vec<_>>();
+        return Err(BuildError::ThemeContrast(summary.join(", ")));
+    }
+    report.low_contrast_colors = low_contrast_colors;
+
+    // Build html
+    let phase_sw = start_phase(options, Phase::Html)?;
+    let out_html = phases::render_html_cached(&src_html, &config, &artifacts, options)?.bytes;
+    finish_phase(
+        options,
+        &mut report,
+        Phase::Html,
+        phase_sw.elapsed(),
+        out_html.len(),
     );
 
-    let mut context = Context::new();
-    context.insert("config", &config);
+    // Custom theme keys are only useful if a template reads them
+    let unused_theme_keys = unused_theme_keys(&config, &template_names);
+    for key in &unused_theme_keys {
+        warn!(
+            key,
+            "custom theme key isn't used by any template, check for typos"
+        );
+        report.warning(
+            WarningKind::UnusedThemeKey,
+            format!("custom theme key '{key}' isn't used by any template, check for typos"),
+        );
+    }
+    if options.strict && !unused_theme_keys.is_empty() {
+        return Err(BuildError::UnusedThemeKeys(unused_theme_keys.join(", ")));
+    }
+    report.unused_theme_keys = unused_theme_keys;
 
-    // Build svg icon svg symbol defs
-    let out_svg_icons = svg_icons::build_svg_icons(&config)?;
-    context.insert("include_svg_icons", &out_svg_icons);
+    // Very large inline content renders slowly, or not at all, in some browsers
+    let limit = |config_limit: Option<usize>, option_limit: usize| {
+        config_limit.map_or(option_limit, |l| l.min(option_limit))
+    };
+    let oversized = limits::oversized(
+        &out_html,
+        limit(config.build.max_style_bytes, options.max_style_bytes),
+        limit(config.build.max_data_url_bytes, options.max_data_url_bytes),
+        config.build.max_output_bytes,
+    );
+    for item in &oversized {
+        warn!(
+            kind = item.kind.as_str(),
+            bytes = item.bytes,
+            limit = item.limit,
+            suggestion = item.kind.suggestion(),
+            "content exceeds size limit, some browsers may struggle to render it"
+        );
+        report.warning(
+            WarningKind::Oversized,
+            format!(
+                "{} of {} bytes exceeds the limit of {} bytes, some browsers may struggle to \
+                 render it: {}",
+                item.kind.as_str(),
+                item.bytes,
+                item.limit,
+                item.kind.suggestion()
+            ),
+        );
+    }
+    if options.strict && !oversized.is_empty() {
+        let summary = oversized
+            .iter()
+            .map(|item| format!("{} of {} bytes", item.kind.as_str(), item.bytes))
+            .collect::<Vec<_>>();
+        return Err(BuildError::Oversized(summary.join(", ")));
+    }
+    report.oversized = oversized;
 
-    // Build site icon css styles
-    let out_site_icons = site_icons::build_site_icons(&config, 24).await?;
-    context.insert("include_site_icons", &out_site_icons);
+    if hosted_securely {
+        let found = mixed_content::scan_output(&out_html, &mixed_content);
+        mixed_content.extend(found);
+    }
+    for item in &mixed_content {
+        if item.inlined {
+            debug!(
+                kind = item.kind.as_str(),
+                url = item.url,
+                "embedding resource that would be loaded insecurely"
+            );
+        } else {
+            warn!(
+                kind = item.kind.as_str(),
+                url = item.url,
+                suggestion = item.kind.suggestion(),
+                "resource is loaded over http by a page hosted over https"
+            );
+            report.warning(
+                WarningKind::MixedContent,
+                format!(
+                    "{} {} is loaded over http by a page hosted over https: {}",
+                    item.kind.as_str(),
+                    item.url,
+                    item.kind.suggestion()
+                ),
+            );
+        }
+    }
+    let insecure = mixed_content
+        .iter()
+        .filter(|item| !item.inlined)
+        .map(|item| item.url.as_str())
+        .collect::<Vec<_>>();
+    if options.strict && !insecure.is_empty() {
+        return Err(BuildError::MixedContent(insecure.join(", ")));
+    }
+    report.mixed_content = mixed_content;
 
-    // Build css
-    let out_css = build_css(src_scss, &mut tera, &context)?;
-    context.insert("include_styles", &out_css);
+    if options.a11y_check {
+        let a11y_issues = a11y::check(&out_html);
+        for item in &a11y_issues {
+            warn!(
+                kind = item.kind.as_str(),
+                subject = item.subject,
+                contrast = item.contrast,
+                suggestion = item.kind.suggestion(),
+                "output has an accessibility problem"
+            );
+            report.warning(
+                WarningKind::A11y,
+                format!(
+                    "{} ({}): {}",
+                    item.kind.as_str(),
+                    item.subject,
+                    item.kind.suggestion()
+                ),
+            );
+        }
+        if options.strict && !a11y_issues.is_empty() {
+            let summary = a11y_issues
+                .iter()
+                .map(|item| format!("{} ({})", item.kind.as_str(), item.subject))
+                .collect::<Vec<_>>();
+            return Err(BuildError::A11y(summary.join(", ")));
+        }
+        report.a11y_issues = a11y_issues;
+    }
 
-    // Build html
-    let out_html = build_html(src_html, &mut tera, &context)?;
+    // Last chance to cancel before anything is written
+    if options.cancel.is_cancelled() {
+        return Err(BuildError::Cancelled);
+    }
+
+    if let Some(path) = &options.links_json {
+        let links_json = LinksJson::new(&config, &artifacts.site_icons.data_urls);
+        debug!(path = path.to_str(), "writing links json");
+        tokio::fs::write(path, serde_json::to_vec_pretty(&links_json)?).await?;
+    }
 
     output.write_all(out_html.as_slice())?;
-    Ok(())
+    report.output_bytes = out_html.len();
+
+    if options.provenance {
+        let provenance = Provenance::collect(&config)?.to_string();
+        debug!(provenance, "embedding provenance");
+        output.write_all(provenance.as_bytes())?;
+        report.output_bytes += provenance.len();
+    }
+
+    report.elapsed_ms = sw.elapsed().as_millis();
+    options.progress.send(Progress::Finished {
+        output_bytes: report.output_bytes,
+    });
+    Ok(report)
 }
 
-/// Renders the SCSS template, then compiles the rendered SCSS into minified CSS.
+/// Builds everything the HTML template is rendered with, without rendering it. Useful for
+/// writing custom templates, since it shows exactly which values are available.
 ///
 /// # Arguments
 ///
-/// * `src_scss` - The SCSS template to compile.
-/// * `tera` - The template renderer to use.
-/// * `ctx` - The build context, used to provide information to the template.
+/// * `resources` - External [resources][ResourceSet] used to build the new tab page.
+/// * `options` - [Options][BuildOptions] that change how the new tab page is built.
 ///
 /// # Errors
 ///
-/// Returns an error if rendering the template, compiling the rendered SCSS, or encoding the
-/// compiled CSS into UTF-8 fails.
+/// Returns an error if any phase before rendering the HTML template fails.
 ///
 /// # Returns
 ///
-/// Minified CSS.
-fn build_css(src_scss: String, tera: &mut Tera, ctx: &Context) -> Result<String, BuildError> {
-    let _span = span!(Level::INFO, "css").entered();
-    info!("building css");
-    let sw = Instant::now();
+/// The template context, as JSON.
+pub async fn template_context(
+    resources: impl ResourceSet,
+    options: &BuildOptions,
+) -> Result<serde_json::Value, BuildError> {
+    let config = resolve_config(&resources)?;
+    let src_html = resources.html()?;
+    let src_scss = resources.scss()?;
+    let template_names = template_names(&src_html, &src_scss)?;
+    let theme_assets = theme_assets::data_urls(&resources.theme_assets()?);
+    let mut report = BuildReport::default();
+    let artifacts = build_artifacts(
+        &config,
+        &src_scss,
+        &template_names,
+        theme_assets,
+        options,
+        &mut report,
+    )
+    .await?;
+    let pwa_files = PwaFiles::new(options.pwa_file_prefix.as_deref());
+    Ok(phases::html_context(&config, &artifacts, &pwa_files).into_json())
+}
+
+/// Runs every phase needed before the HTML template can be rendered, recording each in the report.
+///
+/// # Arguments
+///
+/// * `config` - The resolved config.
+/// * `src_scss` - The SCSS template.
+/// * `template_names` - Every name referenced by the templates, see [`template_names`].
+/// * `theme_assets` - Data URLs of the files bundled with the theme, see [`theme_assets`].
+/// * `options` - Options that change how the artifacts are built.
+/// * `report` - The report to record each phase in.
+///
+/// # Errors
+///
+/// Returns an error if any phase fails, or the build is cancelled.
+async fn build_artifacts(
+    config: &Config,
+    src_scss: &str,
+    template_names: &HashSet<String>,
+    theme_assets: BTreeMap<String, String>,
+    options: &BuildOptions,
+    report: &mut BuildReport,
+) -> Result<Artifacts, BuildError> {
+    if options.reproducible && !config.widgets.feeds.is_empty() {
+        warn!("feeds are fetched live, output containing them may not be reproducible");
+        report.warning(
+            WarningKind::Unreproducible,
+            "feeds are fetched live, output containing them may not be reproducible".into(),
+        );
+    }
 
-    let format = rsass::output::Format {
-        style: rsass::output::Style::Compressed,
-        ..Default::default()
+    // Styles using `material_svg` read from the icons repo, so they must wait until it's ready
+    let svg_icons_ready = Notify::new();
+    let styles_need_svg_icons = src_scss.contains("material_svg");
+
+    let (svg_icons_used, site_icons_used) = used_icon_phases(src_scss, template_names);
+    let (skip_svg_icons, skip_site_icons) = (!svg_icons_used, !site_icons_used);
+    if skip_svg_icons {
+        debug!("svg icons aren't used by any template, skipping phase");
+    }
+    if skip_site_icons {
+        debug!("site icons aren't used by any template, skipping phase");
+    }
+
+    // Build svg icon svg symbol defs. Cloning or pulling the icons repo blocks, so it runs on a
+    // separate thread.
+    let svg_icons_phase = async {
+        if skip_svg_icons {
+            return Ok((Default::default(), Duration::ZERO));
+        }
+        let phase_sw = start_phase(options, Phase::SvgIcons)?;
+        let (config, task_options, span) = (config.clone(), options.clone(), Span::current());
+        let task = task::spawn_blocking(move || {
+            let _span = span.entered();
+            phases::build_svg_icons(&config, &task_options)
+        });
+        let out = cancellable(options, task).await??;
+        svg_icons_ready.notify_one();
+        options
+            .progress
+            .send(Progress::PhaseFinished(Phase::SvgIcons));
+        Ok((out, phase_sw.elapsed()))
     };
-    let rendered = tera
-        .render_str(src_scss.as_str(), ctx)
-        .map_err(BuildError::Template)?;
-    let compiled =
-        rsass::compile_scss(rendered.as_bytes(), format).map_err(BuildError::ScssCompile)?;
-    let encoded = from_utf8(compiled.as_slice()).map_err(BuildError::EncodeUtf8)?;
-
-    debug!(
-        elapsed_ms = sw.elapsed().as_millis(),
-        "finished building css"
-    );
-    Ok(format!("<style>{encoded}</style>"))
+
+    // Build site icon css styles
+    let site_icons_phase = run_phase(options, Phase::SiteIcons, async {
+        if skip_site_icons {
+            return Ok(SiteIconCss::default());
+        }
+        let (html, data_urls) =
+            site_icons::build_site_icons(config, config.build.icon_size, options, report).await?;
+        Ok::<_, BuildError>(SiteIconCss {
+            html,
+            data_urls,
+            ..Default::default()
+        })
+    });
+
+    // Fetch feed items
+    let feeds_phase = run_phase(options, Phase::Feeds, phases::build_feeds(config, options));
+
+    // Load the background image, then build css, which only depends on it (and the icons repo,
+    // if needed)
+    let styles_phase = async {
+        let (background, background_elapsed) = run_phase(
+            options,
+            Phase::Background,
+            phases::build_background(config, options),
+        )
+        .await?;
+
+        if styles_need_svg_icons {
+            svg_icons_ready.notified().await;
+        }
+        let phase_sw = start_phase(options, Phase::Css)?;
+        let css = phases::render_css_cached(src_scss, config, &background, &theme_assets, options)?;
+        // Scheduled themes switch variants in the browser, so both must be compiled
+        let alternate_css = match config.theme.schedule {
+            Some(_) => {
+                let mut alternate = config.clone();
+                alternate.theme.dark = !config.theme.dark;
+                Some(phases::render_css_cached(
+                    src_scss,
+                    &alternate,
+                    &background,
+                    &theme_assets,
+                    options,
+                )?)
+            }
+            None => None,
+        };
+        options.progress.send(Progress::PhaseFinished(Phase::Css));
+        Ok((
+            background,
+            background_elapsed,
+            css,
+            alternate_css,
+            phase_sw.elapsed(),
+        ))
+    };
+
+    // The phases above are independent, so run them concurrently
+    let (
+        (svg_icons, svg_icons_elapsed),
+        (site_icons, site_icons_elapsed),
+        (feeds, feeds_elapsed),
+        (background, background_elapsed, css, alternate_css, css_elapsed),
+    ) = tokio::try_join!(svg_icons_phase, site_icons_phase, feeds_phase, styles_phase)?;
+    let artifacts = Artifacts {
+        svg_icons,
+        site_icons,
+        feeds,
+        background,
+        css,
+        alternate_css,
+        theme_assets,
+    };
+
+    let finished_phases = [
+        (
+            Phase::SvgIcons,
+            svg_icons_elapsed,
+            artifacts.svg_icons.html.len(),
+        ),
+        (
+            Phase::SiteIcons,
+            site_icons_elapsed,
+            artifacts.site_icons.html.len(),
+        ),
+        (Phase::Feeds, feeds_elapsed, 0),
+        (Phase::Background, background_elapsed, 0),
+        (Phase::Css, css_elapsed, artifacts.css.css.len()),
+    ];
+    for (phase, elapsed, output_bytes) in finished_phases {
+        let skipped = match phase {
+            Phase::SvgIcons => skip_svg_icons,
+            Phase::SiteIcons => skip_site_icons,
+            _ => false,
+        };
+        if !skipped {
+            report.phase(phase, elapsed, output_bytes);
+        }
+    }
+
+    Ok(artifacts)
+}
+
+/// Checks which icon phases are needed. Icon phases are skipped if no template includes their
+/// output, e.g. text-only templates.
+///
+/// # Returns
+///
+/// Whether SVG icons and site icons are used, in that order.
+fn used_icon_phases(src_scss: &str, template_names: &HashSet<String>) -> (bool, bool) {
+    let styles_need_svg_icons = src_scss.contains("material_svg");
+    let html_needs_svg_icons =
+        template_names.contains("include_svg_icons") || template_names.contains("material_svg");
+    (
+        styles_need_svg_icons || html_needs_svg_icons,
+        template_names.contains("include_site_icons"),
+    )
 }
 
-/// Renders the HTML template, then minifies the rendered HTML.
+/// Loads the config and resolves it as [`build`] does, see [`Config::resolve`].
+///
+/// # Errors
+///
+/// Returns an error if loading or resolving the config fails.
+pub fn resolve_config(resources: &impl ResourceSet) -> Result<Config, BuildError> {
+    let config = resources.config()?.resolve(&ResolveOptions::default())?;
+    Ok(config.into_inner())
+}
+
+/// Builds a new tab page and writes it to a file. Nothing is written unless the build succeeds, and
+/// the file is replaced atomically, so an existing page is never left empty or partially written.
+/// The existing page is backed up first if requested by [`BuildOptions::backups`] or
+/// [`BuildOptions::backup_dir`].
+///
+/// If `Config.app` is provided, a web app manifest and service worker are written next to the
+/// page, so it can be installed as a progressive web app.
+///
+/// If `Build.mobile_variant` is set and the page is built for desktop, a mobile variant is built
+/// too, and written next to the page (see [`mobile_variant_path`]).
 ///
 /// # Arguments
 ///
-/// * `src_html` - The HTML template to compile.
-/// * `tera` - The template renderer to use.
-/// * `ctx` - The build context, used to provide information to the template.
+/// * `resources` - External [resources][ResourceSet] used to build the new tab page.
+/// * `options` - [Options][BuildOptions] that change how the new tab page is built.
+/// * `path` - The file to write the new tab page to.
 ///
 /// # Errors
 ///
-/// Returns an error if rendering the template fails.
+/// Returns an error if building fails, or if writing the file fails.
 ///
 /// # Returns
 ///
-/// Minified HTML.
-fn build_html(src_html: String, tera: &mut Tera, ctx: &Context) -> Result<Vec<u8>, BuildError> {
-    let _span = span!(Level::INFO, "html").entered();
-    info!("building html");
-    let sw = Instant::now();
+/// A [report][BuildReport] summarizing the build.
+pub async fn build_to_path(
+    resources: impl ResourceSet,
+    options: &BuildOptions,
+    path: impl AsRef<Path>,
+) -> Result<BuildReport, BuildError> {
+    let path = path.as_ref();
+    let config = resources.config()?;
+    let mut output = Vec::new();
+    let mut report = build(&resources, options, &mut output).await?;
+    write_output(path, &output, options).await?;
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("newtab");
 
-    let rendered = tera
-        .render_str(src_html.as_str(), ctx)
-        .map_err(BuildError::Template)?;
-    let cfg = &minify_html::Cfg::default();
-    let minified = minify_html::minify(rendered.as_bytes(), cfg);
+    // Installable pages also need a manifest and service worker next to them
+    let manifest = match options.target {
+        Target::Page => pwa::manifest(&config, file_name),
+        Target::StaticList => None,
+    };
+    if let Some(manifest) = manifest {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let pwa_files = PwaFiles::new(options.pwa_file_prefix.as_deref());
+        let manifest_path = dir.join(&pwa_files.manifest);
+        debug!(path = manifest_path.to_str(), "writing web app manifest");
+        tokio::fs::write(manifest_path, serde_json::to_vec_pretty(&manifest)?).await?;
+        let service_worker_path = dir.join(&pwa_files.service_worker);
+        debug!(
+            path = service_worker_path.to_str(),
+            "writing service worker"
+        );
+        let service_worker = pwa::service_worker(&output, file_name, &pwa_files);
+        tokio::fs::write(service_worker_path, service_worker).await?;
+    }
 
-    debug!(
-        elapsed_ms = sw.elapsed().as_millis(),
-        "finished building html"
-    );
-    Ok(minified)
+    let build_mobile_variant = config.build.mobile_variant
+        && report.layout == BuildLayout::Desktop
+        && options.target == Target::Page;
+    if build_mobile_variant {
+        let mobile_path = mobile_variant_path(path);
+        debug!(path = mobile_path.to_str(), "building mobile variant");
+        let mobile = LayoutResources {
+            resources: &resources,
+            layout: Some(BuildLayout::Mobile),
+        };
+        let mut mobile_output = Vec::new();
+        let mobile_report = build(mobile, options, &mut mobile_output).await?;
+        write_output(&mobile_path, &mobile_output, options).await?;
+        report.mobile_variant = Some(Box::new(mobile_report));
+    }
+
+    Ok(report)
+}
+
+/// Finds where the mobile variant of a page is written, which is next to the page and named after
+/// it with a `.mobile` suffix, e.g. `newtab.mobile.html` for `newtab.html`.
+pub fn mobile_variant_path(path: impl AsRef<Path>) -> PathBuf {
+    suffixed_path(path.as_ref(), "mobile")
+}
+
+/// Inserts a suffix between the stem and extension of a file name, e.g. `newtab.mobile.html` for
+/// `newtab.html` and `mobile`.
+fn suffixed_path(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("newtab");
+    let file_name = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{stem}.{suffix}.{ext}"),
+        None => format!("{stem}.{suffix}"),
+    };
+    path.with_file_name(file_name)
 }
 
-// fn build_css()
+/// Writes built output to a file atomically, backing up the existing file first if requested by
+/// the options.
+///
+/// # Errors
+///
+/// Returns an error if writing, backing up, or renaming fails. The temporary file is removed.
+async fn write_output(
+    path: &Path,
+    output: &[u8],
+    options: &BuildOptions,
+) -> Result<(), BuildError> {
+    // Write next to the destination, so renaming never crosses file systems
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("newtab");
+    let temp_path = path.with_file_name(format!(".{file_name}.{}.tmp", process::id()));
+    debug!(path = temp_path.to_str(), "writing temporary output file");
+    let written = async {
+        tokio::fs::write(&temp_path, output).await?;
+        if options.backups > 0 || options.backup_dir.is_some() {
+            backup::backup(path, options.backups, options.backup_dir.as_deref()).await?;
+        }
+        tokio::fs::rename(&temp_path, path).await
+    }
+    .await;
+    if let Err(e) = written {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        return Err(BuildError::OutputWrite(e, path.into()));
+    }
+    Ok(())
+}
+
+/// Builds every profile in the config (see `Config.profiles`) with [`build_to_path`], each to a
+/// file in `dir` named after the profile, e.g. `work.html`. Profiles are built one after another,
+/// so site icons fetched for one profile are read from the cache by the next, even with
+/// [`CacheMode::Refresh`]. Files written next to each page are named after the profile too, such
+/// as `work.webmanifest`, and `links.work.json` for [`BuildOptions::links_json`].
+///
+/// # Arguments
+///
+/// * `resources` - External [resources][ResourceSet] used to build the new tab pages.
+/// * `options` - [Options][BuildOptions] that change how the new tab pages are built.
+/// * `dir` - The directory to write the new tab pages to.
+///
+/// # Errors
+///
+/// Returns an error if loading the config fails, or if building any profile fails. Profiles built
+/// before the failure are kept.
+///
+/// # Returns
+///
+/// A [report][BuildReport] for each profile, in the order they were built.
+pub async fn build_profiles(
+    resources: impl ResourceSet,
+    options: &BuildOptions,
+    dir: impl AsRef<Path>,
+) -> Result<Vec<BuildReport>, BuildError> {
+    let names = resources.config()?.profiles.into_keys().collect::<Vec<_>>();
+    let refreshed_after = options.refreshed_after.unwrap_or_else(SystemTime::now);
+    let mut reports = Vec::with_capacity(names.len());
+    for name in names {
+        let path = dir.as_ref().join(format!("{name}.html"));
+        debug!(profile = name, path = path.to_str(), "building profile");
+        let profile = ProfileResources {
+            resources: &resources,
+            profile: name.clone(),
+        };
+        let profile_options = BuildOptions {
+            links_json: options
+                .links_json
+                .as_deref()
+                .map(|path| suffixed_path(path, &name)),
+            refreshed_after: Some(refreshed_after),
+            pwa_file_prefix: Some(name.clone()),
+            ..options.clone()
+        };
+        let mut report = build_to_path(profile, &profile_options, &path).await?;
+        report.profile = Some(name);
+        reports.push(report);
+    }
+    Ok(reports)
+}
+
+/// Announces the start of a build phase.
+///
+/// # Errors
+///
+/// Returns [`BuildError::Cancelled`] if the build has been cancelled.
+///
+/// # Returns
+///
+/// The time the phase started.
+fn start_phase(options: &BuildOptions, phase: Phase) -> Result<Instant, BuildError> {
+    if options.cancel.is_cancelled() {
+        return Err(BuildError::Cancelled);
+    }
+    options.progress.send(Progress::PhaseStarted(phase));
+    Ok(Instant::now())
+}
+
+/// Announces and runs a build phase, stopping early if the build is cancelled.
+///
+/// # Returns
+///
+/// The output of the phase, and the time spent running it.
+async fn run_phase<T, E>(
+    options: &BuildOptions,
+    phase: Phase,
+    fut: impl Future<Output = Result<T, E>>,
+) -> Result<(T, Duration), BuildError>
+where
+    BuildError: From<E>,
+{
+    let phase_sw = start_phase(options, phase)?;
+    let out = cancellable(options, fut).await?;
+    options.progress.send(Progress::PhaseFinished(phase));
+    Ok((out, phase_sw.elapsed()))
+}
+
+/// Runs a build phase until it completes or the build is cancelled, whichever happens first. If
+/// cancelled, the phase is dropped.
+async fn cancellable<T, E>(
+    options: &BuildOptions,
+    phase: impl Future<Output = Result<T, E>>,
+) -> Result<T, BuildError>
+where
+    BuildError: From<E>,
+{
+    tokio::select! {
+        biased;
+        _ = options.cancel.cancelled() => Err(BuildError::Cancelled),
+        res = phase => Ok(res?),
+    }
+}
+
+/// Collects every name referenced by the templates, see [`tera_ast::referenced_names`].
+///
+/// # Errors
+///
+/// Returns an error if a template can't be parsed.
+fn template_names(src_html: &str, src_scss: &str) -> Result<HashSet<String>, BuildError> {
+    let mut names = HashSet::new();
+    for (name, src) in [(HTML_TEMPLATE, src_html), (SCSS_TEMPLATE, src_scss)] {
+        let referenced = tera_ast::referenced_names(src).map_err(|e| {
+            let diagnostic = TemplateDiagnostic::tera(name, src, &e);
+            BuildError::Render(e, Box::new(diagnostic))
+        })?;
+        names.extend(referenced);
+    }
+    Ok(names)
+}
+
+/// Finds the custom theme keys in the config that aren't referenced by any of the templates.
+///
+/// # Arguments
+///
+/// * `config` - The config containing the custom theme keys.
+/// * `template_names` - Every name referenced by the templates, see [`template_names`].
+///
+/// # Returns
+///
+/// The unused keys, sorted.
+fn unused_theme_keys(config: &Config, template_names: &HashSet<String>) -> Vec<String> {
+    let mut unused = config
+        .theme
+        .custom
+        .keys()
+        .filter(|key| !template_names.contains(*key))
+        .cloned()
+        .collect::<Vec<String>>();
+    unused.sort_unstable();
+    unused
+}
+
+/// Announces the end of a build phase and records it in the report.
+fn finish_phase(
+    options: &BuildOptions,
+    report: &mut BuildReport,
+    phase: Phase,
+    elapsed: Duration,
+    output_bytes: usize,
+) {
+    report.phase(phase, elapsed, output_bytes);
+    options.progress.send(Progress::PhaseFinished(phase));
+}
@@ -0,0 +1,182 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Audits the rendered output for common accessibility problems, see
+//! [`BuildOptions::a11y_check`][super::BuildOptions::a11y_check].
+
+use std::collections::HashSet;
+
+use scraper::{ElementRef, Html, Selector};
+
+use crate::util::color;
+
+use super::report::{A11yIssueKind, A11yIssueReport};
+
+/// Minimum contrast ratio between the text and background colors of a CSS rule, from
+/// [WCAG 2.1 level AA][1] for normal text.
+///
+/// [1]: <https://www.w3.org/TR/WCAG21/#contrast-minimum>
+const MIN_CONTRAST: f32 = 4.5;
+
+/// Longest subject, in characters, before it's shortened in reports. Keeps data URLs readable.
+const MAX_SUBJECT_CHARS: usize = 80;
+
+impl A11yIssueKind {
+    /// Describes the problem in messages.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::UnlabeledLink => "link without text or label",
+            Self::MissingAlt => "image without alt text",
+            Self::LowContrast => "low contrast colors",
+        }
+    }
+
+    /// Suggests how to fix the problem.
+    pub(crate) fn suggestion(self) -> &'static str {
+        match self {
+            Self::UnlabeledLink => "give the link visible text, an aria-label, or a title",
+            Self::MissingAlt => "add an alt attribute, left empty if the image is decorative",
+            Self::LowContrast => "adjust the theme colors so text stands out from its background",
+        }
+    }
+}
+
+/// Finds accessibility problems in the output: links with only an icon and no accessible name,
+/// images without alt text, and CSS rules whose text and background colors have low contrast.
+///
+/// # Arguments
+///
+/// * `html` - The rendered output.
+///
+/// # Returns
+///
+/// Each problem found, links and images first, in the order they appear in the output.
+pub(crate) fn check(html: &[u8]) -> Vec<A11yIssueReport> {
+    let document = Html::parse_document(&String::from_utf8_lossy(html));
+    let mut reports = Vec::new();
+
+    for link in document.select(&selector("a")) {
+        if !hidden(link) && !labelled(link) {
+            reports.push(A11yIssueReport {
+                kind: A11yIssueKind::UnlabeledLink,
+                subject: shorten(link.value().attr("href").unwrap_or("<a>")),
+                contrast: None,
+            });
+        }
+    }
+
+    for img in document.select(&selector("img:not([alt])")) {
+        if !hidden(img) {
+            reports.push(A11yIssueReport {
+                kind: A11yIssueKind::MissingAlt,
+                subject: shorten(img.value().attr("src").unwrap_or("<img>")),
+                contrast: None,
+            });
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for style in document.select(&selector("style")) {
+        let css = style.text().collect::<String>();
+        for (rule, contrast) in low_contrast_rules(&css) {
+            if seen.insert(rule.clone()) {
+                reports.push(A11yIssueReport {
+                    kind: A11yIssueKind::LowContrast,
+                    subject: shorten(&rule),
+                    contrast: Some(contrast),
+                });
+            }
+        }
+    }
+
+    reports
+}
+
+/// Parses a selector that's known to be valid.
+fn selector(selector: &str) -> Selector {
+    Selector::parse(selector).expect("selector should be valid")
+}
+
+/// Checks if an element is hidden from assistive technology.
+fn hidden(element: ElementRef) -> bool {
+    element.value().attr("aria-hidden") == Some("true")
+}
+
+/// Checks if a link has an accessible name, from its text, an ARIA attribute, its title, or the
+/// alt text of an image inside it.
+fn labelled(link: ElementRef) -> bool {
+    let attr = |element: ElementRef, name| {
+        element
+            .value()
+            .attr(name)
+            .is_some_and(|value: &str| !value.trim().is_empty())
+    };
+    link.text().any(|text| !text.trim().is_empty())
+        || ["aria-label", "aria-labelledby", "title"]
+            .into_iter()
+            .any(|name| attr(link, name))
+        || link.select(&selector("img")).any(|img| attr(img, "alt"))
+}
+
+/// Shortens a subject to at most [`MAX_SUBJECT_CHARS`] characters.
+fn shorten(subject: &str) -> String {
+    let subject = subject.trim();
+    if subject.chars().count() <= MAX_SUBJECT_CHARS {
+        return subject.into();
+    }
+    let mut short = subject.chars().take(MAX_SUBJECT_CHARS).collect::<String>();
+    short.push_str("...");
+    short
+}
+
+/// Finds CSS rules that set both a text and a background color with low contrast between them.
+/// Rules nested in at-rules, such as `@media`, are checked as well. Colors that can't be resolved
+/// statically, such as translucent colors and custom properties, are ignored.
+///
+/// # Returns
+///
+/// Each rule found, formatted as its selector and colors, and the contrast ratio of its colors.
+fn low_contrast_rules(css: &str) -> Vec<(String, f32)> {
+    let mut rules = Vec::new();
+    let mut selector_start = 0;
+    let mut open = None;
+    for (i, c) in css.char_indices() {
+        match c {
+            '{' => {
+                open = Some((css[selector_start..i].trim(), i + 1));
+                selector_start = i + 1;
+            }
+            '}' => {
+                if let Some((selector, body_start)) = open.take() {
+                    if let Some(rule) = low_contrast_rule(selector, &css[body_start..i]) {
+                        rules.push(rule);
+                    }
+                }
+                selector_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    rules
+}
+
+/// Checks the contrast of a single CSS rule, see [`low_contrast_rules`].
+fn low_contrast_rule(selector: &str, body: &str) -> Option<(String, f32)> {
+    let mut fg = None;
+    let mut bg = None;
+    for declaration in body.split(';') {
+        let Some((property, value)) = declaration.split_once(':') else {
+            continue;
+        };
+        match property.trim() {
+            "color" => fg = Some(value.trim()),
+            "background" | "background-color" => bg = Some(value.trim()),
+            _ => {}
+        }
+    }
+    let (fg, bg) = (fg?, bg?);
+    let contrast = color::contrast_ratio(
+        color::relative_luminance(color::parse(fg)?),
+        color::relative_luminance(color::parse(bg)?),
+    );
+    (contrast < MIN_CONTRAST).then(|| (format!("{selector} ({fg} on {bg})"), contrast))
+}
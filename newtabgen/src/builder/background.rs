@@ -0,0 +1,235 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Manages loading the background image, embedding it in the page, and deriving theme colors from
+//! it.
+
+use std::collections::HashMap;
+
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage};
+use serde::Serialize;
+use thiserror::Error;
+use tokio::time::Instant;
+use tracing::{debug, info, instrument};
+
+use super::CacheMode;
+use crate::{
+    config::Config,
+    util::color::{hsl_to_rgb, rgb_to_hsl, to_hex},
+};
+
+/// Errors that may occur when loading the background image.
+#[derive(Error, Debug)]
+pub enum BackgroundError {
+    /// Occurs when reading a local background image fails.
+    #[error("failed to read background image @ {1} ({0})")]
+    Read(#[source] std::io::Error, String),
+
+    /// Occurs when downloading a remote background image fails.
+    #[error("failed to download background image: {1} ({0})")]
+    Request(#[source] reqwest::Error, String),
+
+    /// Occurs when decoding the background image fails.
+    #[error("failed to decode background image: {1} ({0})")]
+    Decode(#[source] image::ImageError, String),
+
+    /// Occurs when encoding the background image for embedding fails.
+    #[error("failed to encode background image: {1} ({0})")]
+    Encode(#[source] image::ImageError, String),
+
+    /// Occurs when the background image is a URL and [`CacheMode::Offline`] is used.
+    #[error("background image can't be downloaded while offline: {0}")]
+    Offline(String),
+}
+
+/// The background image, and colors derived from it.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Background {
+    /// URL of the background image, for use in CSS. This is a data URL if the image is embedded,
+    /// or the configured source otherwise.
+    pub image_url: Option<String>,
+
+    /// Colors derived from the background image, if requested by the config.
+    pub derived_colors: Option<DerivedColors>,
+}
+
+/// Colors derived from the background image, formatted as CSS hex colors.
+#[derive(Debug, Clone, Serialize)]
+pub struct DerivedColors {
+    /// Average color of the image.
+    pub average: String,
+
+    /// Most common color of the image.
+    pub dominant: String,
+
+    /// Saturated variant of the dominant color, readable against the theme's background.
+    pub accent: String,
+
+    /// Muted variant of the average color, suitable for layering content over the image.
+    pub overlay: String,
+}
+
+/// Loads the background image, then embeds it and derives colors from it, if requested by the
+/// config. The image is only loaded if needed.
+///
+/// # Errors
+///
+/// Returns an error if loading, decoding, or encoding the background image fails, or if it must be
+/// downloaded while [offline][CacheMode::Offline].
+///
+/// # Returns
+///
+/// The background, which is empty if no background image is configured.
+#[instrument(name = "background", skip_all)]
+pub async fn build_background(
+    config: &Config,
+    cache: CacheMode,
+) -> Result<Background, BackgroundError> {
+    let theme = &config.theme;
+    let Some(source) = &theme.background_image else {
+        return Ok(Background::default());
+    };
+    if !theme.background_embed && !theme.derive_colors {
+        return Ok(Background {
+            image_url: Some(source.clone()),
+            derived_colors: None,
+        });
+    }
+
+    info!("loading background image");
+    let sw = Instant::now();
+    let img = load_background_image(source, cache).await?;
+
+    let derived_colors = if theme.derive_colors {
+        let colors = derive_colors(&img, theme.dark);
+        debug!(?colors, "derived colors");
+        Some(colors)
+    } else {
+        None
+    };
+    let image_url = if theme.background_embed {
+        embed(
+            img,
+            source,
+            theme.background_max_width,
+            theme.background_quality,
+        )?
+    } else {
+        source.clone()
+    };
+
+    debug!(
+        elapsed_ms = sw.elapsed().as_millis(),
+        "finished loading background image"
+    );
+    Ok(Background {
+        image_url: Some(image_url),
+        derived_colors,
+    })
+}
+
+/// Downscales an image to at most `max_width` pixels wide, then encodes it as a JPEG data URL.
+fn embed(
+    img: DynamicImage,
+    source: &str,
+    max_width: u32,
+    quality: u8,
+) -> Result<String, BackgroundError> {
+    let img = if img.width() > max_width {
+        debug!(
+            from = img.width(),
+            to = max_width,
+            "downscaling background image"
+        );
+        // Resizing preserves the aspect ratio, so the height only needs to be large enough
+        img.resize(max_width, img.height(), FilterType::Lanczos3)
+    } else {
+        img
+    };
+
+    let mut buf = Vec::new();
+    JpegEncoder::new_with_quality(&mut buf, quality.clamp(1, 100))
+        .encode_image(&img.into_rgb8())
+        .map_err(|e| BackgroundError::Encode(e, source.into()))?;
+    debug!(bytes = buf.len(), "embedding background image");
+    Ok(format!(
+        "data:image/jpeg;base64,{}",
+        data_encoding::BASE64.encode(&buf)
+    ))
+}
+
+/// Loads and decodes a background image from a local file path or a URL.
+pub(crate) async fn load_background_image(
+    source: &str,
+    cache: CacheMode,
+) -> Result<DynamicImage, BackgroundError> {
+    let bytes = if source.starts_with("http://") || source.starts_with("https://") {
+        if cache == CacheMode::Offline {
+            return Err(BackgroundError::Offline(source.into()));
+        }
+        debug!(url = source, "downloading background image");
+        reqwest::get(source)
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| BackgroundError::Request(e, source.into()))?
+            .bytes()
+            .await
+            .map_err(|e| BackgroundError::Request(e, source.into()))?
+            .to_vec()
+    } else {
+        debug!(path = source, "reading background image");
+        tokio::fs::read(source)
+            .await
+            .map_err(|e| BackgroundError::Read(e, source.into()))?
+    };
+
+    image::load_from_memory(&bytes).map_err(|e| BackgroundError::Decode(e, source.into()))
+}
+
+/// Derives theme colors from an image.
+///
+/// # Arguments
+///
+/// * `img` - The image to derive colors from.
+/// * `dark` - Indicates if the colors will be used with a dark theme.
+fn derive_colors(img: &DynamicImage, dark: bool) -> DerivedColors {
+    // Colors don't need to be precise, so save some time by working with a small thumbnail
+    let rgba = img.thumbnail(64, 64).into_rgba8();
+
+    let mut sum = [0u64; 3];
+    let mut count = 0u64;
+    let mut buckets = HashMap::<[u8; 3], ([u64; 3], u64)>::new();
+    for p in rgba.pixels().filter(|p| p[3] > 32) {
+        let bucket = buckets
+            .entry([p[0] >> 4, p[1] >> 4, p[2] >> 4])
+            .or_default();
+        for c in 0..3 {
+            sum[c] += u64::from(p[c]);
+            bucket.0[c] += u64::from(p[c]);
+        }
+        bucket.1 += 1;
+        count += 1;
+    }
+
+    let mean = |sum: [u64; 3], count: u64| -> [f32; 3] {
+        let count = count.max(1) as f32;
+        sum.map(|c| c as f32 / count / 255f32)
+    };
+    let average = mean(sum, count);
+    let dominant = buckets
+        .into_values()
+        .max_by_key(|bucket| bucket.1)
+        .map(|(sum, count)| mean(sum, count))
+        .unwrap_or(average);
+
+    let (h, s, _) = rgb_to_hsl(dominant);
+    let accent = hsl_to_rgb(h, s.max(0.45), if dark { 0.65 } else { 0.4 });
+    let (h, s, _) = rgb_to_hsl(average);
+    let overlay = hsl_to_rgb(h, s.min(0.3), if dark { 0.08 } else { 0.92 });
+
+    DerivedColors {
+        average: to_hex(average),
+        dominant: to_hex(dominant),
+        accent: to_hex(accent),
+        overlay: to_hex(overlay),
+    }
+}
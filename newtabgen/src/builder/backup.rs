@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Keeps backups of previously built pages, so a bad rebuild can be rolled back.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use tracing::debug;
+
+/// Copies the file at `path` to a backup, removing the oldest backups beyond `keep`. Nothing is
+/// done if the file doesn't exist.
+///
+/// Without a backup directory, backups are kept next to the file and numbered from newest to
+/// oldest, e.g. `newtab.html.1` and `newtab.html.2`. In a backup directory, backups are named
+/// after the time they were made instead, e.g. `newtab.html.1700000000`.
+///
+/// # Arguments
+///
+/// * `path` - The file to back up.
+/// * `keep` - Number of backups to keep. If a backup directory is provided, 0 keeps all of them.
+/// * `dir` - Directory to keep backups in, instead of next to the file.
+///
+/// # Errors
+///
+/// Returns an error if copying the file, or moving or removing older backups, fails.
+pub(crate) async fn backup(path: &Path, keep: usize, dir: Option<&Path>) -> io::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid output file name"))?;
+
+    match dir {
+        None => rotate_numbered(path, file_name, keep).await,
+        Some(dir) => timestamped(path, file_name, keep, dir).await,
+    }
+}
+
+/// Shifts each numbered backup up by one, dropping the oldest, then copies the file to backup 1.
+async fn rotate_numbered(path: &Path, file_name: &str, keep: usize) -> io::Result<()> {
+    if keep == 0 {
+        return Ok(());
+    }
+    let numbered = |n: usize| path.with_file_name(format!("{file_name}.{n}"));
+
+    let oldest = numbered(keep);
+    if oldest.exists() {
+        tokio::fs::remove_file(&oldest).await?;
+    }
+    for n in (1..keep).rev() {
+        let from = numbered(n);
+        if from.exists() {
+            tokio::fs::rename(&from, numbered(n + 1)).await?;
+        }
+    }
+
+    debug!(path = numbered(1).to_str(), "backing up previous output");
+    tokio::fs::copy(path, numbered(1)).await?;
+    Ok(())
+}
+
+/// Copies the file into the backup directory under a timestamped name, then removes the oldest
+/// backups beyond `keep`.
+async fn timestamped(path: &Path, file_name: &str, keep: usize, dir: &Path) -> io::Result<()> {
+    tokio::fs::create_dir_all(dir).await?;
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let backup = dir.join(format!("{file_name}.{secs}"));
+    debug!(path = backup.to_str(), "backing up previous output");
+    tokio::fs::copy(path, &backup).await?;
+
+    if keep == 0 {
+        return Ok(());
+    }
+    let prefix = format!("{file_name}.");
+    let mut backups = Vec::<(u64, PathBuf)>::new();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let secs = name
+            .to_str()
+            .and_then(|n| n.strip_prefix(&prefix))
+            .and_then(|s| s.parse::<u64>().ok());
+        if let Some(secs) = secs {
+            backups.push((secs, entry.path()));
+        }
+    }
+    backups.sort_unstable_by(|a, b| b.cmp(a));
+    for (_, old) in backups.into_iter().skip(keep) {
+        debug!(path = old.to_str(), "removing old backup");
+        tokio::fs::remove_file(old).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,27 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Helps debug custom templates, by writing the intermediate rendered templates to a directory
+//! (see [`BuildOptions::debug_dir`][super::BuildOptions::debug_dir]).
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use tracing::{info, warn};
+
+/// Writes an intermediate file to the debug directory, creating the directory if needed. Failures
+/// are logged rather than returned, since the files are only a debugging aid.
+///
+/// # Returns
+///
+/// The path of the written file, or `None` if writing it failed.
+pub(crate) fn write(dir: &Path, name: &str, contents: &[u8]) -> Option<PathBuf> {
+    let path = dir.join(name);
+    if let Err(e) = fs::create_dir_all(dir).and_then(|_| fs::write(&path, contents)) {
+        warn!(path = path.to_str(), error = %e, "failed to write debug file");
+        return None;
+    }
+    info!(path = path.to_str(), "wrote debug file");
+    Some(path)
+}
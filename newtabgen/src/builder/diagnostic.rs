@@ -0,0 +1,287 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides [`TemplateDiagnostic`], which explains where rendering a template or compiling the
+//! rendered SCSS failed, with an excerpt of the source around it.
+
+use std::{error::Error as _, fmt, path::Path};
+
+use serde::Serialize;
+
+/// Number of lines of context shown before and after the line an error occurred on.
+const CONTEXT_LINES: usize = 3;
+
+/// Name Tera gives templates rendered with [`tera::Tera::render_str`], which its error messages
+/// refer to instead of the actual template.
+const ONE_OFF_NAME: &str = "__tera_one_off";
+
+/// Name of the HTML template in diagnostics.
+pub(crate) const HTML_TEMPLATE: &str = "html template";
+
+/// Name of the SCSS template in diagnostics.
+pub(crate) const SCSS_TEMPLATE: &str = "scss template";
+
+/// Where and why a template failed to render, or the rendered SCSS failed to compile.
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateDiagnostic {
+    /// Name of the template, such as "html template", or the file it was written to.
+    pub template: String,
+
+    /// What went wrong.
+    pub message: String,
+
+    /// Line the error occurred on, starting at 1, if known.
+    pub line: Option<usize>,
+
+    /// Column the error occurred on, starting at 1, if known.
+    pub column: Option<usize>,
+
+    /// Lines around the error and their line numbers, if its line is known.
+    pub excerpt: Vec<(usize, String)>,
+
+    /// Additional hints about the error, such as which template line rendered SCSS came from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub help: Option<String>,
+}
+
+impl TemplateDiagnostic {
+    /// Creates a diagnostic, taking the excerpt from the source.
+    fn new(
+        template: String,
+        message: String,
+        src: &str,
+        position: Option<(usize, Option<usize>)>,
+        help: Option<String>,
+    ) -> Self {
+        let lines = src.lines().collect::<Vec<_>>();
+        let position = position.filter(|(line, _)| *line > 0 && *line <= lines.len());
+        let excerpt = match position {
+            Some((line, _)) => {
+                let first = line.saturating_sub(CONTEXT_LINES).max(1);
+                let last = (line + CONTEXT_LINES).min(lines.len());
+                (first..=last)
+                    .zip(&lines[first - 1..last])
+                    .map(|(no, text)| (no, (*text).to_owned()))
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        Self {
+            template,
+            message,
+            line: position.map(|(line, _)| line),
+            column: position.and_then(|(_, column)| column),
+            excerpt,
+            help,
+        }
+    }
+
+    /// Explains a Tera error. Parse errors contain their position, but render errors don't, so
+    /// their position is guessed from the first name quoted in the message, such as the variable
+    /// that wasn't found.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - Name of the template, such as "html template".
+    /// * `src` - The template source.
+    /// * `error` - The error returned when parsing or rendering the template.
+    pub(crate) fn tera(template: &str, src: &str, error: &tera::Error) -> Self {
+        let mut messages = vec![error.to_string()];
+        let mut source = error.source();
+        while let Some(e) = source {
+            messages.push(e.to_string());
+            source = e.source();
+        }
+
+        // Parse errors are formatted by pest, with the position and the reason on separate lines
+        if let Some((position, reason)) = messages.iter().find_map(|m| pest_position(m)) {
+            return Self::new(template.into(), reason, src, Some(position), None);
+        }
+
+        let message = messages
+            .iter()
+            .filter(|m| !m.starts_with("Failed to render") && !m.starts_with("Failed to parse"))
+            .map(|m| {
+                m.replace(&format!(" while rendering '{ONE_OFF_NAME}'"), "")
+                    .replace(ONE_OFF_NAME, template)
+            })
+            .collect::<Vec<_>>();
+        let message = if message.is_empty() {
+            messages.join(": ").replace(ONE_OFF_NAME, template)
+        } else {
+            message.join(": ")
+        };
+        let position = quoted_name(&message).and_then(|name| find_in_tag(src, name));
+        Self::new(template.into(), message, src, position, None)
+    }
+
+    /// Explains an SCSS compilation error, since the compiler only knows about the rendered SCSS,
+    /// which users never see.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The compiler's error message, which should contain a `line:column` position.
+    /// * `rendered` - The rendered SCSS that failed to compile.
+    /// * `template` - The SCSS template it was rendered from.
+    /// * `dumped` - Where the rendered SCSS was written, if it was.
+    pub(crate) fn scss(
+        message: &str,
+        rendered: &str,
+        template: &str,
+        dumped: Option<&Path>,
+    ) -> Self {
+        let name = dumped.map_or_else(|| "rendered scss".into(), |p| p.display().to_string());
+        let position = error_position(message);
+        let help = position
+            .and_then(|(line, _)| rendered.lines().nth(line - 1))
+            .map(|line| match template_line(template, line) {
+                Some(no) => format!("rendered from line {no} of the scss template"),
+                None => "couldn't be traced back to a line of the scss template".into(),
+            });
+        let reason = message
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or(message);
+        let reason = reason.strip_prefix("Error: ").unwrap_or(reason);
+        Self::new(name, reason.into(), rendered, position, help)
+    }
+
+    /// Describes the error on a single line, for error messages.
+    pub fn summary(&self) -> String {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                format!("{}:{line}:{column}: {}", self.template, self.message)
+            }
+            (Some(line), None) => format!("{}:{line}: {}", self.template, self.message),
+            _ => format!("{}: {}", self.template, self.message),
+        }
+    }
+}
+
+impl fmt::Display for TemplateDiagnostic {
+    /// Formats the diagnostic over several lines, with the excerpt and a marker under the
+    /// column the error occurred on.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "error: {}", self.message)?;
+        let width = self
+            .excerpt
+            .last()
+            .map_or(1, |(no, _)| no.to_string().len());
+        let pad = " ".repeat(width);
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                writeln!(f, "{pad}--> {}:{line}:{column}", self.template)?
+            }
+            (Some(line), None) => writeln!(f, "{pad}--> {}:{line}", self.template)?,
+            _ => writeln!(f, "{pad}--> {}", self.template)?,
+        }
+        if !self.excerpt.is_empty() {
+            writeln!(f, "{pad} |")?;
+            for (no, text) in &self.excerpt {
+                writeln!(f, "{no:>width$} | {text}")?;
+                if Some(*no) == self.line {
+                    if let Some(column) = self.column {
+                        // Tabs are kept so the marker lines up with the text above it
+                        let indent = text
+                            .chars()
+                            .take(column.saturating_sub(1))
+                            .map(|c| if c == '\t' { '\t' } else { ' ' })
+                            .collect::<String>();
+                        writeln!(f, "{pad} | {indent}^")?;
+                    }
+                }
+            }
+            writeln!(f, "{pad} |")?;
+        }
+        if let Some(help) = &self.help {
+            writeln!(f, "{pad} = help: {help}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Finds the position and reason in a parse error formatted by pest, which look like this:
+///
+/// ```text
+///  --> 3:5
+///   |
+/// 3 | {{ foo( }}
+///   |     ^---
+///   |
+///   = expected an expression
+/// ```
+fn pest_position(message: &str) -> Option<((usize, Option<usize>), String)> {
+    let position = message
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("--> "))?;
+    let (line, column) = position.split_once(':')?;
+    let reason = message
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("= "))
+        .unwrap_or("failed to parse template");
+    Some((
+        (line.parse().ok()?, column.trim().parse().ok()),
+        reason.into(),
+    ))
+}
+
+/// Finds the first name quoted with backticks in a message, such as a variable or filter name.
+fn quoted_name(message: &str) -> Option<&str> {
+    let (_, rest) = message.split_once('`')?;
+    let (name, _) = rest.split_once('`')?;
+    (!name.is_empty()).then_some(name)
+}
+
+/// Finds the first occurrence of a name within a template tag, `{{ ... }}` or `{% ... %}`.
+///
+/// # Returns
+///
+/// The line and column of the name, starting at 1.
+fn find_in_tag(src: &str, name: &str) -> Option<(usize, Option<usize>)> {
+    src.lines().enumerate().find_map(|(index, line)| {
+        let tag = line.find("{{").into_iter().chain(line.find("{%")).min()?;
+        let offset = line[tag..].find(name)? + tag;
+        Some((index + 1, Some(line[..offset].chars().count() + 1)))
+    })
+}
+
+/// Finds the position of an error, from the first `line:column` position in its message.
+fn error_position(message: &str) -> Option<(usize, Option<usize>)> {
+    message
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '(' || c == ')')
+        .filter_map(|word| word.split_once(':'))
+        .find_map(|(line, column)| {
+            let column = column.trim_end_matches(|c: char| !c.is_ascii_digit());
+            let column = column.parse::<usize>().ok()?;
+            let line = line.parse::<usize>().ok().filter(|line| *line > 0)?;
+            Some((line, Some(column)))
+        })
+}
+
+/// Finds the template line a rendered line came from. Template lines without expressions must
+/// match exactly, and lines with expressions must match the text around them.
+///
+/// # Returns
+///
+/// The line number, or `None` if no line, or more than one line, matches.
+fn template_line(template: &str, rendered: &str) -> Option<usize> {
+    let rendered = rendered.trim();
+    if rendered.is_empty() {
+        return None;
+    }
+    let mut matches = template.lines().enumerate().filter(|(_, line)| {
+        let line = line.trim();
+        match (line.find("{{"), line.rfind("}}")) {
+            (Some(start), Some(end)) if start < end => {
+                let (prefix, suffix) = (&line[..start], &line[end + 2..]);
+                !(prefix.is_empty() && suffix.is_empty())
+                    && rendered.len() >= prefix.len() + suffix.len()
+                    && rendered.starts_with(prefix)
+                    && rendered.ends_with(suffix)
+            }
+            _ => line == rendered,
+        }
+    });
+    let (index, _) = matches.next()?;
+    matches.next().is_none().then_some(index + 1)
+}
@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Finds links whose URL appears more than once across the config, and optionally drops all but
+//! the first occurrence, see [`BuildOptions::dedupe`][super::BuildOptions::dedupe].
+
+use std::collections::{HashMap, HashSet};
+
+use url::Url;
+
+use crate::config::{Config, Section};
+
+use super::report::DuplicateLinkReport;
+
+/// Finds every URL linked more than once, in the order the first occurrences appear.
+///
+/// URLs are compared after normalization, so `https://example.com` and `https://example.com/` are
+/// the same link.
+pub(crate) fn find(config: &Config) -> Vec<DuplicateLinkReport> {
+    let mut duplicates = Vec::<DuplicateLinkReport>::new();
+    let mut by_url = HashMap::<String, usize>::new();
+    for page in &config.pages {
+        let mut stack = page
+            .sections
+            .iter()
+            .rev()
+            .map(|s| (vec![page.name.as_str()], s))
+            .collect::<Vec<(Vec<&str>, &Section)>>();
+        while let Some((mut path, section)) = stack.pop() {
+            path.push(&section.name);
+            for link in &section.links {
+                let index = *by_url.entry(normalize(&link.url)).or_insert_with(|| {
+                    duplicates.push(DuplicateLinkReport {
+                        url: link.url.clone(),
+                        locations: Vec::new(),
+                    });
+                    duplicates.len() - 1
+                });
+                duplicates[index].locations.push(path.join(" / "));
+            }
+            stack.extend(section.sections.iter().rev().map(|s| (path.clone(), s)));
+        }
+    }
+    duplicates.retain(|d| d.locations.len() > 1);
+    duplicates
+}
+
+/// Drops every link whose URL already appeared earlier in the config, in the same order as
+/// [`find`].
+///
+/// # Returns
+///
+/// The number of links dropped.
+pub(crate) fn dedupe(config: &mut Config) -> usize {
+    let mut seen = HashSet::new();
+    let mut dropped = 0;
+    for page in &mut config.pages {
+        for section in &mut page.sections {
+            dedupe_section(section, &mut seen, &mut dropped);
+        }
+    }
+    dropped
+}
+
+/// Drops links of a section and its subsections whose URL is in `seen`, adding the rest.
+fn dedupe_section(section: &mut Section, seen: &mut HashSet<String>, dropped: &mut usize) {
+    let before = section.links.len();
+    section
+        .links
+        .retain(|link| seen.insert(normalize(&link.url)));
+    *dropped += before - section.links.len();
+    for subsection in &mut section.sections {
+        dedupe_section(subsection, seen, dropped);
+    }
+}
+
+/// Normalizes a URL for comparison, leaving it as is if it can't be parsed.
+fn normalize(url: &str) -> String {
+    Url::parse(url).map_or_else(|_| url.to_owned(), String::from)
+}
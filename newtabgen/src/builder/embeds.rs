@@ -0,0 +1,82 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Validates the pages embedded in sections, see `Section.embeds`.
+
+use tracing::warn;
+use url::Url;
+
+use crate::config::{Config, Section};
+
+use super::BuildError;
+
+/// Smallest width or height of an embed, in pixels.
+const MIN_SIZE: u32 = 50;
+
+/// Largest width or height of an embed, in pixels.
+const MAX_SIZE: u32 = 2000;
+
+/// Sandbox tokens browsers understand, see `Embed.sandbox`.
+const SANDBOX_TOKENS: &[&str] = &[
+    "allow-downloads",
+    "allow-forms",
+    "allow-modals",
+    "allow-orientation-lock",
+    "allow-pointer-lock",
+    "allow-popups",
+    "allow-popups-to-escape-sandbox",
+    "allow-presentation",
+    "allow-same-origin",
+    "allow-scripts",
+    "allow-storage-access-by-user-activation",
+    "allow-top-navigation",
+    "allow-top-navigation-by-user-activation",
+    "allow-top-navigation-to-custom-protocols",
+];
+
+/// Checks that every embed has an HTTP or HTTPS URL, a size within bounds, and only known sandbox
+/// tokens. Embeds allowed both scripts and their own origin are logged, since they can remove
+/// their own sandbox.
+///
+/// # Errors
+///
+/// Returns an error describing the first invalid embed found.
+pub(crate) fn validate(config: &Config) -> Result<(), BuildError> {
+    let embeds = config
+        .pages
+        .iter()
+        .flat_map(|p| &p.sections)
+        .flat_map(Section::all_embeds);
+    for embed in embeds {
+        let url = &embed.url;
+        let valid_url = Url::parse(url).is_ok_and(|u| matches!(u.scheme(), "http" | "https"));
+        if !valid_url {
+            return Err(BuildError::Embed(format!(
+                "{url} (must be an http or https url)"
+            )));
+        }
+        for size in [embed.width, embed.height] {
+            if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+                return Err(BuildError::Embed(format!(
+                    "{url} (size must be from {MIN_SIZE} to {MAX_SIZE} pixels, got {size})"
+                )));
+            }
+        }
+        if let Some(token) = embed
+            .sandbox
+            .iter()
+            .find(|t| !SANDBOX_TOKENS.contains(&t.as_str()))
+        {
+            return Err(BuildError::Embed(format!(
+                "{url} (unknown sandbox token '{token}')"
+            )));
+        }
+        let allows = |token: &str| embed.sandbox.iter().any(|t| t == token);
+        if allows("allow-scripts") && allows("allow-same-origin") {
+            warn!(
+                url,
+                "embed allows both scripts and its own origin, so it can remove its sandbox"
+            );
+        }
+    }
+    Ok(())
+}
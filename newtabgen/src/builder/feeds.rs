@@ -0,0 +1,257 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Manages fetching and parsing RSS/Atom feeds, so their newest items can be rendered statically.
+//!
+//! Failing to fetch or parse a feed doesn't fail the build. Instead, the error is logged and
+//! exposed to templates alongside the (empty) feed.
+
+#[cfg(feature = "feeds")]
+use std::io::Cursor;
+
+use serde::Serialize;
+use thiserror::Error;
+use tokio::time::Instant;
+use tracing::{debug, info, instrument, warn};
+
+use super::CacheMode;
+use crate::config::{Config, Feed};
+#[cfg(feature = "feeds")]
+use crate::util;
+
+/// Errors that may occur when fetching or parsing a feed.
+#[derive(Error, Debug)]
+pub enum FeedError {
+    /// Occurs when no suitable place to cache feeds can be found.
+    #[error("failed to locate cache dir")]
+    CacheDir,
+
+    /// Occurs when writing a feed to cache fails.
+    #[error("failed to write feed to cache ({0})")]
+    CacheWrite(#[source] tokio::io::Error),
+
+    /// Occurs when reading a cached feed fails.
+    #[error("failed to read cached feed ({0})")]
+    CacheRead(#[source] tokio::io::Error),
+
+    /// Occurs when building the [`reqwest::Client`] fails.
+    #[error(transparent)]
+    HttpClient(#[from] reqwest::Error),
+
+    /// Occurs when downloading a feed fails.
+    #[error("failed to download feed: {1} ({0})")]
+    Request(#[source] reqwest::Error, String),
+
+    /// Occurs when parsing a downloaded feed fails.
+    #[cfg(feature = "feeds")]
+    #[error("failed to parse feed: {1} ({0})")]
+    Parse(#[source] feed_rs::parser::ParseFeedError, String),
+
+    /// Occurs when a feed is configured, but the library was built without the `feeds` feature.
+    #[error("feeds are not supported by this build of newtabgen")]
+    Disabled,
+
+    /// Occurs when a feed isn't cached and [`CacheMode::Offline`] prevents downloading it.
+    #[error("feed isn't cached, and downloading is disabled while offline: {0}")]
+    Offline(String),
+}
+
+/// A feed, as exposed to templates.
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedContext {
+    /// Display name, from the config or the feed itself.
+    pub name: String,
+
+    /// Feed URL.
+    pub url: String,
+
+    /// Name of the page to show the feed on.
+    pub page: Option<String>,
+
+    /// The newest items in the feed, newest first.
+    pub items: Vec<FeedItem>,
+
+    /// Describes why the feed couldn't be loaded, if it couldn't be.
+    pub error: Option<String>,
+}
+
+/// A single feed item, as exposed to templates.
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedItem {
+    /// Item title.
+    pub title: String,
+
+    /// Item URL.
+    pub url: Option<String>,
+
+    /// Publication date (or last update, if the feed doesn't specify it) in RFC 3339 format.
+    pub published: Option<String>,
+}
+
+/// Fetches and parses each feed in the config.
+///
+/// # Arguments
+///
+/// * `config` - The config to extract feeds from.
+/// * `cache` - How cached feeds are used.
+///
+/// # Errors
+///
+/// Returns an error if the HTTP client can't be built. Errors related to individual feeds are
+/// logged and exposed through [`FeedContext::error`] instead.
+///
+/// # Returns
+///
+/// The newest items of each feed, in the order the feeds are listed in the config.
+#[instrument(name = "feeds", skip_all)]
+pub async fn build_feeds(config: &Config, cache: CacheMode) -> Result<Vec<FeedContext>, FeedError> {
+    info!("building feeds");
+    let sw = Instant::now();
+
+    let http_client = reqwest::Client::builder()
+        .user_agent("newtabgen (reading feeds) github.com/fr33zing/newtabgen")
+        .build()?;
+    let first_page = config.pages.first().map(|p| p.name.clone());
+
+    let mut feeds = Vec::<FeedContext>::new();
+    for feed in &config.widgets.feeds {
+        let mut context = FeedContext {
+            name: feed.name.clone().unwrap_or_else(|| feed.url.clone()),
+            url: feed.url.clone(),
+            page: feed.page.clone().or_else(|| first_page.clone()),
+            items: Vec::new(),
+            error: None,
+        };
+
+        match load_items(feed, &http_client, cache).await {
+            Ok((title, items)) => {
+                if let (None, Some(title)) = (&feed.name, title) {
+                    context.name = title;
+                }
+                context.items = items;
+            }
+            Err(e) => {
+                warn!(url = feed.url, error = format!("{}", e), "skipping feed");
+                context.error = Some(e.to_string());
+            }
+        }
+
+        feeds.push(context);
+    }
+
+    debug!(
+        elapsed_ms = sw.elapsed().as_millis(),
+        "finished building feeds"
+    );
+    Ok(feeds)
+}
+
+/// Loads a feed, then extracts its title and newest items.
+///
+/// # Returns
+///
+/// The feed's own title, if it has one, and up to `feed.limit` items, newest first.
+#[cfg(feature = "feeds")]
+async fn load_items(
+    feed: &Feed,
+    http_client: &reqwest::Client,
+    cache: CacheMode,
+) -> Result<(Option<String>, Vec<FeedItem>), FeedError> {
+    let parsed = load_feed(&feed.url, http_client, cache).await?;
+    let mut entries = parsed.entries;
+    entries.sort_by_key(|e| std::cmp::Reverse(e.published.or(e.updated)));
+    let items = entries
+        .into_iter()
+        .take(feed.limit)
+        .map(|e| FeedItem {
+            title: e.title.map(|t| t.content).unwrap_or_default(),
+            url: e.links.first().map(|l| l.href.clone()),
+            published: e.published.or(e.updated).map(|d| d.to_rfc3339()),
+        })
+        .collect();
+    Ok((parsed.title.map(|t| t.content), items))
+}
+
+/// Stands in for loading a feed when feed support is disabled.
+#[cfg(not(feature = "feeds"))]
+async fn load_items(
+    _feed: &Feed,
+    _http_client: &reqwest::Client,
+    _cache: CacheMode,
+) -> Result<(Option<String>, Vec<FeedItem>), FeedError> {
+    Err(FeedError::Disabled)
+}
+
+/// Checks if a feed would be downloaded instead of read from the cache, without changing the
+/// cache. Feeds are never downloaded if feed support is disabled.
+pub(crate) fn feed_needs_download(url: &str, cache: CacheMode) -> bool {
+    if !cfg!(feature = "feeds") || cache == CacheMode::Offline {
+        return false;
+    }
+    if cache == CacheMode::Refresh {
+        return true;
+    }
+    crate::util::cache_subdir("feeds")
+        .map(|dir| dir.join(crate::util::sha1_base32(url.as_bytes())))
+        .map_or(true, |path| {
+            !path.exists() || crate::util::cache_expired(&path, 3600)
+        })
+}
+
+/// Loads and parses a feed, reading it from the cache if possible. Cached feeds expire after one
+/// hour, unless `cache` is [`CacheMode::Offline`]. [`CacheMode::Refresh`] ignores cached feeds.
+#[cfg(feature = "feeds")]
+async fn load_feed(
+    url: &str,
+    http_client: &reqwest::Client,
+    cache: CacheMode,
+) -> Result<feed_rs::model::Feed, FeedError> {
+    let path = util::cache_subdir("feeds")
+        .map_err(|_| FeedError::CacheDir)?
+        .join(util::sha1_base32(url.as_bytes()));
+
+    // Expire after one hour
+    if path.exists()
+        && (cache == CacheMode::Refresh
+            || cache == CacheMode::PreferCache && util::cache_expired(&path, 3600))
+        && tokio::fs::remove_file(&path).await.is_err()
+    {
+        warn!(
+            path = path.to_str(),
+            "failed to remove expired feed from cache"
+        );
+    }
+
+    if path.exists() {
+        debug!(path = path.to_str(), "reading cached feed");
+        let bytes = tokio::fs::read(&path).await.map_err(FeedError::CacheRead)?;
+        return parse_feed(bytes, url);
+    }
+
+    if cache == CacheMode::Offline {
+        return Err(FeedError::Offline(url.into()));
+    }
+
+    debug!(url, "downloading feed");
+    let bytes = http_client
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| FeedError::Request(e, url.into()))?
+        .bytes()
+        .await
+        .map_err(|e| FeedError::Request(e, url.into()))?
+        .to_vec();
+    let feed = parse_feed(bytes.clone(), url)?;
+    debug!(path = path.to_str(), "writing feed to cache");
+    tokio::fs::write(&path, &bytes)
+        .await
+        .map_err(FeedError::CacheWrite)?;
+    Ok(feed)
+}
+
+/// Parses a downloaded or cached feed.
+#[cfg(feature = "feeds")]
+fn parse_feed(bytes: Vec<u8>, url: &str) -> Result<feed_rs::model::Feed, FeedError> {
+    feed_rs::parser::parse(Cursor::new(bytes)).map_err(|e| FeedError::Parse(e, url.into()))
+}
@@ -0,0 +1,425 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides the [`IconFetcher`] trait, which allows replacing how site icons are obtained, along
+//! with the built-in implementations.
+
+#[cfg(feature = "remote-icons")]
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    sync::{Arc, Mutex},
+};
+use std::{fmt, path::PathBuf};
+
+use async_trait::async_trait;
+use image::DynamicImage;
+#[cfg(feature = "remote-icons")]
+use image::ImageFormat;
+use reqwest::{
+    header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "remote-icons")]
+use tokio::sync::OnceCell;
+use tracing::debug;
+#[cfg(feature = "remote-icons")]
+use url::Url;
+
+#[cfg(feature = "remote-icons")]
+use super::robots::Robots;
+use super::{rate_limit::HostRateLimiter, site_icons::SiteIconError};
+use crate::config::IconService;
+
+/// User agent sent with site icon requests.
+const USER_AGENT: &str = "newtabgen (looking for icons) github.com/fr33zing/newtabgen";
+
+/// Where a fetched icon was downloaded from, along with the validators the server sent for it.
+/// Stored with the cached icon, so it can be revalidated with a conditional request once expired.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IconSource {
+    /// URL the icon was downloaded from.
+    pub url: String,
+
+    /// Value of the `ETag` response header, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+
+    /// Value of the `Last-Modified` response header, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl IconSource {
+    /// Reads the validators of a response to a request for the icon at `url`.
+    fn new(url: String, headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_owned)
+        };
+        Self {
+            url,
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+
+    /// Indicates if the server sent any validators, without which the icon can't be revalidated.
+    pub fn has_validators(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+}
+
+/// An icon obtained by [`IconFetcher::fetch_with_source`].
+#[derive(Debug)]
+pub struct FetchedIcon {
+    /// The decoded icon, in its original size.
+    pub img: DynamicImage,
+
+    /// Where the icon was downloaded from, if it was downloaded.
+    pub source: Option<IconSource>,
+}
+
+/// Obtains the icon for a website. Results are cached by the builder, so implementations don't
+/// need to cache icons themselves.
+#[async_trait]
+pub trait IconFetcher: fmt::Debug + Send + Sync {
+    /// Fetches the icon for the provided website URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no icon could be obtained.
+    ///
+    /// # Returns
+    ///
+    /// The decoded icon, in its original size.
+    async fn fetch(&self, website_url: &str) -> Result<DynamicImage, SiteIconError>;
+
+    /// Fetches the icon like [`IconFetcher::fetch`], along with where it was downloaded from so
+    /// the cached copy can be revalidated once it expires. Implementations sending several
+    /// requests should wait for `limiter` before each of them. Defaults to waiting once for remote
+    /// fetchers, and no source, in which case expired icons are fetched again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no icon could be obtained.
+    async fn fetch_with_source(
+        &self,
+        website_url: &str,
+        limiter: &HostRateLimiter,
+    ) -> Result<FetchedIcon, SiteIconError> {
+        if self.is_remote() {
+            limiter.wait(website_url).await;
+        }
+        Ok(FetchedIcon {
+            img: self.fetch(website_url).await?,
+            source: None,
+        })
+    }
+
+    /// Indicates if fetching uses the network, so it must be avoided while offline. Defaults to
+    /// `true`.
+    fn is_remote(&self) -> bool {
+        true
+    }
+}
+
+/// The default [`IconFetcher`], which locates icons in the website itself and downloads them.
+#[cfg(feature = "remote-icons")]
+#[derive(Debug, Default)]
+pub struct RemoteIconFetcher {
+    /// Client used for downloading icons, created on first use.
+    http_client: OnceCell<reqwest::Client>,
+
+    /// Whether websites whose `robots.txt` disallows loading them are skipped.
+    respect_robots: bool,
+
+    /// The `robots.txt` of each website seen so far, keyed by origin. Each is downloaded once, even
+    /// if several of the website's icons are fetched at the same time.
+    robots: Mutex<HashMap<String, Arc<OnceCell<Robots>>>>,
+}
+
+#[cfg(feature = "remote-icons")]
+impl RemoteIconFetcher {
+    /// Creates a fetcher that skips websites whose `robots.txt` disallows loading them, failing
+    /// with [`SiteIconError::Robots`] instead, so `Config.icon_fallback` is asked if provided.
+    /// Icons are still downloaded from wherever the page points to.
+    pub fn respecting_robots() -> Self {
+        Self {
+            respect_robots: true,
+            ..Default::default()
+        }
+    }
+
+    /// Checks if the `robots.txt` of a website allows loading it, downloading the `robots.txt`
+    /// the first time the website is seen.
+    async fn robots_allow(
+        &self,
+        client: &reqwest::Client,
+        url: &Url,
+        limiter: &HostRateLimiter,
+    ) -> bool {
+        let origin = url.origin().ascii_serialization();
+        let robots = self
+            .robots
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(origin)
+            .or_default()
+            .clone();
+        robots
+            .get_or_init(|| Robots::fetch(client, url, limiter))
+            .await
+            .allows(url)
+    }
+
+    /// Returns the HTTP client, creating it if needed.
+    async fn http_client(&self) -> Result<&reqwest::Client, SiteIconError> {
+        self.http_client
+            .get_or_try_init(|| async {
+                reqwest::Client::builder()
+                    .user_agent(USER_AGENT)
+                    .build()
+                    .map_err(SiteIconError::HttpClient)
+            })
+            .await
+    }
+}
+
+#[cfg(feature = "remote-icons")]
+#[async_trait]
+impl IconFetcher for RemoteIconFetcher {
+    async fn fetch(&self, website_url: &str) -> Result<DynamicImage, SiteIconError> {
+        let limiter = HostRateLimiter::new(std::time::Duration::ZERO);
+        Ok(self.fetch_with_source(website_url, &limiter).await?.img)
+    }
+
+    /// Locates, downloads, and decodes a suitable icon in the webpage. This process involves
+    /// sending multiple HTTP requests, each spaced out by `limiter`.
+    async fn fetch_with_source(
+        &self,
+        website_url: &str,
+        limiter: &HostRateLimiter,
+    ) -> Result<FetchedIcon, SiteIconError> {
+        let http_client = self.http_client().await?;
+
+        if self.respect_robots {
+            let url =
+                Url::parse(website_url).map_err(|_| SiteIconError::UrlLoad(website_url.into()))?;
+            if !self.robots_allow(http_client, &url, limiter).await {
+                return Err(SiteIconError::Robots(website_url.into()));
+            }
+        }
+
+        limiter.wait(website_url).await;
+        debug!(website_url, "locating remote site icon");
+        let mut icons = site_icons::Icons::new();
+        icons
+            .load_website(website_url)
+            .await
+            .map_err(|_| SiteIconError::UrlLoad(website_url.into()))?;
+        debug!("choosing site icon");
+        let entries = icons.entries().await;
+        let icon = {
+            // Prefer favicon
+            let favicon = entries
+                .iter()
+                .find(|i| i.url.path().contains("favicon.ico"));
+            match favicon {
+                Some(i) => i,
+                None => entries
+                    .iter()
+                    .find(|i| !matches!(i.info, site_icons::IconInfo::SVG))
+                    .ok_or_else(|| SiteIconError::IconNotFound(website_url.into()))?,
+            }
+        };
+        let icon_url = icon.url.to_string();
+        limiter.wait(&icon_url).await;
+        debug!(icon_url, "downloading site icon");
+        let response = http_client
+            .get(icon.url.to_string())
+            .send()
+            .await
+            .map_err(|e| SiteIconError::IconRequest(e, icon.url.clone().into()))?;
+        let source = IconSource::new(icon_url, response.headers());
+        let icon_bytes = response
+            .bytes()
+            .await
+            .map_err(|e| SiteIconError::IconRequest(e, icon.url.clone().into()))?;
+        debug!(len = icon_bytes.len(), "reading downloaded site icon");
+        let cursor = Cursor::new(icon_bytes);
+        let mut reader = image::io::Reader::new(cursor);
+        let format = match icon.info.clone() {
+            site_icons::IconInfo::PNG { size: _ } => ImageFormat::Png,
+            site_icons::IconInfo::JPEG { size: _ } => ImageFormat::Jpeg,
+            site_icons::IconInfo::ICO { sizes: _ } => ImageFormat::Ico,
+            site_icons::IconInfo::SVG => unreachable!("SVGs should be filtered out"),
+        };
+        reader.set_format(format);
+        let img = reader
+            .decode()
+            .map_err(|e| SiteIconError::IconDecode(e, website_url.into()))?;
+        Ok(FetchedIcon {
+            img,
+            source: Some(source),
+        })
+    }
+}
+
+/// Downloads the icon of a website from a favicon service, see `Config.icon_fallback`, waiting for
+/// `limiter` before sending the request.
+///
+/// # Errors
+///
+/// Returns an error if the URL has no host, or the icon can't be downloaded or decoded.
+pub(crate) async fn fetch_from_service(
+    service: &IconService,
+    website_url: &str,
+    limiter: &HostRateLimiter,
+) -> Result<FetchedIcon, SiteIconError> {
+    let host = url::Url::parse(website_url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_owned))
+        .ok_or_else(|| SiteIconError::UrlLoad(website_url.into()))?;
+    let icon_url = service.icon_url(&host);
+    limiter.wait(&icon_url).await;
+    debug!(icon_url, "downloading site icon from favicon service");
+    download(
+        reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .build()?
+            .get(&icon_url),
+        icon_url,
+    )
+    .await?
+    .ok_or_else(|| SiteIconError::IconNotFound(website_url.into()))
+}
+
+/// Checks if a cached icon changed since it was downloaded, with a conditional request carrying
+/// the validators the server sent for it.
+///
+/// # Errors
+///
+/// Returns an error if the request fails, or a changed icon can't be decoded.
+///
+/// # Returns
+///
+/// The changed icon, or `None` if the cached icon is still current.
+pub(crate) async fn revalidate(source: &IconSource) -> Result<Option<FetchedIcon>, SiteIconError> {
+    debug!(icon_url = source.url, "revalidating cached site icon");
+    let mut request = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .build()?
+        .get(&source.url);
+    if let Some(etag) = &source.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &source.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+    download(request, source.url.clone()).await
+}
+
+/// Sends a request for an icon and decodes the response.
+///
+/// # Errors
+///
+/// Returns an error if the request fails or the icon can't be decoded.
+///
+/// # Returns
+///
+/// The icon, or `None` if the server responded that it wasn't modified.
+async fn download(
+    request: reqwest::RequestBuilder,
+    icon_url: String,
+) -> Result<Option<FetchedIcon>, SiteIconError> {
+    let response = request
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .map_err(|e| SiteIconError::IconRequest(e, icon_url.clone()))?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    let source = IconSource::new(icon_url.clone(), response.headers());
+    let icon_bytes = response
+        .bytes()
+        .await
+        .map_err(|e| SiteIconError::IconRequest(e, icon_url.clone()))?;
+    let img =
+        image::load_from_memory(&icon_bytes).map_err(|e| SiteIconError::IconDecode(e, icon_url))?;
+    Ok(Some(FetchedIcon {
+        img,
+        source: Some(source),
+    }))
+}
+
+/// An [`IconFetcher`] that reads icons from a local directory instead of the network.
+///
+/// Icons are matched by the website's host name, ignoring any `www.` prefix. For example, the
+/// icon for `https://www.github.com/fr33zing` may be named `github.com.png` or `github.com.ico`.
+#[derive(Debug, Clone)]
+pub struct LocalIconFetcher {
+    /// Directory containing the icons.
+    pub dir: PathBuf,
+}
+
+impl LocalIconFetcher {
+    /// Creates a fetcher that reads icons from the provided directory.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl IconFetcher for LocalIconFetcher {
+    fn is_remote(&self) -> bool {
+        false
+    }
+
+    async fn fetch(&self, website_url: &str) -> Result<DynamicImage, SiteIconError> {
+        let host = url::Url::parse(website_url)
+            .ok()
+            .and_then(|u| {
+                u.host_str()
+                    .map(|h| h.trim_start_matches("www.").to_owned())
+            })
+            .ok_or_else(|| SiteIconError::UrlLoad(website_url.into()))?;
+
+        for ext in ["png", "ico", "jpg", "jpeg", "gif", "webp", "bmp"] {
+            let path = self.dir.join(format!("{host}.{ext}"));
+            if !path.exists() {
+                continue;
+            }
+
+            debug!(path = path.to_str(), "reading local site icon");
+            let icon_bytes = tokio::fs::read(&path)
+                .await
+                .map_err(|e| SiteIconError::Fetch(e.into(), website_url.into()))?;
+            let img = image::load_from_memory(&icon_bytes)
+                .map_err(|e| SiteIconError::IconDecode(e, website_url.into()))?;
+            return Ok(img);
+        }
+
+        Err(SiteIconError::IconNotFound(website_url.into()))
+    }
+}
+
+/// The default [`IconFetcher`] when the library is built without the `remote-icons` feature.
+/// Fails to fetch every icon, so only cached icons can be used unless another fetcher is provided.
+#[cfg(not(feature = "remote-icons"))]
+#[derive(Debug, Default)]
+pub(crate) struct DisabledIconFetcher;
+
+#[cfg(not(feature = "remote-icons"))]
+#[async_trait]
+impl IconFetcher for DisabledIconFetcher {
+    async fn fetch(&self, website_url: &str) -> Result<DynamicImage, SiteIconError> {
+        Err(SiteIconError::Fetch(
+            "remote icons are not supported by this build of newtabgen".into(),
+            website_url.into(),
+        ))
+    }
+}
@@ -0,0 +1,120 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Detects inline styles and data URLs in the output that are large enough for some browsers to
+//! slow down or stop rendering them.
+
+use super::report::{OversizedKind, OversizedReport};
+
+impl OversizedKind {
+    /// Describes the kind of content in messages.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Style => "style block",
+            Self::DataUrl => "data url",
+            Self::Output => "output",
+        }
+    }
+
+    /// Suggests how to shrink this kind of content.
+    pub(crate) fn suggestion(self) -> &'static str {
+        match self {
+            Self::Style => {
+                "set lazy_icons to defer site icons, or theme.background_embed to false to link \
+                 the background image instead of embedding it"
+            }
+            Self::DataUrl => {
+                "lower theme.background_max_width or theme.background_quality, or set \
+                 theme.background_embed to false to link the background image instead"
+            }
+            Self::Output => {
+                "lower build.icon_size, enable build.minify_html and build.minify_css, or shrink \
+                 the inline styles and data urls as suggested for them"
+            }
+        }
+    }
+}
+
+/// Finds each `<style>` block and `data:` URL in the output that exceeds its limit, and checks
+/// the size of the output as a whole.
+///
+/// # Arguments
+///
+/// * `html` - The rendered output.
+/// * `max_style_bytes` - Largest size allowed for a single `<style>` block.
+/// * `max_data_url_bytes` - Largest size allowed for a single data URL.
+/// * `max_output_bytes` - Largest size allowed for the output, if limited.
+///
+/// # Returns
+///
+/// The oversized content, in the order it appears in the output, followed by the output itself
+/// if it's too large.
+pub(crate) fn oversized(
+    html: &[u8],
+    max_style_bytes: usize,
+    max_data_url_bytes: usize,
+    max_output_bytes: Option<usize>,
+) -> Vec<OversizedReport> {
+    let styles = style_blocks(html)
+        .into_iter()
+        .map(|(start, bytes)| (start, OversizedKind::Style, bytes, max_style_bytes));
+    let urls = data_urls(html)
+        .into_iter()
+        .map(|(start, bytes)| (start, OversizedKind::DataUrl, bytes, max_data_url_bytes));
+    let mut found = styles
+        .chain(urls)
+        .filter(|(_, _, bytes, limit)| bytes > limit)
+        .collect::<Vec<_>>();
+    found.sort_unstable_by_key(|(start, ..)| *start);
+    let mut reports = found
+        .into_iter()
+        .map(|(_, kind, bytes, limit)| OversizedReport { kind, bytes, limit })
+        .collect::<Vec<_>>();
+    if let Some(limit) = max_output_bytes.filter(|limit| html.len() > *limit) {
+        reports.push(OversizedReport {
+            kind: OversizedKind::Output,
+            bytes: html.len(),
+            limit,
+        });
+    }
+    reports
+}
+
+/// Finds the position and size of the contents of each `<style>` block.
+fn style_blocks(html: &[u8]) -> Vec<(usize, usize)> {
+    let mut blocks = Vec::new();
+    let mut pos = 0;
+    while let Some(open) = find(html, b"<style", pos) {
+        let Some(content_start) = find(html, b">", open).map(|i| i + 1) else {
+            break;
+        };
+        let content_end = find(html, b"</style", content_start).unwrap_or(html.len());
+        blocks.push((content_start, content_end - content_start));
+        pos = content_end;
+    }
+    blocks
+}
+
+/// Finds the position and size of each data URL. A data URL ends at the first quote, closing
+/// parenthesis, whitespace, or tag, which can't appear unescaped within it.
+fn data_urls(html: &[u8]) -> Vec<(usize, usize)> {
+    let mut urls = Vec::new();
+    let mut pos = 0;
+    while let Some(start) = find(html, b"data:", pos) {
+        let len = html[start..]
+            .iter()
+            .position(|b| matches!(b, b'"' | b'\'' | b')' | b'<') || b.is_ascii_whitespace())
+            .unwrap_or(html.len() - start);
+        urls.push((start, len));
+        pos = start + len;
+    }
+    urls
+}
+
+/// Finds the first occurrence of `needle` in `haystack`, starting at `from`.
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|i| i + from)
+}
@@ -0,0 +1,190 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides [`LinksJson`], a read-only JSON export of the resolved links, for other tools such as
+//! launchers or mobile apps to consume.
+//!
+//! # Schema
+//!
+//! The export reflects the config after it's resolved, so references are expanded, links are
+//! sorted, and URLs are rewritten. Fields that aren't set are omitted.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "title": "New Tab",
+//!   "pages": [
+//!     {
+//!       "name": "Home",
+//!       "icon": "home",
+//!       "default": true,
+//!       "sections": [
+//!         {
+//!           "name": "Dev",
+//!           "description": "Markdown text",
+//!           "links": [
+//!             {
+//!               "name": "Crates",
+//!               "url": "https://crates.io/",
+//!               "description": "Rust packages",
+//!               "badge": "new",
+//!               "icon": "data:image/png;base64,..."
+//!             }
+//!           ],
+//!           "sections": []
+//!         }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! `version` is incremented whenever a field is removed or its meaning changes. Adding fields
+//! doesn't change the version, so consumers should ignore fields they don't know.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::config::{Config, Link, Page, Section};
+
+/// Version of the [`LinksJson`] schema.
+pub const LINKS_JSON_VERSION: u32 = 1;
+
+/// A read-only export of the resolved links.
+#[derive(Debug, Clone, Serialize)]
+pub struct LinksJson {
+    /// Version of the schema, see [`LINKS_JSON_VERSION`].
+    pub version: u32,
+
+    /// Title of the new tab page.
+    pub title: String,
+
+    /// Each page, in the order the tabs are shown.
+    pub pages: Vec<LinksJsonPage>,
+}
+
+/// A page in a [`LinksJson`] export.
+#[derive(Debug, Clone, Serialize)]
+pub struct LinksJsonPage {
+    /// Page name.
+    pub name: String,
+
+    /// Name of the material design icon used for the page.
+    pub icon: String,
+
+    /// Indicates if the page is shown when the new tab page is opened.
+    pub default: bool,
+
+    /// Sections of the page.
+    pub sections: Vec<LinksJsonSection>,
+}
+
+/// A section in a [`LinksJson`] export.
+#[derive(Debug, Clone, Serialize)]
+pub struct LinksJsonSection {
+    /// Section name.
+    pub name: String,
+
+    /// Markdown text describing the section.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Links in the section.
+    pub links: Vec<LinksJsonLink>,
+
+    /// Sections nested within this one.
+    pub sections: Vec<LinksJsonSection>,
+}
+
+/// A link in a [`LinksJson`] export.
+#[derive(Debug, Clone, Serialize)]
+pub struct LinksJsonLink {
+    /// Display name.
+    pub name: String,
+
+    /// Website URL.
+    pub url: String,
+
+    /// Text describing the link.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Short label shown next to the name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub badge: Option<String>,
+
+    /// Site icon, as a PNG data URL.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+}
+
+impl LinksJson {
+    /// Creates an export of the links in the config.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The resolved config.
+    /// * `data_urls` - Site icon data URLs, keyed by website URL.
+    pub fn new(config: &Config, data_urls: &BTreeMap<String, String>) -> Self {
+        let default_page = config.default_page_index().unwrap_or_default();
+        Self {
+            version: LINKS_JSON_VERSION,
+            title: config.title.clone(),
+            pages: config
+                .pages
+                .iter()
+                .enumerate()
+                .map(|(i, page)| LinksJsonPage::new(page, i == default_page, data_urls))
+                .collect(),
+        }
+    }
+}
+
+impl LinksJsonPage {
+    /// Creates an export of a page.
+    fn new(page: &Page, default: bool, data_urls: &BTreeMap<String, String>) -> Self {
+        Self {
+            name: page.name.clone(),
+            icon: page.icon.clone(),
+            default,
+            sections: page
+                .sections
+                .iter()
+                .map(|s| LinksJsonSection::new(s, data_urls))
+                .collect(),
+        }
+    }
+}
+
+impl LinksJsonSection {
+    /// Creates an export of a section and its nested sections.
+    fn new(section: &Section, data_urls: &BTreeMap<String, String>) -> Self {
+        Self {
+            name: section.name.clone(),
+            description: section.description.clone(),
+            links: section
+                .links
+                .iter()
+                .map(|l| LinksJsonLink::new(l, data_urls))
+                .collect(),
+            sections: section
+                .sections
+                .iter()
+                .map(|s| LinksJsonSection::new(s, data_urls))
+                .collect(),
+        }
+    }
+}
+
+impl LinksJsonLink {
+    /// Creates an export of a link.
+    fn new(link: &Link, data_urls: &BTreeMap<String, String>) -> Self {
+        Self {
+            name: link.name.clone(),
+            url: link.url.clone(),
+            description: link.description.clone(),
+            badge: link.badge.clone(),
+            icon: data_urls.get(&link.url).cloned(),
+        }
+    }
+}
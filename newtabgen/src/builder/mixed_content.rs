@@ -0,0 +1,143 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Detects resources loaded over plain HTTP by a page hosted over HTTPS, which browsers block or
+//! mark as insecure.
+
+use std::collections::HashSet;
+
+use crate::config::{Config, Page};
+
+use super::report::{MixedContentKind, MixedContentReport};
+
+impl MixedContentKind {
+    /// Describes the kind of resource in messages.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::BackgroundImage => "background image",
+            Self::AppIcon => "app icon",
+            Self::Badge => "badge",
+            Self::Resource => "resource",
+        }
+    }
+
+    /// Suggests how to avoid loading this kind of resource over plain HTTP.
+    pub(crate) fn suggestion(self) -> &'static str {
+        match self {
+            Self::BackgroundImage => {
+                "set output.inline_insecure or theme.background_embed to embed it in the page"
+            }
+            Self::AppIcon => "host the icon next to the page, or use an https url",
+            Self::Badge => {
+                "use an https url, badges are fetched by the browser when the page opens"
+            }
+            Self::Resource => "use an https url, or embed the resource in the template",
+        }
+    }
+}
+
+/// Checks if the page is hosted over HTTPS, so plain HTTP resources are mixed content.
+pub(crate) fn hosted_securely(config: &Config) -> bool {
+    config
+        .output
+        .base_url
+        .as_deref()
+        .is_some_and(|url| url.to_ascii_lowercase().starts_with("https://"))
+}
+
+/// Checks if a URL is loaded over plain HTTP.
+fn insecure(url: &str) -> bool {
+    url.to_ascii_lowercase().starts_with("http://")
+}
+
+/// Finds resources in the config that would be loaded over plain HTTP, embedding them instead
+/// where possible if requested by `Output.inline_insecure`. Should only be used if the page is
+/// [hosted securely][hosted_securely].
+///
+/// # Returns
+///
+/// Each resource found, and whether it was embedded.
+pub(crate) fn inline_config(config: &mut Config) -> Vec<MixedContentReport> {
+    let mut reports = Vec::new();
+    let theme = &mut config.theme;
+    if let Some(url) = theme.background_image.as_deref().filter(|u| insecure(u)) {
+        // Embedded images are downloaded at build time, so only linked ones are a problem
+        if !theme.background_embed {
+            let inlined = config.output.inline_insecure;
+            theme.background_embed = inlined;
+            reports.push(MixedContentReport {
+                kind: MixedContentKind::BackgroundImage,
+                url: url.into(),
+                inlined,
+            });
+        }
+    }
+    if let Some(app) = &config.app {
+        for icon in app.icons.iter().filter(|i| insecure(&i.src)) {
+            reports.push(MixedContentReport {
+                kind: MixedContentKind::AppIcon,
+                url: icon.src.clone(),
+                inlined: false,
+            });
+        }
+    }
+    let badge_urls = config
+        .pages
+        .iter()
+        .flat_map(Page::all_links)
+        .filter_map(|l| l.badge_url.as_deref())
+        .filter(|u| insecure(u));
+    for url in badge_urls {
+        reports.push(MixedContentReport {
+            kind: MixedContentKind::Badge,
+            url: url.into(),
+            inlined: false,
+        });
+    }
+    reports
+}
+
+/// Finds resources referenced by the output over plain HTTP, in `src` attributes and CSS `url()`
+/// functions, quoted or not, that aren't already reported.
+///
+/// # Arguments
+///
+/// * `html` - The rendered output.
+/// * `reported` - Resources already found in the config.
+///
+/// # Returns
+///
+/// Each resource found, in the order it appears in the output.
+pub(crate) fn scan_output(html: &[u8], reported: &[MixedContentReport]) -> Vec<MixedContentReport> {
+    let html = String::from_utf8_lossy(html);
+    let mut seen = reported
+        .iter()
+        .map(|r| r.url.as_str())
+        .collect::<HashSet<_>>();
+    let mut reports = Vec::new();
+    for prefix in ["src=", "url("] {
+        for (start, _) in html.match_indices(prefix) {
+            // Minified output leaves attribute values unquoted where it can
+            let rest = &html[start + prefix.len()..];
+            let rest = rest.strip_prefix(['"', '\'']).unwrap_or(rest);
+            if !insecure(rest) {
+                continue;
+            }
+            let len = rest
+                .find(|c: char| matches!(c, '"' | '\'' | ')' | '<' | '>') || c.is_whitespace())
+                .unwrap_or(rest.len());
+            let url = &rest[..len];
+            if seen.insert(url) {
+                reports.push((start, url.to_owned()));
+            }
+        }
+    }
+    reports.sort_unstable();
+    reports
+        .into_iter()
+        .map(|(_, url)| MixedContentReport {
+            kind: MixedContentKind::Resource,
+            url,
+            inlined: false,
+        })
+        .collect()
+}
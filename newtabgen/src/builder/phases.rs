@@ -0,0 +1,580 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Exposes each phase of the build individually, so other tools can embed parts of a new tab page
+//! (e.g. only the site icon CSS) in their own pipelines.
+//!
+//! Each phase returns a typed artifact instead of writing to an output. [`build`][super::build]
+//! runs every phase and renders the artifacts into a single page with [`render_html`].
+//!
+//! Unlike [`build`][super::build], these functions use the config exactly as provided. Pages,
+//! sections, and links aren't sorted, link URLs aren't rewritten, and progress events aren't sent
+//! for the phases themselves.
+
+use std::{
+    collections::BTreeMap,
+    str::from_utf8,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use icu_locid::Locale;
+use resource::resource_str;
+use tera::{Context, Tera};
+use tokio::time::Instant;
+use tracing::{debug, info, span, warn, Level};
+
+use crate::{
+    config::{Config, Page},
+    tera_filters, tera_functions,
+};
+
+use super::{
+    background, debug_files,
+    diagnostic::{TemplateDiagnostic, HTML_TEMPLATE, SCSS_TEMPLATE},
+    feeds,
+    pwa::PwaFiles,
+    render_cache,
+    report::{BuildReport, LowContrastIconReport, SiteIconReport},
+    resource_hints, site_icons, svg_icons, theme_schedule, BuildError, BuildOptions, Minify,
+    TeraHook,
+};
+
+pub use super::background::{Background, DerivedColors};
+pub use super::feeds::{FeedContext, FeedItem};
+
+/// SVG symbol definitions for the page icons.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SvgIconDefs {
+    /// A hidden HTML `<svg>` element containing the symbol definitions.
+    pub html: String,
+}
+
+/// CSS classes for the site icons of each link.
+#[derive(Debug, Clone, Default)]
+pub struct SiteIconCss {
+    /// A `<style>` element containing the classes, followed by a `<template>` for each page with
+    /// deferred icons if `config.lazy_icons` is set.
+    pub html: String,
+
+    /// Each site icon, in the order they were built.
+    pub site_icons: Vec<SiteIconReport>,
+
+    /// Site icons with low contrast against the background, even after inversion.
+    pub low_contrast_icons: Vec<LowContrastIconReport>,
+
+    /// [Data URL][1] of each site icon, before inversion, keyed by website URL.
+    ///
+    /// [1]: <https://developer.mozilla.org/en-US/docs/Web/HTTP/Basics_of_HTTP/Data_URLs>
+    pub data_urls: BTreeMap<String, String>,
+}
+
+/// The compiled page styles.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Css {
+    /// Minified CSS.
+    pub css: String,
+}
+
+/// The rendered new tab page.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Html {
+    /// Minified HTML.
+    pub bytes: Vec<u8>,
+}
+
+/// The artifacts of every phase that the page is rendered from.
+#[derive(Debug, Clone, Default)]
+pub struct Artifacts {
+    /// Output of [`build_svg_icons`].
+    pub svg_icons: SvgIconDefs,
+
+    /// Output of [`build_site_icon_css`].
+    pub site_icons: SiteIconCss,
+
+    /// Output of [`build_feeds`].
+    pub feeds: Vec<FeedContext>,
+
+    /// Output of [`build_background`].
+    pub background: Background,
+
+    /// Output of [`render_css`].
+    pub css: Css,
+
+    /// Output of [`render_css`] for the other variant of the theme, with `Theme.dark` inverted.
+    /// Only used if `Theme.schedule` is provided, to switch between the variants.
+    pub alternate_css: Option<Css>,
+
+    /// Data URLs of the files bundled with the theme, keyed by their path within its `assets`
+    /// directory.
+    pub theme_assets: BTreeMap<String, String>,
+}
+
+/// Clones or updates the icons repository and builds symbol definitions for each page icon.
+///
+/// This blocks while the icons repository is cloned or pulled, so async callers should run it on
+/// a blocking thread.
+///
+/// # Errors
+///
+/// Returns an error if cloning the icon repo or processing the icons fails.
+pub fn build_svg_icons(config: &Config, options: &BuildOptions) -> Result<SvgIconDefs, BuildError> {
+    let html = svg_icons::build_svg_icons(config, options)?;
+    Ok(SvgIconDefs { html })
+}
+
+/// Fetches, processes, and converts the site icon of each link into CSS classes.
+///
+/// # Errors
+///
+/// Returns an error if any icon can't be obtained or processed, or if `options.strict` is set and
+/// an icon has low contrast against the background.
+pub async fn build_site_icon_css(
+    config: &Config,
+    options: &BuildOptions,
+) -> Result<SiteIconCss, BuildError> {
+    let mut report = BuildReport::default();
+    let (html, data_urls) =
+        site_icons::build_site_icons(config, config.build.icon_size, options, &mut report).await?;
+    Ok(SiteIconCss {
+        html,
+        data_urls,
+        site_icons: report.site_icons,
+        low_contrast_icons: report.low_contrast_icons,
+    })
+}
+
+/// Fetches and parses each feed in the config.
+///
+/// # Errors
+///
+/// Returns an error if the HTTP client can't be built. Errors related to individual feeds are
+/// exposed through [`FeedContext::error`] instead.
+pub async fn build_feeds(
+    config: &Config,
+    options: &BuildOptions,
+) -> Result<Vec<FeedContext>, BuildError> {
+    Ok(feeds::build_feeds(config, options.cache).await?)
+}
+
+/// Loads the background image, then embeds it and derives theme colors from it, if requested by
+/// the config.
+///
+/// # Errors
+///
+/// Returns an error if loading, decoding, or encoding the background image fails.
+pub async fn build_background(
+    config: &Config,
+    options: &BuildOptions,
+) -> Result<Background, BuildError> {
+    Ok(background::build_background(config, options.cache).await?)
+}
+
+/// Renders the SCSS template, then compiles the rendered SCSS into minified CSS, unless
+/// `Build.minify_css` is disabled.
+///
+/// # Arguments
+///
+/// * `src_scss` - The SCSS template to compile.
+/// * `config` - The config, provided to the template.
+/// * `background` - The background image and colors derived from it, provided to the template.
+/// * `theme_assets` - Data URLs of the files bundled with the theme, provided to the template.
+///
+/// # Errors
+///
+/// Returns an error if rendering the template, compiling the rendered SCSS, or encoding the
+/// compiled CSS into UTF-8 fails.
+pub fn render_css(
+    src_scss: &str,
+    config: &Config,
+    background: &Background,
+    theme_assets: &BTreeMap<String, String>,
+) -> Result<Css, BuildError> {
+    let options = BuildOptions {
+        render_cache: false,
+        ..Default::default()
+    };
+    render_css_cached(src_scss, config, background, theme_assets, &options)
+}
+
+/// Like [`render_css`], but minifies as requested by [`BuildOptions::minify`], and reuses the
+/// compiled CSS from the render cache if the template and its context haven't changed, caching it
+/// otherwise.
+pub(crate) fn render_css_cached(
+    src_scss: &str,
+    config: &Config,
+    background: &Background,
+    theme_assets: &BTreeMap<String, String>,
+    options: &BuildOptions,
+) -> Result<Css, BuildError> {
+    let _span = span!(Level::INFO, "css").entered();
+    info!("building css");
+    let sw = Instant::now();
+
+    let context = css_context(config, background, theme_assets);
+    let key = (options.uses_render_cache() && !timestamped(src_scss, config, options))
+        .then(|| render_cache::key(&format!("css:{:?}", options.minify), src_scss, &context))
+        .flatten();
+    if let Some(css) = key
+        .as_deref()
+        .and_then(render_cache::load)
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+    {
+        debug!(elapsed_ms = sw.elapsed().as_millis(), "reused cached css");
+        return Ok(Css { css });
+    }
+
+    let style = if config.build.minify_css && options.minify != Minify::Pretty {
+        rsass::output::Style::Compressed
+    } else {
+        rsass::output::Style::Expanded
+    };
+    let format = rsass::output::Format {
+        style,
+        ..Default::default()
+    };
+    let mut tera = tera(config, &options.tera_hooks);
+    register_build_info(&mut tera, config, options, None);
+    let rendered = tera
+        .render_str(src_scss, &context)
+        .map_err(|e| render_error(SCSS_TEMPLATE, src_scss, e))?;
+    let dumped = options
+        .debug_dir
+        .as_deref()
+        .and_then(|dir| debug_files::write(dir, "styles.scss", rendered.as_bytes()));
+    let compiled = rsass::compile_scss(rendered.as_bytes(), format).map_err(|e| {
+        let diagnostic =
+            TemplateDiagnostic::scss(&e.to_string(), &rendered, src_scss, dumped.as_deref());
+        BuildError::ScssCompile(e, Box::new(diagnostic))
+    })?;
+    let css = from_utf8(compiled.as_slice())
+        .map_err(BuildError::EncodeUtf8)?
+        .to_owned();
+    if let Some(dir) = &options.debug_dir {
+        debug_files::write(dir, "styles.css", css.as_bytes());
+    }
+    if let Some(key) = &key {
+        render_cache::store(key, css.as_bytes());
+    }
+
+    debug!(
+        elapsed_ms = sw.elapsed().as_millis(),
+        "finished building css"
+    );
+    Ok(Css { css })
+}
+
+/// Renders the HTML template with the artifacts of the other phases, then minifies the rendered
+/// HTML, unless `Build.minify_html` is disabled.
+///
+/// # Arguments
+///
+/// * `src_html` - The HTML template to compile.
+/// * `config` - The config, provided to the template.
+/// * `artifacts` - The artifacts to embed in the page.
+///
+/// # Errors
+///
+/// Returns an error if rendering the template fails.
+pub fn render_html(
+    src_html: &str,
+    config: &Config,
+    artifacts: &Artifacts,
+) -> Result<Html, BuildError> {
+    let options = BuildOptions {
+        render_cache: false,
+        ..Default::default()
+    };
+    render_html_cached(src_html, config, artifacts, &options)
+}
+
+/// Like [`render_html`], but minifies as requested by [`BuildOptions::minify`], and reuses the
+/// minified HTML from the render cache if the template and its context haven't changed, caching
+/// it otherwise.
+pub(crate) fn render_html_cached(
+    src_html: &str,
+    config: &Config,
+    artifacts: &Artifacts,
+    options: &BuildOptions,
+) -> Result<Html, BuildError> {
+    let _span = span!(Level::INFO, "html").entered();
+    info!("building html");
+    let sw = Instant::now();
+
+    let pwa_files = PwaFiles::new(options.pwa_file_prefix.as_deref());
+    let mut context = html_context(config, artifacts, &pwa_files);
+    let key = (options.uses_render_cache() && !timestamped(src_html, config, options))
+        .then(|| render_cache::key(&format!("html:{:?}", options.minify), src_html, &context))
+        .flatten();
+    if let Some(bytes) = key.as_deref().and_then(render_cache::load) {
+        debug!(elapsed_ms = sw.elapsed().as_millis(), "reused cached html");
+        return Ok(Html { bytes });
+    }
+
+    // Rendered after the cache lookup, since their sources and data are part of the config
+    let mut tera = tera(config, &options.tera_hooks);
+    register_build_info(&mut tera, config, options, Some(artifacts));
+    context.insert(
+        "page_templates",
+        &render_page_templates(&mut tera, config, &context)?,
+    );
+    let rendered = tera
+        .render_str(src_html, &context)
+        .map_err(|e| render_error(HTML_TEMPLATE, src_html, e))?;
+    if let Some(dir) = &options.debug_dir {
+        debug_files::write(dir, "index.html", rendered.as_bytes());
+    }
+    let bytes = match minify_html_cfg(options.minify) {
+        Some(cfg) if config.build.minify_html => minify_html::minify(rendered.as_bytes(), &cfg),
+        Some(_) => rendered.into_bytes(),
+        None => pretty_html(&rendered).into_bytes(),
+    };
+    if let Some(key) = &key {
+        render_cache::store(key, &bytes);
+    }
+
+    debug!(
+        elapsed_ms = sw.elapsed().as_millis(),
+        "finished building html"
+    );
+    Ok(Html { bytes })
+}
+
+/// Renders the templates of pages that provide one (see `Page.template`), with the context of the
+/// HTML template plus the page as `page` and its data as `data`.
+///
+/// # Errors
+///
+/// Returns an error if rendering a template fails.
+///
+/// # Returns
+///
+/// The rendered templates, keyed by page name.
+fn render_page_templates(
+    tera: &mut Tera,
+    config: &Config,
+    context: &Context,
+) -> Result<BTreeMap<String, String>, BuildError> {
+    let mut rendered = BTreeMap::new();
+    for page in &config.pages {
+        let Some(src) = &page.template_src else {
+            continue;
+        };
+        let mut context = context.clone();
+        context.insert("page", page);
+        context.insert("data", &page.data);
+        let name = page.template.as_deref().unwrap_or("page template");
+        let html = tera
+            .render_str(src, &context)
+            .map_err(|e| render_error(name, src, e))?;
+        rendered.insert(page.name.clone(), html);
+    }
+    Ok(rendered)
+}
+
+/// Checks if the template or any page template may render the build timestamp, which would go
+/// stale if the rendered output were reused from the render cache.
+fn timestamped(src: &str, config: &Config, options: &BuildOptions) -> bool {
+    let uses_build_info = |src: &str| src.contains("build_info");
+    !options.reproducible
+        && (uses_build_info(src)
+            || (config.pages.iter())
+                .filter_map(|p| p.template_src.as_deref())
+                .any(uses_build_info))
+}
+
+/// Registers `build_info()` with everything known about the build so far, replacing the version
+/// registered by [`tera`], which only knows about the config.
+fn register_build_info(
+    tera: &mut Tera,
+    config: &Config,
+    options: &BuildOptions,
+    artifacts: Option<&Artifacts>,
+) {
+    let built_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .ok();
+    let info = tera_functions::BuildInfo {
+        built_at: built_at.filter(|_| !options.reproducible),
+        site_icons: artifacts.map(|a| a.site_icons.site_icons.len()),
+        ..tera_functions::BuildInfo::new(config)
+    };
+    tera.register_function("build_info", info);
+}
+
+/// Wraps an error from rendering the HTML or SCSS template, explaining where it occurred.
+fn render_error(template: &str, src: &str, error: tera::Error) -> BuildError {
+    let diagnostic = TemplateDiagnostic::tera(template, src, &error);
+    BuildError::Render(error, Box::new(diagnostic))
+}
+
+/// Creates the HTML minifier settings for a level of minification.
+///
+/// # Returns
+///
+/// The settings, or `None` if the HTML shouldn't be minified.
+fn minify_html_cfg(minify: Minify) -> Option<minify_html::Cfg> {
+    let cfg = match minify {
+        Minify::Pretty => return None,
+        Minify::Safe => minify_html::Cfg {
+            do_not_minify_doctype: true,
+            ensure_spec_compliant_unquoted_attribute_values: true,
+            keep_closing_tags: true,
+            keep_html_and_head_opening_tags: true,
+            keep_spaces_between_attributes: true,
+            ..Default::default()
+        },
+        Minify::Standard => minify_html::Cfg::default(),
+        Minify::Aggressive => minify_html::Cfg {
+            minify_css: true,
+            minify_js: true,
+            remove_bangs: true,
+            remove_processing_instructions: true,
+            ..Default::default()
+        },
+    };
+    Some(cfg)
+}
+
+/// Tidies rendered HTML for reading, by removing the blank lines left behind by template tags and
+/// trailing whitespace. Lines within `<pre>` elements are kept as is, since their whitespace is
+/// significant.
+fn pretty_html(rendered: &str) -> String {
+    let mut pretty = String::with_capacity(rendered.len());
+    let mut in_pre = false;
+    for line in rendered.lines() {
+        let keep = in_pre || !line.trim().is_empty();
+        if line.contains("<pre") {
+            in_pre = true;
+        }
+        if line.contains("</pre>") {
+            in_pre = false;
+        }
+        if keep {
+            pretty.push_str(if in_pre { line } else { line.trim_end() });
+            pretty.push('\n');
+        }
+    }
+    pretty
+}
+
+/// Renders a plain list of every link in the config, for [`Target::StaticList`][super::Target].
+///
+/// # Errors
+///
+/// Returns an error if rendering the template fails.
+pub fn render_static_list(config: &Config) -> Result<Html, BuildError> {
+    let _span = span!(Level::INFO, "html").entered();
+    info!("building static list");
+
+    let src = resource_str!("res/static-list.html").to_string();
+    let rendered = tera(config, &[])
+        .render_str(&src, &context(config))
+        .map_err(BuildError::Template)?;
+    Ok(Html {
+        bytes: rendered.into_bytes(),
+    })
+}
+
+/// Creates a template renderer with the filters and functions available to templates, including
+/// those registered by `hooks` (see [`BuildOptions::tera_hooks`]).
+pub(crate) fn tera(config: &Config, hooks: &[TeraHook]) -> Tera {
+    let locale = match config.locale.as_deref().map(str::parse::<Locale>) {
+        Some(Ok(locale)) => locale,
+        Some(Err(_)) => {
+            warn!(
+                locale = config.locale,
+                "invalid locale, formatting with locale-neutral rules"
+            );
+            Locale::UND
+        }
+        None => Locale::UND,
+    };
+
+    let mut tera = Tera::default();
+    tera.register_filter("hash", tera_filters::Hash);
+    tera.register_filter("site_icon", tera_filters::SiteIcon);
+    tera.register_filter("markdown", tera_filters::Markdown);
+    tera.register_filter("slugify", tera_filters::Slugify::default());
+    tera.register_filter("contrast_color", tera_filters::ContrastColor);
+    tera.register_filter("truncate_middle", tera_filters::TruncateMiddle);
+    tera.register_filter("url_host", tera_filters::UrlHost);
+    tera.register_filter("url_path", tera_filters::UrlPath);
+    tera.register_filter("url_origin", tera_filters::UrlOrigin);
+    tera.register_filter("format_number", tera_filters::FormatNumber(locale.clone()));
+    tera.register_filter("format_bytes", tera_filters::FormatBytes(locale.clone()));
+    tera.register_filter("format_duration", tera_filters::FormatDuration(locale));
+    tera.register_function("len", tera_functions::Len);
+    tera.register_function("svg_icon_href", tera_functions::SvgIconHref);
+    tera.register_function("material_svg", tera_functions::MaterialSvg);
+    tera.register_function(
+        "count_links_in_page",
+        tera_functions::CountLinksInPage(config.clone()),
+    );
+    tera.register_function(
+        "links_by_domain",
+        tera_functions::LinksByDomain(config.clone()),
+    );
+    tera.register_function("links_by_tag", tera_functions::LinksByTag(config.clone()));
+    tera.register_function("build_info", tera_functions::BuildInfo::new(config));
+    for hook in hooks {
+        hook.apply(&mut tera);
+    }
+    tera
+}
+
+/// Creates the template context of the SCSS template.
+pub(crate) fn css_context(
+    config: &Config,
+    background: &Background,
+    theme_assets: &BTreeMap<String, String>,
+) -> Context {
+    let mut context = context(config);
+    context.insert("derived_colors", &background.derived_colors);
+    context.insert("background_image", &background.image_url);
+    context.insert("theme_assets", theme_assets);
+    context
+}
+
+/// Creates the template context of the HTML template.
+pub(crate) fn html_context(
+    config: &Config,
+    artifacts: &Artifacts,
+    pwa_files: &PwaFiles,
+) -> Context {
+    let mut context = context(config);
+    context.insert("pwa_files", pwa_files);
+    context.insert("include_svg_icons", &artifacts.svg_icons.html);
+    context.insert("include_site_icons", &artifacts.site_icons.html);
+    context.insert("feeds", &artifacts.feeds);
+    context.insert("derived_colors", &artifacts.background.derived_colors);
+    context.insert("background_image", &artifacts.background.image_url);
+    context.insert("theme_assets", &artifacts.theme_assets);
+    context.insert("resource_hints", &resource_hints::origins(config));
+    context.insert(
+        "include_styles",
+        &theme_schedule::include_styles(config, &artifacts.css, artifacts.alternate_css.as_ref()),
+    );
+    // Filled in when rendering, since page templates are rendered with this context
+    context.insert("page_templates", &BTreeMap::<String, String>::new());
+    context
+}
+
+/// Creates the template context shared by all templates.
+fn context(config: &Config) -> Context {
+    let mut context = Context::new();
+    context.insert("config", config);
+    context.insert(
+        "default_page",
+        &config.default_page_index().unwrap_or_default(),
+    );
+    context.insert(
+        "runtime_badges",
+        &config
+            .pages
+            .iter()
+            .flat_map(Page::all_links)
+            .any(|l| l.badge_url.is_some()),
+    );
+    context.insert("density_scale", &config.theme.density.scale());
+    context
+}
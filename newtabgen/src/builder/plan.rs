@@ -0,0 +1,176 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Lists the network operations a build would perform without performing them, so users can
+//! audit what will be contacted.
+
+use std::{fmt, path::Path};
+
+use itertools::Itertools;
+use serde::Serialize;
+use url::Url;
+
+use crate::{
+    config::Page,
+    resources::{self, ResourceSet},
+};
+
+use super::{
+    feeds, resolve_config, site_icons, svg_icons, template_names, used_icon_phases, BuildError,
+    BuildOptions, CacheMode, Target,
+};
+
+/// What a network operation is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkKind {
+    /// Downloading a remote template, theme, or other resource.
+    Resource,
+
+    /// Cloning or updating the material design icons repository.
+    IconRepo,
+
+    /// Locating and downloading the icon of a website.
+    SiteIcon,
+
+    /// Downloading the background image.
+    Background,
+
+    /// Downloading an RSS/Atom feed.
+    Feed,
+}
+
+impl fmt::Display for NetworkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NetworkKind::Resource => "resource",
+            NetworkKind::IconRepo => "icon repo",
+            NetworkKind::SiteIcon => "site icon",
+            NetworkKind::Background => "background",
+            NetworkKind::Feed => "feed",
+        })
+    }
+}
+
+/// A network operation a build would perform.
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkOperation {
+    /// What the operation is for.
+    pub kind: NetworkKind,
+
+    /// The URL contacted. Site icons are located by loading the website first, so the icon
+    /// itself may be downloaded from elsewhere.
+    pub url: String,
+
+    /// What's done with the URL, e.g. "clone" or "download".
+    pub action: &'static str,
+}
+
+/// Lists the network operations building with the provided resources and options would perform,
+/// in the order the phases start. Downloads that would be read from the cache instead are left
+/// out, so nothing is listed while offline.
+///
+/// Nothing is downloaded and the cache isn't changed, but the config is loaded and resolved, and
+/// the templates are parsed to find which phases are needed.
+///
+/// # Errors
+///
+/// Returns an error if loading or resolving the config or templates fails.
+pub async fn plan_network(
+    resources: impl ResourceSet,
+    options: &BuildOptions,
+) -> Result<Vec<NetworkOperation>, BuildError> {
+    let config = resolve_config(&resources)?;
+    if options.target == Target::StaticList {
+        return Ok(Vec::new());
+    }
+    let src_html = resources.html()?;
+    let src_scss = resources.scss()?;
+    let template_names = template_names(&src_html, &src_scss)?;
+    let (svg_icons_used, site_icons_used) = used_icon_phases(&src_scss, &template_names);
+
+    let mut operations = Vec::new();
+    let repo_operation = svg_icons::planned_repo_operation(
+        config.icon_repo_rev.as_deref(),
+        config.icon_repo_update_hours,
+        options,
+    );
+    if let (true, Some(action)) = (svg_icons_used, repo_operation) {
+        operations.push(NetworkOperation {
+            kind: NetworkKind::IconRepo,
+            url: svg_icons::ICONS_REPO_URL.into(),
+            action,
+        });
+    }
+    if site_icons_used {
+        let urls = config
+            .pages
+            .iter()
+            .flat_map(Page::all_links)
+            .map(|l| l.url.as_str())
+            .unique()
+            .collect::<Vec<_>>();
+        // Links sharing an icon only fetch it for the first of them
+        for url in site_icons::icon_groups(&config, &urls)
+            .into_iter()
+            .map(|group| group[0])
+        {
+            let operation = match site_icons::icon_override(&config, url) {
+                Some(source) => resources::remote_url(Path::new(source))
+                    .filter(|source| resources::needs_download(source, options.cache))
+                    .map(|source| NetworkOperation {
+                        kind: NetworkKind::SiteIcon,
+                        url: source.to_string(),
+                        action: "download icon override",
+                    }),
+                None => site_icons::icon_needs_fetch(url, options).then(|| NetworkOperation {
+                    kind: NetworkKind::SiteIcon,
+                    url: url.into(),
+                    action: match config.icon_fallback {
+                        Some(_) => "locate, else ask service",
+                        None => "locate and download icon",
+                    },
+                }),
+            };
+            // Overrides shared by several websites are only downloaded once
+            if let Some(operation) = operation {
+                if !operations.iter().any(|o| o.url == operation.url) {
+                    operations.push(operation);
+                }
+            }
+        }
+    }
+    let theme = &config.theme;
+    if let Some(source) = &theme.background_image {
+        let remote = source.starts_with("http://") || source.starts_with("https://");
+        if remote
+            && (theme.background_embed || theme.derive_colors)
+            && options.cache != CacheMode::Offline
+        {
+            operations.push(NetworkOperation {
+                kind: NetworkKind::Background,
+                url: source.clone(),
+                action: "download",
+            });
+        }
+    }
+    operations.extend(
+        (config.widgets.feeds.iter())
+            .filter(|feed| feeds::feed_needs_download(&feed.url, options.cache))
+            .map(|feed| NetworkOperation {
+                kind: NetworkKind::Feed,
+                url: feed.url.clone(),
+                action: "download",
+            }),
+    );
+    Ok(operations)
+}
+
+/// Describes downloading a remote resource, such as a template or theme given as a URL, unless
+/// it would be read from the cache.
+pub fn plan_resource(url: &Url, options: &BuildOptions) -> Option<NetworkOperation> {
+    resources::needs_download(url, options.cache).then(|| NetworkOperation {
+        kind: NetworkKind::Resource,
+        url: url.to_string(),
+        action: "download",
+    })
+}
@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides the [`Progress`] events sent while building, allowing callers to display progress.
+
+use std::fmt;
+
+use tokio::sync::mpsc::UnboundedSender;
+
+/// A step of the build process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Building SVG symbol defs from the icons repository.
+    SvgIcons,
+
+    /// Fetching and processing site icons.
+    SiteIcons,
+
+    /// Fetching and parsing feeds.
+    Feeds,
+
+    /// Loading the background image.
+    Background,
+
+    /// Rendering and compiling the styles.
+    Css,
+
+    /// Rendering and minifying the page.
+    Html,
+}
+
+impl Phase {
+    /// Returns the name of the phase, as used in logs and reports.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Phase::SvgIcons => "svg_icons",
+            Phase::SiteIcons => "site_icons",
+            Phase::Feeds => "feeds",
+            Phase::Background => "background",
+            Phase::Css => "css",
+            Phase::Html => "html",
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An event describing the progress of a build.
+#[derive(Debug, Clone)]
+pub enum Progress {
+    /// Occurs when a phase starts.
+    PhaseStarted(Phase),
+
+    /// Occurs when an item within a phase (e.g. a single icon) is done.
+    Item {
+        /// The phase the item belongs to.
+        phase: Phase,
+
+        /// Number of items done so far, including this one.
+        current: usize,
+
+        /// Total number of items in the phase.
+        total: usize,
+    },
+
+    /// Occurs when a phase finishes.
+    PhaseFinished(Phase),
+
+    /// Occurs when the build finishes, after the output has been written.
+    Finished {
+        /// Size of the output, in bytes.
+        output_bytes: usize,
+    },
+}
+
+/// Sends [`Progress`] events, if anyone is listening.
+#[derive(Debug, Clone, Default)]
+pub struct ProgressSender(Option<UnboundedSender<Progress>>);
+
+impl ProgressSender {
+    /// Creates a sender that forwards events to the provided channel.
+    pub fn new(sender: UnboundedSender<Progress>) -> Self {
+        Self(Some(sender))
+    }
+
+    /// Sends an event. Events are dropped if there's no receiver.
+    pub(crate) fn send(&self, event: Progress) {
+        if let Some(sender) = &self.0 {
+            let _ = sender.send(event);
+        }
+    }
+}
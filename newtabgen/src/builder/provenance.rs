@@ -0,0 +1,108 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides the [`Provenance`] record, which can be embedded in a built page as an HTML comment so
+//! that the page can later be verified by rebuilding it.
+
+use std::fmt;
+
+use crate::{config::Config, util};
+
+use super::{svg_icons, BuildError};
+
+/// Opening of the HTML comment that holds a [`Provenance`] record.
+const COMMENT_START: &str = "<!-- newtabgen provenance:";
+
+/// Closing of the HTML comment that holds a [`Provenance`] record.
+const COMMENT_END: &str = "-->";
+
+/// Records the inputs a new tab page was built from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    /// Version of the newtabgen library that built the page.
+    pub version: String,
+
+    /// Hash of the parsed configuration.
+    pub config_hash: String,
+
+    /// Hash of the `Cargo.lock` the library was compiled with, or "unknown" if it wasn't available.
+    pub lockfile_hash: String,
+
+    /// ID of the material design icons repository commit the SVG icons were taken from.
+    pub icon_repo_commit: String,
+}
+
+/// Hashes the parsed configuration, so builds from the same config can be recognized.
+///
+/// # Errors
+///
+/// Returns an error if the config can't be serialized.
+pub(crate) fn config_hash(config: &Config) -> Result<String, serde_json::Error> {
+    // Round-trip through `Value` so maps are serialized in a stable order
+    let config_json = serde_json::to_value(config)?.to_string();
+    Ok(util::sha1_base32(config_json.as_bytes()))
+}
+
+impl Provenance {
+    /// Collects the provenance of a build using the provided config. Must be called after the SVG
+    /// icons have been built, since that's when the icons repository is cloned or updated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the config can't be serialized or the icons repository can't be read.
+    pub(crate) fn collect(config: &Config) -> Result<Self, BuildError> {
+        Ok(Self {
+            version: env!("CARGO_PKG_VERSION").into(),
+            config_hash: config_hash(config)?,
+            lockfile_hash: env!("NEWTABGEN_LOCKFILE_HASH").into(),
+            icon_repo_commit: svg_icons::icons_repo_commit()?,
+        })
+    }
+
+    /// Extracts a provenance record from a built page.
+    ///
+    /// # Returns
+    ///
+    /// The last provenance record found in the page, or `None` if the page doesn't contain a
+    /// complete record.
+    pub fn from_html(html: &[u8]) -> Option<Self> {
+        let html = String::from_utf8_lossy(html);
+        let start = html.rfind(COMMENT_START)? + COMMENT_START.len();
+        let len = html[start..].find(COMMENT_END)?;
+
+        let mut version = None;
+        let mut config_hash = None;
+        let mut lockfile_hash = None;
+        let mut icon_repo_commit = None;
+        for (key, value) in html[start..start + len]
+            .split_whitespace()
+            .filter_map(|pair| pair.split_once('='))
+        {
+            let field = match key {
+                "version" => &mut version,
+                "config" => &mut config_hash,
+                "lockfile" => &mut lockfile_hash,
+                "icon_repo" => &mut icon_repo_commit,
+                _ => continue,
+            };
+            *field = Some(value.to_string());
+        }
+
+        Some(Self {
+            version: version?,
+            config_hash: config_hash?,
+            lockfile_hash: lockfile_hash?,
+            icon_repo_commit: icon_repo_commit?,
+        })
+    }
+}
+
+impl fmt::Display for Provenance {
+    /// Formats the record as an HTML comment.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{COMMENT_START} version={} config={} lockfile={} icon_repo={} {COMMENT_END}",
+            self.version, self.config_hash, self.lockfile_hash, self.icon_repo_commit
+        )
+    }
+}
@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Generates the web app manifest and service worker that let the page be installed as a
+//! progressive web app.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{config::Config, util};
+
+/// File name of the web app manifest, which the page links to.
+pub(crate) const MANIFEST_FILE_NAME: &str = "manifest.webmanifest";
+
+/// File name of the service worker, which the page registers.
+pub(crate) const SERVICE_WORKER_FILE_NAME: &str = "sw.js";
+
+/// File names of the web app manifest and service worker of a page, available to templates as
+/// `pwa_files`. Pages built into the same directory need different names, see
+/// [`BuildOptions::pwa_file_prefix`][super::BuildOptions::pwa_file_prefix].
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct PwaFiles {
+    /// File name of the web app manifest.
+    pub manifest: String,
+
+    /// File name of the service worker.
+    pub service_worker: String,
+
+    /// Prefix of the names of the caches the service worker creates.
+    #[serde(skip)]
+    pub cache_prefix: String,
+}
+
+impl PwaFiles {
+    /// Names the files after the provided prefix, or uses the default names if there is none.
+    pub(crate) fn new(prefix: Option<&str>) -> Self {
+        match prefix {
+            Some(prefix) => Self {
+                manifest: format!("{prefix}.webmanifest"),
+                service_worker: format!("{prefix}.{SERVICE_WORKER_FILE_NAME}"),
+                cache_prefix: format!("newtabgen-{prefix}-"),
+            },
+            None => Self {
+                manifest: MANIFEST_FILE_NAME.into(),
+                service_worker: SERVICE_WORKER_FILE_NAME.into(),
+                cache_prefix: "newtabgen-".into(),
+            },
+        }
+    }
+}
+
+impl Default for PwaFiles {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+/// Creates a web app manifest from `config.app`.
+///
+/// # Arguments
+///
+/// * `config` - The config to read the app name, icons, and colors from.
+/// * `page_file_name` - File name of the page, which the app starts at.
+///
+/// # Returns
+///
+/// The manifest, or `None` if `config.app` isn't provided.
+pub(crate) fn manifest(config: &Config, page_file_name: &str) -> Option<Value> {
+    let app = config.app.as_ref()?;
+    let name = app.name.clone().unwrap_or_else(|| config.title.clone());
+    let mut manifest = json!({
+        "name": name,
+        "short_name": app.short_name.clone().unwrap_or_else(|| name.clone()),
+        "start_url": format!("./{page_file_name}"),
+        "display": "standalone",
+        "icons": app.icons,
+    });
+    let colors = [
+        ("theme_color", &app.theme_color),
+        ("background_color", &app.background_color),
+    ];
+    for (key, color) in colors {
+        if let Some(color) = color {
+            manifest[key] = json!(color);
+        }
+    }
+    Some(manifest)
+}
+
+/// Creates a service worker that serves the page and manifest from the network, falling back to a
+/// cached copy while offline. The cache is named after a hash of the page, so each build replaces
+/// the previous cache, leaving the caches of pages with other file names alone.
+///
+/// # Arguments
+///
+/// * `page` - The built page.
+/// * `page_file_name` - File name of the page.
+/// * `files` - File names of the manifest and service worker of the page.
+pub(crate) fn service_worker(page: &[u8], page_file_name: &str, files: &PwaFiles) -> String {
+    let prefix = &files.cache_prefix;
+    let cache = format!("{prefix}{}", util::sha1_base32(page));
+    let files = json!([
+        format!("./{page_file_name}"),
+        format!("./{}", files.manifest)
+    ]);
+    format!(
+        r#"const PREFIX = "{prefix}";
+const CACHE = "{cache}";
+const FILES = {files};
+
+self.addEventListener("install", (event) => {{
+    event.waitUntil(
+        caches.open(CACHE).then((cache) => cache.addAll(FILES)).then(() => self.skipWaiting()),
+    );
+}});
+
+self.addEventListener("activate", (event) => {{
+    event.waitUntil(
+        caches
+            .keys()
+            .then((keys) => keys.filter((k) => k.startsWith(PREFIX) && k !== CACHE))
+            .then((keys) => Promise.all(keys.map((k) => caches.delete(k))))
+            .then(() => self.clients.claim()),
+    );
+}});
+
+self.addEventListener("fetch", (event) => {{
+    if (event.request.method !== "GET") return;
+    event.respondWith(
+        fetch(event.request)
+            .then((response) => {{
+                if (response.ok && new URL(event.request.url).origin === location.origin) {{
+                    const copy = response.clone();
+                    caches.open(CACHE).then((cache) => cache.put(event.request, copy));
+                }}
+                return response;
+            }})
+            .catch(() => caches.match(event.request, {{ ignoreSearch: true }})),
+    );
+}});
+"#
+    )
+}
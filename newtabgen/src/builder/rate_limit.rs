@@ -0,0 +1,59 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Spaces out requests sent to the same host, so building a config with many links to one website
+//! doesn't hammer its server or trip rate limits in front of it.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use url::Url;
+
+/// Hands out the times at which requests to each host may be sent, at least `interval` apart. Site
+/// icons are fetched concurrently, so [`IconFetcher`][super::icon_fetcher::IconFetcher]s share one
+/// limiter for the whole build.
+#[derive(Debug)]
+pub struct HostRateLimiter {
+    /// Shortest time between the start of two requests to the same host.
+    interval: Duration,
+
+    /// Earliest time the next request to each host may be sent.
+    next: Mutex<HashMap<String, Instant>>,
+}
+
+impl HostRateLimiter {
+    /// Creates a limiter that hasn't seen any requests yet. A zero interval doesn't limit requests,
+    /// e.g. to call [`IconFetcher::fetch_with_source`][super::icon_fetcher::IconFetcher] outside of
+    /// a build.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next: Mutex::default(),
+        }
+    }
+
+    /// Waits until a request to the host of the provided URL may be sent, reserving that slot.
+    /// Returns immediately if the URL has no host or the interval is zero.
+    pub async fn wait(&self, url: &str) {
+        if self.interval.is_zero() {
+            return;
+        }
+        let host = match Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_owned))
+        {
+            Some(host) => host,
+            None => return,
+        };
+        let at = {
+            let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let at = next.get(&host).map_or(now, |t| (*t).max(now));
+            next.insert(host, at + self.interval);
+            at
+        };
+        tokio::time::sleep_until(at.into()).await;
+    }
+}
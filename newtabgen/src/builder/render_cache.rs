@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Caches rendered templates, so rebuilds where only site icons or feeds expired skip Tera and
+//! rsass entirely.
+//!
+//! Entries are keyed by a hash of the template source and its serialized context. Since the
+//! context includes the config and every artifact embedded in the output, any change that could
+//! affect the rendered template results in a different key. The crate version is hashed too, so
+//! entries aren't reused after the renderer or its filters change.
+
+use std::{fs, path::PathBuf};
+
+use sha1::{Digest, Sha1};
+use tera::Context;
+use tracing::{debug, warn};
+
+use crate::util;
+
+/// Name of the cache subdirectory holding rendered templates.
+const CACHE_SUBDIR: &str = "render";
+
+/// Number of seconds after which entries that haven't been used are removed.
+const MAX_AGE_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Computes the cache key of a rendered template.
+///
+/// # Arguments
+///
+/// * `kind` - Kind of output, so the same template rendered in different ways doesn't collide.
+/// * `src` - The template source.
+/// * `context` - The context the template is rendered with.
+///
+/// # Returns
+///
+/// The key, or `None` if the context can't be serialized.
+pub(crate) fn key(kind: &str, src: &str, context: &Context) -> Option<String> {
+    let context = serde_json::to_vec(&context.clone().into_json()).ok()?;
+    let mut hasher = Sha1::new();
+    for part in [
+        env!("CARGO_PKG_VERSION").as_bytes(),
+        kind.as_bytes(),
+        src.as_bytes(),
+        &context,
+    ] {
+        // Length-prefix each part so their boundaries can't be shifted
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    Some(hex::encode(hasher.finalize()))
+}
+
+/// Finds the path of a cache entry.
+fn entry_path(key: &str) -> Option<PathBuf> {
+    match util::cache_subdir(CACHE_SUBDIR) {
+        Ok(dir) => Some(dir.join(key)),
+        Err(_) => {
+            warn!("failed to find render cache directory");
+            None
+        }
+    }
+}
+
+/// Reads a rendered template from the cache.
+///
+/// # Returns
+///
+/// The rendered template, or `None` if it isn't cached.
+pub(crate) fn load(key: &str) -> Option<Vec<u8>> {
+    let path = entry_path(key)?;
+    let bytes = fs::read(&path).ok()?;
+    debug!(path = path.to_str(), "reading cached render");
+    util::touch(&path);
+    Some(bytes)
+}
+
+/// Writes a rendered template to the cache, and removes entries that haven't been used in a
+/// while. Failures are logged rather than returned, since the cache is only an optimization.
+pub(crate) fn store(key: &str, bytes: &[u8]) {
+    let Some(path) = entry_path(key) else {
+        return;
+    };
+    debug!(path = path.to_str(), "writing render to cache");
+    if fs::write(&path, bytes).is_err() {
+        warn!(path = path.to_str(), "failed to write render to cache");
+    }
+    prune();
+}
+
+/// Removes entries from the cache that haven't been read or written in a while.
+fn prune() {
+    let Ok(dir) = util::cache_subdir(CACHE_SUBDIR) else {
+        return;
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.flatten().map(|e| e.path()) {
+        if util::cache_unused(&path, MAX_AGE_SECS) && fs::remove_file(&path).is_err() {
+            warn!(
+                path = path.to_str(),
+                "failed to remove unused render from cache"
+            );
+        }
+    }
+}
@@ -0,0 +1,330 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides the [`BuildReport`] struct, a machine-readable summary of a build.
+
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::time::Instant;
+
+use super::progress::Phase;
+use crate::config::BuildLayout;
+
+/// A machine-readable summary of a build.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BuildReport {
+    /// Name of the profile that was built, if any (see `Config.profiles`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+
+    /// Layout the page was built for (see `Build.layout`).
+    pub layout: BuildLayout,
+
+    /// Total time spent building, in milliseconds.
+    pub elapsed_ms: u128,
+
+    /// Size of the final output, in bytes.
+    pub output_bytes: usize,
+
+    /// Number of site icons read from the cache.
+    pub cache_hits: usize,
+
+    /// Number of site icons fetched from the network.
+    pub cache_misses: usize,
+
+    /// Problems found during the build that didn't stop it, in the order they were found. Most
+    /// are also described in more detail by the other fields.
+    pub warnings: Vec<Warning>,
+
+    /// Each build phase. Some phases run concurrently, so their elapsed times may overlap.
+    pub phases: Vec<PhaseReport>,
+
+    /// Each site icon, in the order they were built.
+    pub site_icons: Vec<SiteIconReport>,
+
+    /// URLs of site icons that changed since they were last fetched, e.g. because the site was
+    /// rebranded.
+    pub changed_icons: Vec<String>,
+
+    /// Site icons with low contrast against the background, even after inversion.
+    pub low_contrast_icons: Vec<LowContrastIconReport>,
+
+    /// Theme colors with low contrast against the background (see `Theme.min_contrast`).
+    pub low_contrast_colors: Vec<ThemeContrastReport>,
+
+    /// Custom theme keys that no template references, which are likely typos.
+    pub unused_theme_keys: Vec<String>,
+
+    /// Inline styles and data URLs large enough that some browsers may struggle to render them.
+    pub oversized: Vec<OversizedReport>,
+
+    /// Resources loaded over plain HTTP by a page hosted over HTTPS (see `Output.base_url`).
+    pub mixed_content: Vec<MixedContentReport>,
+
+    /// URLs linked more than once across the config. Only the first occurrence is kept if
+    /// `BuildOptions::dedupe` is set.
+    pub duplicate_links: Vec<DuplicateLinkReport>,
+
+    /// Accessibility problems found in the output, if it was audited (see
+    /// `BuildOptions::a11y_check`).
+    pub a11y_issues: Vec<A11yIssueReport>,
+
+    /// Report of the mobile variant built alongside the page, if any (see `Build.mobile_variant`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mobile_variant: Option<Box<BuildReport>>,
+}
+
+/// A problem found during a build that didn't stop it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Warning {
+    /// Kind of problem.
+    pub kind: WarningKind,
+
+    /// Description of the problem, and how to fix it if known.
+    pub message: String,
+}
+
+/// Kinds of problems that may be found during a build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// A theme color with low contrast against the background, see
+    /// [`BuildReport::low_contrast_colors`].
+    LowContrastColor,
+
+    /// A site icon with low contrast against the background, see
+    /// [`BuildReport::low_contrast_icons`].
+    LowContrastIcon,
+
+    /// A custom theme key that no template references, see [`BuildReport::unused_theme_keys`].
+    UnusedThemeKey,
+
+    /// Content exceeding its size limit, see [`BuildReport::oversized`].
+    Oversized,
+
+    /// A resource loaded over plain HTTP by a page hosted over HTTPS, see
+    /// [`BuildReport::mixed_content`].
+    MixedContent,
+
+    /// An accessibility problem, see [`BuildReport::a11y_issues`].
+    A11y,
+
+    /// A URL linked more than once, see [`BuildReport::duplicate_links`].
+    DuplicateLink,
+
+    /// Output that may not be reproducible, despite `BuildOptions::reproducible`.
+    Unreproducible,
+}
+
+/// Summary of a single build phase.
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseReport {
+    /// Phase name, e.g. "svg_icons" or "html".
+    pub name: String,
+
+    /// Time spent in the phase, in milliseconds.
+    pub elapsed_ms: u128,
+
+    /// Size of the phase's output, in bytes.
+    pub output_bytes: usize,
+}
+
+/// A URL linked more than once across the config.
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateLinkReport {
+    /// The URL, as first linked.
+    pub url: String,
+
+    /// Where the URL is linked, as the page name followed by the section names, e.g.
+    /// `Home / Dev / Docs`. A location appears once for each link in it.
+    pub locations: Vec<String>,
+}
+
+/// Summary of a single site icon.
+#[derive(Debug, Clone, Serialize)]
+pub struct SiteIconReport {
+    /// URL of the website the icon belongs to.
+    pub url: String,
+
+    /// Indicates if the icon was read from the cache instead of fetched from the network.
+    pub cached: bool,
+
+    /// Time spent loading and processing the icon, in milliseconds.
+    pub elapsed_ms: u128,
+}
+
+/// Summary of a site icon with low contrast against the background.
+#[derive(Debug, Clone, Serialize)]
+pub struct LowContrastIconReport {
+    /// URL of the website the icon belongs to.
+    pub url: String,
+
+    /// Contrast ratio between the icon and the background, ranging from 1 to 21.
+    pub contrast: f32,
+}
+
+/// A theme color with low contrast against the background.
+#[derive(Debug, Clone, Serialize)]
+pub struct ThemeContrastReport {
+    /// Which colors are compared.
+    pub pair: ThemeColorPair,
+
+    /// The color drawn over the background, as configured.
+    pub foreground: String,
+
+    /// The background color, as configured.
+    pub background: String,
+
+    /// Contrast ratio between the colors, ranging from 1 to 21.
+    pub contrast: f32,
+
+    /// Minimum contrast ratio it falls short of, see `Theme.min_contrast`.
+    pub minimum: f32,
+}
+
+/// Pairs of theme colors that are checked for contrast.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeColorPair {
+    /// The text color against the background.
+    Text,
+
+    /// The accent color against the background.
+    Accent,
+}
+
+/// Inline content in the output that exceeds its size limit.
+#[derive(Debug, Clone, Serialize)]
+pub struct OversizedReport {
+    /// Kind of content.
+    pub kind: OversizedKind,
+
+    /// Size of the content, in bytes.
+    pub bytes: usize,
+
+    /// Size limit it exceeds, in bytes.
+    pub limit: usize,
+}
+
+/// Kinds of content that may be too large.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OversizedKind {
+    /// A `<style>` block.
+    Style,
+
+    /// A `data:` URL, such as an embedded image.
+    DataUrl,
+
+    /// The whole output, see `Build.max_output_bytes` in the config.
+    Output,
+}
+
+/// A resource loaded over plain HTTP by a page hosted over HTTPS.
+#[derive(Debug, Clone, Serialize)]
+pub struct MixedContentReport {
+    /// Kind of resource.
+    pub kind: MixedContentKind,
+
+    /// URL of the resource.
+    pub url: String,
+
+    /// Indicates if the resource was embedded in the page instead, fixing the problem (see
+    /// `Output.inline_insecure`).
+    pub inlined: bool,
+}
+
+/// Kinds of resources that may be loaded over plain HTTP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MixedContentKind {
+    /// The background image, see `Theme.background_image`.
+    BackgroundImage,
+
+    /// An icon of the web app manifest, see `App.icons`.
+    AppIcon,
+
+    /// A badge fetched when the page is opened, see `Link.badge_url`.
+    Badge,
+
+    /// Any other resource referenced by the output, such as an image in a custom template.
+    Resource,
+}
+
+/// An accessibility problem found in the output.
+#[derive(Debug, Clone, Serialize)]
+pub struct A11yIssueReport {
+    /// Kind of problem.
+    pub kind: A11yIssueKind,
+
+    /// What has the problem, such as the URL of a link, the source of an image, or a CSS rule and
+    /// its colors.
+    pub subject: String,
+
+    /// Contrast ratio between the colors, ranging from 1 to 21, for low contrast problems.
+    pub contrast: Option<f32>,
+}
+
+/// Kinds of accessibility problems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum A11yIssueKind {
+    /// A link with no accessible name, such as one containing only an icon.
+    UnlabeledLink,
+
+    /// An image without an `alt` attribute.
+    MissingAlt,
+
+    /// A CSS rule whose text and background colors don't meet the WCAG AA contrast ratio.
+    LowContrast,
+}
+
+impl BuildReport {
+    /// Records a finished build phase.
+    pub(crate) fn phase(&mut self, phase: Phase, elapsed: Duration, output_bytes: usize) {
+        self.phases.push(PhaseReport {
+            name: phase.as_str().into(),
+            elapsed_ms: elapsed.as_millis(),
+            output_bytes,
+        });
+    }
+
+    /// Records a built site icon.
+    pub(crate) fn site_icon(&mut self, url: &str, cached: bool, started: Instant) {
+        if cached {
+            self.cache_hits += 1;
+        } else {
+            self.cache_misses += 1;
+        }
+        self.site_icons.push(SiteIconReport {
+            url: url.into(),
+            cached,
+            elapsed_ms: started.elapsed().as_millis(),
+        });
+    }
+
+    /// Records a site icon that changed since it was last fetched.
+    pub(crate) fn changed_icon(&mut self, url: &str) {
+        self.changed_icons.push(url.into());
+    }
+
+    /// Records a problem that didn't stop the build.
+    pub(crate) fn warning(&mut self, kind: WarningKind, message: String) {
+        self.warnings.push(Warning { kind, message });
+    }
+
+    /// Records a site icon with low contrast against the background.
+    pub(crate) fn low_contrast_icon(&mut self, url: &str, contrast: f32) {
+        self.warning(
+            WarningKind::LowContrastIcon,
+            format!(
+                "icon of {url} has low contrast against the background ({contrast:.2}:1), \
+                 consider replacing it"
+            ),
+        );
+        self.low_contrast_icons.push(LowContrastIconReport {
+            url: url.into(),
+            contrast,
+        });
+    }
+}
@@ -0,0 +1,102 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Resolves a loaded config into the data the templates are rendered with, as a standalone step
+//! for tools that inspect configs without building.
+
+use std::ops::Deref;
+
+use serde::Serialize;
+
+use crate::{
+    config::{BuildLayout, Config},
+    resources::ResourceError,
+    rewrite, sort,
+};
+
+use super::{embeds, theme_schedule, BuildError};
+
+/// Options that change how a config is resolved, see [`Config::resolve`].
+#[derive(Debug, Clone, Default)]
+pub struct ResolveOptions {
+    /// Name of the profile to resolve instead of the config itself, see `Config.profiles`.
+    pub profile: Option<String>,
+
+    /// Layout replacing `Build.layout`, like a layout chosen on the command line.
+    pub layout: Option<BuildLayout>,
+}
+
+/// A config as the templates see it, produced by [`Config::resolve`]. Dereferences to the
+/// [`Config`], and serializes as one.
+#[derive(Debug, Clone, Serialize)]
+#[serde(transparent)]
+pub struct ResolvedConfig(Config);
+
+impl ResolvedConfig {
+    /// Unwraps the resolved config.
+    pub fn into_inner(self) -> Config {
+        self.0
+    }
+}
+
+impl Deref for ResolvedConfig {
+    type Target = Config;
+
+    fn deref(&self) -> &Config {
+        &self.0
+    }
+}
+
+impl Config {
+    /// Resolves the config as [`build`][crate::build] does before rendering anything: the profile
+    /// and layout are applied, pages, sections, and links are sorted, link URLs are rewritten, and
+    /// the result is validated.
+    ///
+    /// Page files and references are resolved when the config is loaded, so a config loaded from
+    /// a [`ResourceSet`][crate::resources::ResourceSet] is expected. Resources embedded because
+    /// the page is hosted securely (see `Output.inline_insecure`) are only embedded while
+    /// building.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the profile doesn't exist, sorting or rewriting fails, the default
+    /// page doesn't exist, or the theme schedule or embeds are invalid.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use newtabgen::{config::Config, resources::{ResourceSet, Resources}, ResolveOptions};
+    ///
+    /// # fn main() -> Result<(), newtabgen::Error> {
+    /// let config: Config = Resources::default().config()?;
+    /// let options = ResolveOptions {
+    ///     profile: Some("work".into()),
+    ///     ..Default::default()
+    /// };
+    /// let resolved = config.resolve(&options)?;
+    /// println!("{} pages", resolved.pages.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve(self, options: &ResolveOptions) -> Result<ResolvedConfig, BuildError> {
+        let mut config = match &options.profile {
+            Some(name) => self
+                .profile(name)
+                .ok_or_else(|| ResourceError::Profile(name.clone()))?,
+            None => self,
+        };
+        if let Some(layout) = options.layout {
+            config.build.layout = layout;
+        }
+
+        sort::sort_config(&mut config)?;
+        rewrite::rewrite_config(&mut config)?;
+        if let (Some(page), None) = (&config.default_page, config.default_page_index()) {
+            return Err(BuildError::DefaultPage(page.to_string()));
+        }
+        if let Some(schedule) = &config.theme.schedule {
+            theme_schedule::validate(schedule)?;
+        }
+        embeds::validate(&config)?;
+        Ok(ResolvedConfig(config))
+    }
+}
@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Chooses the sites the browser is hinted to connect to early, see
+//! `Performance.resource_hints`.
+
+use url::Url;
+
+use crate::config::{Config, Link, LinkSize, Section};
+
+/// Finds the origins of the most prominent links: large links of the page shown first, then the
+/// links of its first section. Links that aren't HTTP or HTTPS are skipped.
+///
+/// # Returns
+///
+/// The origins in order of prominence, without duplicates and at most
+/// `Performance.max_resource_hints` of them, or none if hints are disabled.
+pub(crate) fn origins(config: &Config) -> Vec<String> {
+    let performance = &config.performance;
+    let page = config
+        .pages
+        .get(config.default_page_index().unwrap_or_default());
+    let (true, Some(page)) = (performance.resource_hints, page) else {
+        return Vec::new();
+    };
+
+    let large = page.all_links().filter(|l| l.size == LinkSize::Large);
+    let first_section = page
+        .sections
+        .first()
+        .into_iter()
+        .flat_map(Section::all_links);
+    let mut origins = Vec::new();
+    for link in large.chain(first_section) {
+        if origins.len() >= performance.max_resource_hints {
+            break;
+        }
+        if let Some(origin) = origin(link) {
+            if !origins.contains(&origin) {
+                origins.push(origin);
+            }
+        }
+    }
+    origins
+}
+
+/// Finds the origin of a link, e.g. `https://example.com` for `https://example.com/a/b`.
+fn origin(link: &Link) -> Option<String> {
+    let url = Url::parse(&link.url).ok()?;
+    matches!(url.scheme(), "http" | "https").then(|| url.origin().ascii_serialization())
+}
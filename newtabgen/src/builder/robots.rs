@@ -0,0 +1,139 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Reads the `robots.txt` of websites, so [`RemoteIconFetcher`][1] can leave pages alone that ask
+//! not to be fetched, see [`RemoteIconFetcher::respecting_robots`][2].
+//!
+//! Only `User-agent`, `Allow`, and `Disallow` lines are understood, with `*` and `$` in paths
+//! matching as described in [RFC 9309][3].
+//!
+//! [1]: super::icon_fetcher::RemoteIconFetcher
+//! [2]: super::icon_fetcher::RemoteIconFetcher::respecting_robots
+//! [3]: <https://www.rfc-editor.org/rfc/rfc9309>
+
+use tracing::debug;
+use url::Url;
+
+use super::rate_limit::HostRateLimiter;
+
+/// Product token matched against `User-agent` lines.
+const PRODUCT_TOKEN: &str = "newtabgen";
+
+/// Largest `robots.txt` read, in bytes, as suggested by RFC 9309. Anything past it is ignored.
+const MAX_ROBOTS_BYTES: usize = 500 * 1024;
+
+/// Rules of a `robots.txt` that apply to newtabgen.
+#[derive(Debug, Default)]
+pub(crate) struct Robots {
+    /// Path patterns, and whether they allow fetching.
+    rules: Vec<(String, bool)>,
+}
+
+impl Robots {
+    /// Downloads and parses the `robots.txt` of the website a URL belongs to, waiting for
+    /// `limiter` first. Websites without one, or whose `robots.txt` can't be downloaded, allow
+    /// everything.
+    pub(crate) async fn fetch(
+        client: &reqwest::Client,
+        url: &Url,
+        limiter: &HostRateLimiter,
+    ) -> Self {
+        let Ok(robots_url) = url.join("/robots.txt") else {
+            return Self::default();
+        };
+        limiter.wait(robots_url.as_str()).await;
+        debug!(robots_url = robots_url.as_str(), "downloading robots.txt");
+        let response = match client.get(robots_url).send().await {
+            Ok(response) if response.status().is_success() => response,
+            _ => return Self::default(),
+        };
+        match response.bytes().await {
+            Ok(bytes) => {
+                let bytes = &bytes[..bytes.len().min(MAX_ROBOTS_BYTES)];
+                Self::parse(&String::from_utf8_lossy(bytes))
+            }
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Parses a `robots.txt`, keeping the rules of the groups for newtabgen, or of the groups for
+    /// every crawler (`*`) if none name newtabgen.
+    pub(crate) fn parse(src: &str) -> Self {
+        let (mut named, mut any) = (Vec::new(), Vec::new());
+        // User agents of the group being read, and whether its rules have started
+        let (mut agents, mut in_rules) = (Vec::<String>::new(), false);
+        for line in src.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let (key, value) = (key.trim().to_ascii_lowercase(), value.trim());
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        agents.clear();
+                        in_rules = false;
+                    }
+                    agents.push(value.to_ascii_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // An empty disallow rule allows everything, like having no rule
+                    if value.is_empty() {
+                        continue;
+                    }
+                    let rule = (value.to_owned(), key == "allow");
+                    if agents.iter().any(|a| a == PRODUCT_TOKEN) {
+                        named.push(rule);
+                    } else if agents.iter().any(|a| a == "*") {
+                        any.push(rule);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Self {
+            rules: if named.is_empty() { any } else { named },
+        }
+    }
+
+    /// Checks if the URL may be fetched. The longest matching rule decides, and allow rules win
+    /// ties.
+    pub(crate) fn allows(&self, url: &Url) -> bool {
+        let path = match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_owned(),
+        };
+        self.rules
+            .iter()
+            .filter(|(pattern, _)| matches(pattern, &path))
+            .max_by_key(|(pattern, allow)| (pattern.len(), *allow))
+            .is_none_or(|(_, allow)| *allow)
+    }
+}
+
+/// Checks if a path matches a rule's pattern, where `*` matches any characters and a trailing `$`
+/// anchors the pattern to the end of the path. Patterns match from the start of the path.
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = path.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+    let parts = parts.collect::<Vec<_>>();
+    for (i, part) in parts.iter().enumerate() {
+        // The last part must end the path if anchored, so match it as late as possible
+        let found = if anchored && i == parts.len() - 1 {
+            rest.rfind(part)
+        } else {
+            rest.find(part)
+        };
+        let Some(at) = found else {
+            return false;
+        };
+        rest = &rest[at + part.len()..];
+    }
+    !anchored || rest.is_empty()
+}
@@ -6,15 +6,39 @@
 //! Typically 'site icon' refers to a website's favicon, but in some cases a different icon may be
 //! found.
 
-use image::{imageops::FilterType, DynamicImage, ImageFormat, ImageOutputFormat};
+use futures_util::{stream, StreamExt};
+use image::{
+    codecs::png::{CompressionType, FilterType as PngFilterType, PngEncoder},
+    imageops::FilterType,
+    ColorType, DynamicImage, ImageEncoder, ImageFormat,
+};
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tokio::time::Instant;
-use tracing::{debug, info, span, warn, Level};
-
-use std::{fmt, io::Cursor, path::PathBuf};
-
-use crate::{config::Config, util};
+use tracing::{debug, info, instrument, warn};
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{
+    config::{Config, IconService, Page, Theme},
+    resources,
+    util::{self, color, domain},
+};
+
+use super::{
+    icon_fetcher::{self, FetchedIcon, IconSource},
+    progress::{Phase, Progress},
+    rate_limit::HostRateLimiter,
+    report::BuildReport,
+    theme_contrast, BuildOptions, CacheMode,
+};
 
 /// Errors that may occur when fetching or building site icons.
 #[derive(Error, Debug)]
@@ -29,7 +53,7 @@ pub enum SiteIconError {
 
     /// Occurs when writing an icon file to cache fails.
     #[error("failed to write icon @ {1} ({0})")]
-    CacheWrite(#[source] image::ImageError, PathBuf),
+    CacheWrite(#[source] tokio::io::Error, PathBuf),
 
     /// Occurs when opening a cached icon file fails.
     #[error("failed to read cached icon @ {1} ({0})")]
@@ -47,6 +71,11 @@ pub enum SiteIconError {
     #[error("failed to load url: {0}")]
     UrlLoad(String),
 
+    /// Occurs when a website's `robots.txt` disallows fetching it, see
+    /// [`RemoteIconFetcher::respecting_robots`][crate::RemoteIconFetcher::respecting_robots].
+    #[error("robots.txt disallows loading url: {0}")]
+    Robots(String),
+
     /// Occurs when no suitable icon could be found in a loaded website.
     #[error("failed to find icon for url: {0}")]
     IconNotFound(String),
@@ -59,11 +88,193 @@ pub enum SiteIconError {
     #[error("failed to decode icon for url: {1} ({0})")]
     IconDecode(#[source] image::ImageError, String),
 
+    /// Occurs when a custom [`IconFetcher`] fails to obtain an icon.
+    #[error("failed to fetch icon for url: {1} ({0})")]
+    Fetch(#[source] Box<dyn std::error::Error + Send + Sync>, String),
+
+    /// Occurs when an icon isn't cached and [`CacheMode::Offline`] prevents downloading it.
+    #[error("icon for url isn't cached, and downloading is disabled while offline: {0}")]
+    Offline(String),
+
+    /// Occurs when loading an icon from `Config.icon_overrides` fails.
+    #[error("failed to load icon override {1} for url: {0} ({2})")]
+    Override(String, String, String),
+
+    /// Occurs in strict mode when icons have low contrast against the background.
+    #[error("{0} icon(s) have low contrast against the background")]
+    LowContrast(usize),
+
     /// Occurs when re-encoding a processed site icon fails.
     #[error("failed to encode icon for url: {1} ({0})")]
     IconEncode(#[source] image::ImageError, String),
 }
 
+/// Name of the file in the site icon cache directory holding the [`IconIndex`].
+const INDEX_FILE: &str = "index.json";
+
+/// Name of the cache subdirectory holding [processed icons][processed_icon].
+const PROCESSED_ICONS_SUBDIR: &str = "processed_icons";
+
+/// Number of seconds after which processed icons that haven't been used are removed.
+const PROCESSED_ICON_MAX_AGE_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Metadata of cached site icons, keyed by website URL. Entries outlive the icons themselves, so
+/// a refreshed icon can be compared to the one it replaces.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IconIndex(BTreeMap<String, IconMetadata>);
+
+/// Metadata of a single cached site icon.
+#[derive(Debug, Serialize, Deserialize)]
+struct IconMetadata {
+    /// Hash of the encoded icon, see [`util::sha1_base32`].
+    hash: String,
+
+    /// Where the icon was downloaded from, used to revalidate it once expired.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<IconSource>,
+}
+
+/// An icon loaded by [`icon`].
+struct LoadedIcon {
+    /// The icon, which may still be encoded.
+    image: IconImage,
+
+    /// Hash of the encoded icon, see [`util::sha1_base32`]. Identifies the icon in the
+    /// [processed icon cache][processed_icon].
+    hash: String,
+
+    /// Indicates if the icon was read from the cache.
+    cached: bool,
+
+    /// Indicates if the icon was fetched and differs from the one previously cached.
+    changed: bool,
+}
+
+/// The image of a [`LoadedIcon`]. Icons that weren't just fetched are only decoded if they have to
+/// be processed, see [`processed_icon`].
+enum IconImage {
+    /// An icon that was decoded while fetching it.
+    Decoded(DynamicImage),
+
+    /// The encoded bytes of a cached icon, and the path they were read from.
+    Cached(Vec<u8>, PathBuf),
+
+    /// The encoded bytes of an icon from `Config.icon_overrides`, and the override's source.
+    Override(Vec<u8>, String),
+}
+
+impl IconImage {
+    /// Decodes the icon, if it isn't already.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the icon can't be decoded.
+    fn decode(self, website_url: &str) -> Result<DynamicImage, SiteIconError> {
+        match self {
+            IconImage::Decoded(img) => Ok(img),
+            IconImage::Cached(bytes, path) => image::io::Reader::new(Cursor::new(bytes))
+                .with_guessed_format()
+                .map_err(|e| SiteIconError::CacheRead(e, path.clone()))?
+                .decode()
+                .map_err(|e| SiteIconError::CacheDecode(e, path)),
+            IconImage::Override(bytes, source) => image::load_from_memory(&bytes)
+                .map_err(|e| SiteIconError::Override(website_url.into(), source, e.to_string())),
+        }
+    }
+}
+
+/// A site icon after resizing and inverting, ready to be embedded. Cached by [`processed_icon`].
+#[derive(Debug, Serialize, Deserialize)]
+struct ProcessedIcon {
+    /// The icon, as a base64 PNG.
+    original: String,
+
+    /// An inverted copy of the icon as a base64 PNG, if the original has low contrast against the
+    /// background of either theme.
+    inverted: Option<String>,
+
+    /// Indicates if the inverted copy is shown with the dark theme.
+    invert_dark: bool,
+
+    /// Indicates if the inverted copy is shown with the light theme.
+    invert_light: bool,
+
+    /// Contrast ratio between the icon shown with the configured theme and its background.
+    contrast: f32,
+}
+
+impl IconIndex {
+    /// Finds the path of the index.
+    fn path() -> Result<PathBuf, SiteIconError> {
+        Ok(util::cache_subdir("site_icons")
+            .map_err(|_| SiteIconError::CacheDir)?
+            .join(INDEX_FILE))
+    }
+
+    /// Reads the index from the cache. A missing or unreadable index is treated as empty, since
+    /// it's only used to notice changed icons and revalidate expired ones.
+    async fn load() -> Result<Self, SiteIconError> {
+        let path = Self::path()?;
+        let Ok(bytes) = tokio::fs::read(&path).await else {
+            return Ok(Self::default());
+        };
+        Ok(serde_json::from_slice(&bytes).unwrap_or_else(|_| {
+            warn!(
+                path = path.to_str(),
+                "failed to parse site icon index, ignoring it"
+            );
+            Self::default()
+        }))
+    }
+
+    /// Writes the index to the cache. Failures are logged rather than returned, for the same
+    /// reason as in [`IconIndex::load`].
+    async fn store(&self) -> Result<(), SiteIconError> {
+        let path = Self::path()?;
+        debug!(path = path.to_str(), "writing site icon index");
+        let json = serde_json::to_vec(self).expect("failed to serialize site icon index");
+        if tokio::fs::write(&path, json).await.is_err() {
+            warn!(path = path.to_str(), "failed to write site icon index");
+        }
+        Ok(())
+    }
+
+    /// Records the hash and source of a newly cached icon.
+    ///
+    /// # Returns
+    ///
+    /// Whether a different icon was previously cached for the same website.
+    fn record(&mut self, website_url: &str, hash: String, source: Option<IconSource>) -> bool {
+        let previous = self.0.insert(
+            website_url.into(),
+            IconMetadata {
+                hash: hash.clone(),
+                source,
+            },
+        );
+        previous.is_some_and(|p| p.hash != hash)
+    }
+
+    /// Finds the source of a cached icon, if it can be revalidated.
+    fn revalidatable_source(&self, website_url: &str) -> Option<IconSource> {
+        self.0
+            .get(website_url)
+            .and_then(|m| m.source.clone())
+            .filter(IconSource::has_validators)
+    }
+}
+
+/// Minimum contrast ratio between an icon and the background before the icon is reported.
+const MIN_CONTRAST: f32 = 2.0;
+
+/// Relative luminance of the template's dark and light backgrounds (`$bg` in the styles), used
+/// when checking icon contrast if the configured background can't be parsed.
+const DEFAULT_BACKGROUND_LUMINANCE: (f32, f32) = (0.009, 0.723);
+
+/// Relative luminance below which a background is dark, where white and black text have the same
+/// contrast against it.
+const DARK_BACKGROUND_LUMINANCE: f32 = 0.179;
+
 /// Generates a unique CSS class for a site icon, based on the provided website URL.
 pub fn site_icon_class(url: &str) -> String {
     format!("ico-{}", util::sha1_base32(url.as_bytes()))
@@ -71,14 +282,19 @@ pub fn site_icon_class(url: &str) -> String {
 
 /// Builds site icons for each URL in the config with the following process:
 ///
-/// 1. Locate, download, and decode a suitable icon in the webpage.
-/// 2. Resize and invert (if needed) the decoded icon.
-/// 3. Convert the processed icon into a [data URL][1] within a CSS class.
+/// 1. Fetch a suitable icon with the configured [`IconFetcher`][super::icon_fetcher::IconFetcher],
+///    unless it's already cached.
+/// 2. Resize the decoded icon, and create an inverted copy if it has low contrast against either
+///    the dark or light theme.
+/// 3. Convert the icon and its inverted copy into [data URLs][1] within a CSS class. The variant
+///    shown is selected by the `data-theme` attribute, falling back to the configured theme.
 ///
 /// # Arguments
 ///
 /// * `config` - The config to extract website URLs from.
 /// * `size` - The size to resize icons to.
+/// * `options` - Options that change how the icons are built.
+/// * `report` - The report to record each icon in.
 ///
 /// # Errors
 ///
@@ -87,103 +303,591 @@ pub fn site_icon_class(url: &str) -> String {
 /// # Returns
 ///
 /// CSS containing classes with [data URL][1] background images. The classname is derived from the
-/// original website URL in the config.
+/// original website URL in the config. If `config.lazy_icons` is set, icons used by a single page
+/// are instead placed in a `<template data-page="...">` for that page, keyed by the page name's
+/// hash.
+///
+/// Also returns the [data URL][1] of each original icon, keyed by website URL.
 ///
 /// [1]: <https://developer.mozilla.org/en-US/docs/Web/HTTP/Basics_of_HTTP/Data_URLs>
-pub async fn build_site_icons(config: &Config, size: u32) -> Result<String, SiteIconError> {
-    let _span = span!(Level::INFO, "site_icons").entered();
+#[instrument(name = "site_icons", skip_all)]
+pub async fn build_site_icons(
+    config: &Config,
+    size: u32,
+    options: &BuildOptions,
+    report: &mut BuildReport,
+) -> Result<(String, BTreeMap<String, String>), SiteIconError> {
     info!("building site icons");
     let sw = Instant::now();
 
-    let mut site_icons = String::default();
+    // Icons are switched between variants with the `data-theme` attribute, falling back to the
+    // configured theme. Variants default to the original icon. The icon class may follow other
+    // classes, as in `class="icon {{ link.url | site_icon }}"`.
+    let default_theme = if config.theme.dark { "dark" } else { "light" };
+    let icons = |scope: &str| format!(r#"{scope}[class^=ico-],{scope}[class*=" ico-"]"#);
+    let mut site_icons = format!(
+        "{}{{--ico-dark:var(--ico);--ico-light:var(--ico);\
+         background-image:var(--ico-{default_theme})}}\
+         {}{{background-image:var(--ico-dark)}}\
+         {}{{background-image:var(--ico-light)}}",
+        icons(""),
+        icons("[data-theme=dark] "),
+        icons("[data-theme=light] "),
+    );
     let urls = config
         .pages
         .iter()
-        .flat_map(|p| &p.sections)
-        .flat_map(|s| &s.links)
+        .flat_map(Page::all_links)
         .map(|l| l.url.as_str())
         .collect::<Vec<&str>>();
-    let http_client = reqwest::Client::builder()
-        .user_agent("newtabgen (looking for icons) github.com/fr33zing/newtabgen")
-        .build()?;
-
-    for url in urls.iter().unique().cloned() {
-        let mut img = icon(url, &http_client).await?;
-        debug!(size, "resizing");
-        img = img.resize(size, size, FilterType::Lanczos3);
-
-        if config.theme.invert_low_contrast_icons {
-            let brightness = avg_brightness(img.clone());
-            let threshold = 0.25;
-            if (config.theme.dark && brightness < threshold)
-                || (!config.theme.dark && brightness > (1f32 - threshold))
-            {
-                img.invert();
-                debug!(brightness, "inverting icon");
+
+    // When lazy, icons used by only one page are deferred until that page is shown
+    let mut lazy_pages = HashMap::<&str, Option<&str>>::new();
+    if config.lazy_icons {
+        for page in &config.pages {
+            for link in page.all_links() {
+                lazy_pages
+                    .entry(link.url.as_str())
+                    .and_modify(|p| {
+                        if *p != Some(page.name.as_str()) {
+                            *p = None;
+                        }
+                    })
+                    .or_insert(Some(page.name.as_str()));
+            }
+        }
+    }
+    let mut page_icons = HashMap::<&str, String>::new();
+    let mut data_urls = BTreeMap::<String, String>::new();
+
+    let mut urls = urls.into_iter().unique().collect::<Vec<&str>>();
+    if options.reproducible {
+        // Keep the output stable when links are moved around the config
+        urls.sort_unstable();
+    }
+    let index = Mutex::new(IconIndex::load().await?);
+    let limiter = HostRateLimiter::new(options.icon_host_interval);
+    let groups = icon_groups(config, &urls);
+    // Icons are loaded concurrently, but processed in order so the output stays stable
+    let mut loads = stream::iter(&groups)
+        .map(|group| {
+            // Links sharing an icon get it from the first of them
+            let url = group[0];
+            let (index, limiter) = (&index, &limiter);
+            async move {
+                let icon_sw = Instant::now();
+                let loaded = match icon_override(config, url) {
+                    Some(source) => override_icon(url, source, options, limiter).await,
+                    None => icon(url, config.icon_fallback.as_ref(), options, index, limiter).await,
+                };
+                (loaded, icon_sw)
             }
+        })
+        .buffered(options.max_icon_connections.max(1));
+    let mut fetched = false;
+    let mut done = 0;
+    for group in &groups {
+        let url = group[0];
+        let (loaded, icon_sw) = loads.next().await.expect("one load per group");
+        let loaded = loaded?;
+        let cached = loaded.cached;
+        fetched |= !cached;
+        if loaded.changed {
+            info!(
+                url,
+                "site icon changed since it was last fetched, the site may have been rebranded"
+            );
+            report.changed_icon(url);
+        }
+        let ProcessedIcon {
+            original,
+            inverted,
+            invert_dark,
+            contrast,
+            ..
+        } = processed_icon(loaded, url, size, config).await?;
+
+        if contrast < MIN_CONTRAST {
+            warn!(
+                url,
+                contrast, "icon has low contrast against the background, consider replacing it"
+            );
+            report.low_contrast_icon(url, contrast);
         }
 
-        let mut writer = Cursor::new(Vec::<u8>::new());
-        img.write_to(&mut writer, ImageOutputFormat::Png)
-            .map_err(|e| SiteIconError::IconDecode(e, url.into()))?;
-        let buf = writer.into_inner();
-        let bytes = buf.as_slice();
-        debug!("generating data url & css class");
-        let data_base64 = data_encoding::BASE64.encode(bytes);
-        let class = site_icon_class(url);
         debug!("writing output");
-        fmt::Write::write_fmt(
-            &mut site_icons,
-            format_args!(".{class}{{background-image:url(data:image/png;base64,{data_base64})}}"),
-        )?;
+        // Links sharing an icon share a rule, unless their icons are deferred to different pages
+        let mut rules = Vec::<(Option<&str>, Vec<String>)>::new();
+        for url in group {
+            let page = lazy_pages.get(url).copied().flatten();
+            let selector = format!(".{}", site_icon_class(url));
+            match rules.iter_mut().find(|(p, _)| *p == page) {
+                Some((_, selectors)) => selectors.push(selector),
+                None => rules.push((page, vec![selector])),
+            }
+        }
+        for (page, selectors) in rules {
+            let out = match page {
+                Some(page) => page_icons.entry(page).or_default(),
+                None => &mut site_icons,
+            };
+            fmt::Write::write_fmt(
+                out,
+                format_args!(
+                    "{}{{--ico:url(data:image/png;base64,{original})",
+                    selectors.join(",")
+                ),
+            )?;
+            if let Some(inverted) = &inverted {
+                let theme = if invert_dark { "dark" } else { "light" };
+                fmt::Write::write_fmt(
+                    out,
+                    format_args!(";--ico-{theme}:url(data:image/png;base64,{inverted})"),
+                )?;
+            }
+            out.push('}');
+        }
+        for (i, url) in group.iter().enumerate() {
+            data_urls.insert((*url).into(), format!("data:image/png;base64,{original}"));
+            report.site_icon(url, cached || i > 0, icon_sw);
+            done += 1;
+            options.progress.send(Progress::Item {
+                phase: Phase::SiteIcons,
+                current: done,
+                total: urls.len(),
+            });
+        }
+    }
+
+    drop(loads);
+    if fetched {
+        let index = index.into_inner().unwrap_or_else(|e| e.into_inner());
+        index.store().await?;
+    }
+
+    if options.strict && !report.low_contrast_icons.is_empty() {
+        return Err(SiteIconError::LowContrast(report.low_contrast_icons.len()));
+    }
+
+    // Deferred icons are kept inert in templates, which the page template activates
+    let mut output = format!("<style>{site_icons}</style>");
+    for page in &config.pages {
+        if let Some(icons) = page_icons.get(page.name.as_str()) {
+            let page_hash = util::sha1_base32(page.name.as_bytes());
+            fmt::Write::write_fmt(
+                &mut output,
+                format_args!(
+                    r#"<template data-page="{page_hash}"><style>{icons}</style></template>"#
+                ),
+            )?;
+        }
     }
 
+    prune_processed_icons();
+
     debug!(
         elapsed_ms = sw.elapsed().as_millis(),
         "finished building site icons"
     );
-    Ok(format!("<style>{site_icons}</style>"))
+    Ok((output, data_urls))
+}
+
+/// Processes a loaded icon for embedding, see [`process_icon`]. The result is cached, keyed by the
+/// icon's hash and the settings that affect processing, so icons are only decoded and processed
+/// again when either changes.
+///
+/// # Errors
+///
+/// Returns an error if the icon has to be processed and that fails.
+async fn processed_icon(
+    loaded: LoadedIcon,
+    website_url: &str,
+    size: u32,
+    config: &Config,
+) -> Result<ProcessedIcon, SiteIconError> {
+    let key = format!(
+        "{}:{}:{size}:{}:{}:{}:{}:png",
+        env!("CARGO_PKG_VERSION"),
+        loaded.hash,
+        config.theme.invert_low_contrast_icons,
+        config.theme.dark,
+        theme_contrast::background(&config.theme, true),
+        theme_contrast::background(&config.theme, false),
+    );
+    let path = util::cache_subdir(PROCESSED_ICONS_SUBDIR)
+        .map_err(|_| SiteIconError::CacheDir)?
+        .join(util::sha1_base32(key.as_bytes()));
+    if let Ok(json) = tokio::fs::read(&path).await {
+        if let Ok(processed) = serde_json::from_slice(&json) {
+            debug!(
+                path = path.to_str(),
+                "reading processed site icon from cache"
+            );
+            util::touch(&path);
+            return Ok(processed);
+        }
+    }
+
+    let processed = process_icon(loaded.image.decode(website_url)?, website_url, size, config)?;
+    debug!(path = path.to_str(), "writing processed site icon to cache");
+    let json = serde_json::to_vec(&processed).expect("failed to serialize processed site icon");
+    if tokio::fs::write(&path, json).await.is_err() {
+        warn!(
+            path = path.to_str(),
+            "failed to write processed site icon to cache"
+        );
+    }
+    Ok(processed)
+}
+
+/// Removes entries from the processed icon cache that haven't been used in a while. Their keys
+/// include the icon's hash, so entries for icons that changed or were removed from the config
+/// would otherwise pile up.
+fn prune_processed_icons() {
+    let Ok(dir) = util::cache_subdir(PROCESSED_ICONS_SUBDIR) else {
+        return;
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.flatten().map(|e| e.path()) {
+        if util::cache_unused(&path, PROCESSED_ICON_MAX_AGE_SECS)
+            && std::fs::remove_file(&path).is_err()
+        {
+            warn!(
+                path = path.to_str(),
+                "failed to remove unused processed site icon from cache"
+            );
+        }
+    }
 }
 
-// todo: improve docs
-/// Attempts to read an icon for the provided URL from the cache. Otherwise, fetches a remote icon
-/// and writes it to the cache.
+/// Resizes an icon, inverts it if needed, and encodes the results as base64 PNGs.
+///
+/// # Errors
+///
+/// Returns an error if encoding fails.
+fn process_icon(
+    img: DynamicImage,
+    url: &str,
+    size: u32,
+    config: &Config,
+) -> Result<ProcessedIcon, SiteIconError> {
+    debug!(size, "resizing");
+    let mut img = img.resize(size, size, FilterType::Lanczos3);
+
+    // Each theme gets the original icon, or an inverted copy if the original would have low
+    // contrast against that theme's background
+    let (invert_dark, invert_light) = if config.theme.invert_low_contrast_icons {
+        let brightness = avg_brightness(img.clone());
+        let threshold = 0.25;
+        let invert = |dark: bool| {
+            if background_luminance(&config.theme, dark) < DARK_BACKGROUND_LUMINANCE {
+                brightness < threshold
+            } else {
+                brightness > (1f32 - threshold)
+            }
+        };
+        (invert(true), invert(false))
+    } else {
+        (false, false)
+    };
+    let background = background_luminance(&config.theme, config.theme.dark);
+
+    let shows_inverted = if config.theme.dark {
+        invert_dark
+    } else {
+        invert_light
+    };
+
+    debug!("generating data urls");
+    let original = data_base64(&img, url)?;
+    let mut contrast = contrast_ratio(&img, background);
+    let inverted = if invert_dark || invert_light {
+        debug!(invert_dark, invert_light, "inverting icon");
+        img.invert();
+        if shows_inverted {
+            contrast = contrast_ratio(&img, background);
+        }
+        Some(data_base64(&img, url)?)
+    } else {
+        None
+    };
+
+    Ok(ProcessedIcon {
+        original,
+        inverted,
+        invert_dark,
+        invert_light,
+        contrast,
+    })
+}
+
+/// Groups the URLs of links that share an icon, see `Config.reuse_domain_icons`. Each URL is in
+/// its own group unless icons are reused.
+///
+/// # Returns
+///
+/// The groups, in the order their first URLs appear in `urls`.
+pub(crate) fn icon_groups<'a>(config: &Config, urls: &[&'a str]) -> Vec<Vec<&'a str>> {
+    let mut groups = Vec::<Vec<&str>>::new();
+    let mut by_key = HashMap::<String, usize>::new();
+    for url in urls {
+        let key = match (config.reuse_domain_icons, icon_override(config, url)) {
+            (false, _) => None,
+            (true, Some(source)) => Some(format!("override {source}")),
+            (true, None) => domain::registrable_domain(url).map(|d| format!("domain {d}")),
+        };
+        match key.as_ref().and_then(|key| by_key.get(key)) {
+            Some(&i) => groups[i].push(url),
+            None => {
+                if let Some(key) = key {
+                    by_key.insert(key, groups.len());
+                }
+                groups.push(vec![url]);
+            }
+        }
+    }
+    groups
+}
+
+/// Finds the icon replacing the detected icon of a website, see `Config.icon_overrides`. A key
+/// matching the URL exactly wins over domains, and longer domains win over shorter ones.
+pub(crate) fn icon_override<'a>(config: &'a Config, website_url: &str) -> Option<&'a str> {
+    let overrides = &config.icon_overrides;
+    if let Some(source) = overrides.get(website_url) {
+        return Some(source);
+    }
+    let url = url::Url::parse(website_url).ok()?;
+    let host = url.host_str()?;
+    overrides
+        .iter()
+        .filter(|(key, _)| {
+            host.strip_suffix(key.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.ends_with('.'))
+        })
+        .max_by_key(|(key, _)| key.len())
+        .map(|(_, source)| source.as_str())
+}
+
+/// Loads an icon from `Config.icon_overrides` instead of fetching it. Overrides given as a URL are
+/// downloaded and cached like other remote resources.
+async fn override_icon(
+    website_url: &str,
+    source: &str,
+    options: &BuildOptions,
+    limiter: &HostRateLimiter,
+) -> Result<LoadedIcon, SiteIconError> {
+    let error = |e: String| SiteIconError::Override(website_url.into(), source.into(), e);
+    debug!(website_url, source, "loading site icon override");
+    let bytes = match resources::remote_url(Path::new(source)) {
+        Some(url) => {
+            if resources::needs_download(&url, options.cache) {
+                limiter.wait(url.as_str()).await;
+            }
+            resources::download_bytes(&reqwest::Client::new(), &url, options.cache)
+                .await
+                .map_err(|e| error(e.to_string()))?
+        }
+        None => tokio::fs::read(source)
+            .await
+            .map_err(|e| error(e.to_string()))?,
+    };
+    Ok(LoadedIcon {
+        hash: util::sha1_base32(&bytes),
+        image: IconImage::Override(bytes, source.into()),
+        cached: true,
+        changed: false,
+    })
+}
+
+/// Attempts to read an icon for the provided URL from the cache. Otherwise, fetches the icon with
+/// the configured [`IconFetcher`][super::icon_fetcher::IconFetcher] and writes it to the cache,
+/// recording its hash in the index. Expired icons whose source is known are revalidated first, and
+/// kept if unchanged. Requests are spaced out per host by `limiter`.
 async fn icon(
     website_url: &str,
-    http_client: &reqwest::Client,
-) -> Result<DynamicImage, SiteIconError> {
-    match icon_cached(website_url).await? {
-        Some(icon) => Ok(icon),
-        None => {
-            let icon = icon_remote(website_url, http_client).await?;
-            cache_icon(website_url, &icon)?;
-            Ok(icon)
+    fallback: Option<&IconService>,
+    options: &BuildOptions,
+    index: &Mutex<IconIndex>,
+    limiter: &HostRateLimiter,
+) -> Result<LoadedIcon, SiteIconError> {
+    // Reproducible builds keep using cached icons, since a refreshed icon would change the output
+    let expire = options.cache == CacheMode::PreferCache && !options.reproducible;
+    if expire && options.icon_fetcher.is_remote() && icon_expired(website_url)? {
+        // Icons the server sent validators for are only downloaded again if they changed
+        let source = index
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .revalidatable_source(website_url);
+        if let Some(source) = source {
+            limiter.wait(&source.url).await;
+            match icon_fetcher::revalidate(&source).await {
+                Ok(None) => {
+                    debug!(website_url, "cached site icon is still current");
+                    renew_cached_icon(website_url).await?;
+                }
+                Ok(Some(fetched)) => return store_icon(website_url, fetched, index).await,
+                Err(e) => debug!(
+                    website_url,
+                    error = format!("{}", e),
+                    "failed to revalidate cached site icon, fetching it again"
+                ),
+            }
         }
     }
+    if options.cache != CacheMode::Refresh || refreshed(website_url, options) {
+        if let Some(icon) = icon_cached(website_url, expire).await? {
+            return Ok(icon);
+        }
+    }
+    if options.cache == CacheMode::Offline && options.icon_fetcher.is_remote() {
+        return Err(SiteIconError::Offline(website_url.into()));
+    }
+    let fetched = match (
+        options
+            .icon_fetcher
+            .fetch_with_source(website_url, limiter)
+            .await,
+        fallback,
+    ) {
+        (Ok(fetched), _) => fetched,
+        // Asking a favicon service is only an option if fetching may use the network anyway
+        (Err(e), Some(service)) if options.icon_fetcher.is_remote() => {
+            debug!(
+                website_url,
+                error = format!("{}", e),
+                "no icon found, asking favicon service"
+            );
+            icon_fetcher::fetch_from_service(service, website_url, limiter)
+                .await
+                .map_err(|fallback_error| {
+                    warn!(
+                        website_url,
+                        error = format!("{}", fallback_error),
+                        "favicon service has no icon either"
+                    );
+                    e
+                })?
+        }
+        (Err(e), _) => return Err(e),
+    };
+    store_icon(website_url, fetched, index).await
+}
+
+/// Writes a fetched icon to the cache, recording its hash and source in the index.
+///
+/// # Errors
+///
+/// Returns an error if the icon can't be cached.
+async fn store_icon(
+    website_url: &str,
+    fetched: FetchedIcon,
+    index: &Mutex<IconIndex>,
+) -> Result<LoadedIcon, SiteIconError> {
+    let FetchedIcon { img, source } = fetched;
+    let hash = cache_icon(website_url, &img).await?;
+    let changed =
+        index
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .record(website_url, hash.clone(), source);
+    Ok(LoadedIcon {
+        image: IconImage::Decoded(img),
+        hash,
+        cached: false,
+        changed,
+    })
 }
 
-// todo: improve docs
-/// Writes an icon to the cache.
-fn cache_icon(website_url: &str, icon: &DynamicImage) -> Result<(), SiteIconError> {
-    let path = util::cache_dir()
+/// Checks if [`icon`] would fetch the icon for the provided URL instead of reading it from the
+/// cache, without changing the cache.
+pub(crate) fn icon_needs_fetch(website_url: &str, options: &BuildOptions) -> bool {
+    if !options.icon_fetcher.is_remote() || options.cache == CacheMode::Offline {
+        return false;
+    }
+    if options.cache == CacheMode::Refresh {
+        return !refreshed(website_url, options);
+    }
+    let expire = !options.reproducible;
+    util::cache_subdir("site_icons")
+        .map(|dir| dir.join(util::sha1_base32(website_url.as_bytes())))
+        .map_or(true, |path| {
+            !path.exists() || (expire && util::cache_expired(&path, 604800))
+        })
+}
+
+/// Finds the path of the cached icon for the provided website URL.
+fn icon_path(website_url: &str) -> Result<PathBuf, SiteIconError> {
+    Ok(util::cache_subdir("site_icons")
         .map_err(|_| SiteIconError::CacheDir)?
-        .join("site_icons")
-        .join(util::sha1_base32(website_url.as_bytes()));
+        .join(util::sha1_base32(website_url.as_bytes())))
+}
+
+/// Checks if the cached icon for the provided website URL was written after
+/// `options.refreshed_after`, so it doesn't need to be refreshed again.
+fn refreshed(website_url: &str, options: &BuildOptions) -> bool {
+    options.refreshed_after.is_some_and(|time| {
+        icon_path(website_url).is_ok_and(|path| util::modified_after(&path, time))
+    })
+}
+
+/// Checks if the cached icon for the provided website URL exists and is older than 1 week.
+fn icon_expired(website_url: &str) -> Result<bool, SiteIconError> {
+    let path = icon_path(website_url)?;
+    Ok(path.exists() && util::cache_expired(&path, 604800))
+}
+
+/// Recreates a cached icon that was revalidated, so it expires 1 week from now. Overwriting it in
+/// place would keep the creation time that expiry is based on.
+///
+/// # Errors
+///
+/// Returns an error if the icon can't be read or written.
+async fn renew_cached_icon(website_url: &str) -> Result<(), SiteIconError> {
+    let path = icon_path(website_url)?;
+    let bytes = tokio::fs::read(&path)
+        .await
+        .map_err(|e| SiteIconError::CacheRead(e, path.clone()))?;
+    tokio::fs::remove_file(&path)
+        .await
+        .map_err(|e| SiteIconError::CacheWrite(e, path.clone()))?;
+    tokio::fs::write(&path, bytes)
+        .await
+        .map_err(|e| SiteIconError::CacheWrite(e, path))
+}
+
+/// Writes an icon to the cache as a PNG.
+///
+/// # Errors
+///
+/// Returns an error if the icon can't be encoded or written.
+///
+/// # Returns
+///
+/// A hash of the encoded icon, which changes along with its content.
+async fn cache_icon(website_url: &str, icon: &DynamicImage) -> Result<String, SiteIconError> {
+    let path = icon_path(website_url)?;
+    let mut bytes = Vec::<u8>::new();
+    icon.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .map_err(|e| SiteIconError::IconEncode(e, website_url.into()))?;
     debug!(path = path.to_str(), "writing site icon to cache");
-    icon.save_with_format(&path, ImageFormat::Png)
+    tokio::fs::write(&path, &bytes)
+        .await
         .map_err(|e| SiteIconError::CacheWrite(e, path))?;
-    Ok(())
+    Ok(util::sha1_base32(&bytes))
 }
 
-/// Attempts to locate, read and decode a cached icon. If the cached icon is older than 1 week, it
-/// will be deleted and `None` will be returned.
+/// Attempts to locate and read a cached icon. If the cached icon is older than 1 week and
+/// `expire` is set, it will be deleted and `None` will be returned.
 ///
-/// Automatic cached icon removal may not work on all platforms (due to the use of
-/// std::fs::Metadata). No error will be raised if this is the case.
+/// Automatic cached icon removal may not work on all platforms (see [`util::cache_expired`]). No
+/// error will be raised if this is the case.
 ///
 /// # Arguments
 ///
 /// * `website_url` - Url of the website the icon was originally downloaded from.
+/// * `expire` - Indicates if an expired icon should be removed instead of read.
 ///
 /// # Errors
 ///
@@ -191,24 +895,16 @@ fn cache_icon(website_url: &str, icon: &DynamicImage) -> Result<(), SiteIconErro
 ///
 /// # Returns
 ///
-/// The read and decoded icon, in its original format.
-async fn icon_cached(website_url: &str) -> Result<Option<DynamicImage>, SiteIconError> {
-    let path = util::cache_subdir("site_icons")
-        .map_err(|_| SiteIconError::CacheDir)?
-        .join(util::sha1_base32(website_url.as_bytes()));
+/// The read icon, left encoded until it has to be processed.
+async fn icon_cached(website_url: &str, expire: bool) -> Result<Option<LoadedIcon>, SiteIconError> {
+    let path = icon_path(website_url)?;
 
     if !path.exists() {
         return Ok(None);
     }
 
-    let expired = 'x: {
-        let Ok(metadata) = std::fs::metadata(&path) else { break 'x false };
-        let Ok(created) = metadata.created() else { break 'x false };
-        let Ok(elapsed) = created.elapsed() else { break 'x false };
-        elapsed.as_secs() >= 604800 // One week
-    };
-
-    if expired {
+    // Expire after one week
+    if expire && util::cache_expired(&path, 604800) {
         if tokio::fs::remove_file(&path).await.is_err() {
             warn!(
                 path = path.to_str(),
@@ -222,81 +918,60 @@ async fn icon_cached(website_url: &str) -> Result<Option<DynamicImage>, SiteIcon
     let icon_bytes = tokio::fs::read(&path)
         .await
         .map_err(|e| SiteIconError::CacheRead(e, path.clone()))?;
-    let cursor = Cursor::new(icon_bytes);
-    let img = image::io::Reader::new(cursor)
-        .with_guessed_format()
-        .map_err(|e| SiteIconError::CacheRead(e, path.clone()))?
-        .decode()
-        .map_err(|e| SiteIconError::CacheDecode(e, path))?;
-
-    Ok(Some(img))
+    Ok(Some(LoadedIcon {
+        hash: util::sha1_base32(&icon_bytes),
+        image: IconImage::Cached(icon_bytes, path),
+        cached: true,
+        changed: false,
+    }))
 }
 
-/// Locates, downloads, and decodes a suitable icon in the webpage. This process involves sending
-/// multiple HTTP requests.
-///
-/// # Arguments
-///
-/// * `website_url` - Url of the website, not an icon.
-/// * `http_client` - Client to use for sending HTTP requests. Requires a valid user agent.
-///
-/// # Errors
+/// Encodes an icon as a base64 PNG, for use in a [data URL][1].
 ///
-/// Returns an error if any step in the process detailed above fails.
+/// [1]: <https://developer.mozilla.org/en-US/docs/Web/HTTP/Basics_of_HTTP/Data_URLs>
+fn data_base64(img: &DynamicImage, website_url: &str) -> Result<String, SiteIconError> {
+    // Encoder settings are fixed so icons are encoded identically across builds
+    let mut buf = Vec::<u8>::new();
+    let rgba = img.to_rgba8();
+    PngEncoder::new_with_quality(&mut buf, CompressionType::Best, PngFilterType::Adaptive)
+        .write_image(&rgba, rgba.width(), rgba.height(), ColorType::Rgba8)
+        .map_err(|e| SiteIconError::IconEncode(e, website_url.into()))?;
+    Ok(data_encoding::BASE64.encode(buf.as_slice()))
+}
+
+/// Finds the relative luminance of the background of a theme variant, see
+/// [`theme_contrast::background`].
+fn background_luminance(theme: &Theme, dark: bool) -> f32 {
+    color::parse(theme_contrast::background(theme, dark))
+        .map(color::relative_luminance)
+        .unwrap_or(if dark {
+            DEFAULT_BACKGROUND_LUMINANCE.0
+        } else {
+            DEFAULT_BACKGROUND_LUMINANCE.1
+        })
+}
+
+/// Calculates the [contrast ratio][1] between the average relative luminance of the visible pixels
+/// in an image and a background with the provided relative luminance.
 ///
 /// # Returns
 ///
-/// The downloaded and decoded icon, in its original format.
-async fn icon_remote(
-    website_url: &str,
-    http_client: &reqwest::Client,
-) -> Result<DynamicImage, SiteIconError> {
-    debug!(website_url, "locating remote site icon");
-    let mut icons = site_icons::Icons::new();
-    icons
-        .load_website(website_url)
-        .await
-        .map_err(|_| SiteIconError::UrlLoad(website_url.into()))?;
-    debug!("choosing site icon");
-    let entries = icons.entries().await;
-    let icon = {
-        // Prefer favicon
-        let favicon = entries
-            .iter()
-            .find(|i| i.url.path().contains("favicon.ico"));
-        match favicon {
-            Some(i) => i,
-            None => entries
-                .iter()
-                .find(|i| !matches!(i.info, site_icons::IconInfo::SVG))
-                .ok_or_else(|| SiteIconError::IconNotFound(website_url.into()))?,
-        }
-    };
-    let icon_url = icon.url.to_string();
-    let _span = span!(Level::DEBUG, "individual", icon_url).entered();
-    debug!("downloading site icon");
-    let icon_bytes = http_client
-        .get(icon.url.to_string())
-        .send()
-        .await
-        .map_err(|e| SiteIconError::IconRequest(e, icon.url.clone().into()))?
-        .bytes()
-        .await
-        .map_err(|e| SiteIconError::IconRequest(e, icon.url.clone().into()))?;
-    debug!(len = icon_bytes.len(), "reading downloaded site icon");
-    let cursor = Cursor::new(icon_bytes);
-    let mut reader = image::io::Reader::new(cursor);
-    let format = match icon.info.clone() {
-        site_icons::IconInfo::PNG { size: _ } => ImageFormat::Png,
-        site_icons::IconInfo::JPEG { size: _ } => ImageFormat::Jpeg,
-        site_icons::IconInfo::ICO { sizes: _ } => ImageFormat::Ico,
-        site_icons::IconInfo::SVG => unreachable!("SVGs should be filtered out"),
-    };
-    reader.set_format(format);
-    let img = reader
-        .decode()
-        .map_err(|e| SiteIconError::IconDecode(e, website_url.into()))?;
-    Ok(img)
+/// The contrast ratio, ranging from 1 to 21.
+///
+/// [1]: <https://www.w3.org/TR/WCAG21/#dfn-contrast-ratio>
+fn contrast_ratio(img: &DynamicImage, background: f32) -> f32 {
+    let rgba = img.to_rgba8();
+    let luminances = rgba
+        .pixels()
+        .filter(|p| p[3] > 32)
+        .map(|p| color::relative_luminance([p[0], p[1], p[2]]))
+        .collect::<Vec<f32>>();
+    if luminances.is_empty() {
+        return 1f32;
+    }
+
+    let icon = luminances.iter().sum::<f32>() / luminances.len() as f32;
+    color::contrast_ratio(icon, background)
 }
 
 /// Calculates the average brightness of visible pixels in an image.
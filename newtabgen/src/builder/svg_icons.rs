@@ -23,6 +23,14 @@ use tracing::{debug, info, span, Level};
 
 use crate::{config::Config, util};
 
+use super::{
+    progress::{Phase, Progress},
+    BuildOptions, CacheMode,
+};
+
+/// Where the icons repository is cloned from.
+pub(crate) const ICONS_REPO_URL: &str = "https://github.com/marella/material-design-icons.git";
+
 /// Errors that may occur when cloning the icon or building svg icons.
 #[derive(Error, Debug)]
 pub enum SvgIconError {
@@ -42,6 +50,10 @@ pub enum SvgIconError {
     #[error(transparent)]
     Repo(#[from] git2::Error),
 
+    /// Occurs when the pinned icon repo revision can't be found, even after fetching.
+    #[error("failed to find icon repo revision: '{1}' ({0})")]
+    RevNotFound(#[source] git2::Error, String),
+
     /// Occurs when loading an icon SVG from the icon repo fails.
     #[error("failed to load icon: '{1}' of style '{2}' @ '{3}' ({0})")]
     IconLoad(#[source] io::Error, String, String, PathBuf),
@@ -49,6 +61,10 @@ pub enum SvgIconError {
     /// Occurs when a requested icon could not be found in the icon repo.
     #[error("failed to find icon: '{0}' of style '{1}' @ '{2}'")]
     IconNotFound(String, String, PathBuf),
+
+    /// Occurs when the icon repository hasn't been cloned yet and [`CacheMode::Offline`] is used.
+    #[error("icon repo isn't cloned yet, and downloading is disabled while offline")]
+    Offline,
 }
 
 /// Generates a unique ID for an icon, based on the icon name and style.
@@ -64,6 +80,7 @@ pub fn svg_icon_id(icon_name: &str, icon_style: &str) -> String {
 /// # Arguments
 ///
 /// * `config` - The config to extract icon references from.
+/// * `options` - Options that change how the icons are built.
 ///
 /// # Errors
 ///
@@ -73,23 +90,40 @@ pub fn svg_icon_id(icon_name: &str, icon_style: &str) -> String {
 ///
 /// An HTML SVG containing symbol definitions. The IDs of the symbols are derived from their icon
 /// name and style.
-pub fn build_svg_icons(config: &Config) -> Result<String, SvgIconError> {
+pub fn build_svg_icons(config: &Config, options: &BuildOptions) -> Result<String, SvgIconError> {
     let _span = span!(Level::INFO, "svg_icons").entered();
     info!("building svg icons");
     let sw = Instant::now();
 
-    let repo_root = icons_repo()?;
+    let repo_root = icons_repo(
+        config.icon_repo_rev.as_deref(),
+        config.icon_repo_update_hours,
+        options,
+    )?;
     let mut symbol_defs = String::default();
-    config
+    let mut icons = config
         .pages
         .iter()
         .map(|page| (page.icon.clone(), page.icon_style.clone()))
         .unique()
-        .map(|t| load_icon(&repo_root, &t.0, &t.1).map(|src| (src, t.0, t.1)))
-        .collect::<Result<Vec<(String, String, String)>, SvgIconError>>()?
-        .iter()
-        .map(|t| to_symbol_def(&t.0, &t.1, &t.2))
-        .try_for_each(|sym_def| symbol_defs.write_str(&sym_def))?;
+        .collect::<Vec<(String, String)>>();
+    if options.reproducible {
+        icons.sort_unstable();
+    }
+    for (i, (name, style)) in icons.iter().enumerate() {
+        let src = load_icon(&repo_root, name, style)?;
+        let src = if config.theme.tint_icons {
+            strip_fills(&src)
+        } else {
+            src
+        };
+        symbol_defs.write_str(&to_symbol_def(&src, name, style))?;
+        options.progress.send(Progress::Item {
+            phase: Phase::SvgIcons,
+            current: i + 1,
+            total: icons.len(),
+        });
+    }
 
     debug!(
         elapsed_ms = sw.elapsed().as_millis(),
@@ -100,37 +134,182 @@ pub fn build_svg_icons(config: &Config) -> Result<String, SvgIconError> {
     ))
 }
 
-/// Clones or updates the icons repository and returns its root directory.
-fn icons_repo() -> Result<PathBuf, SvgIconError> {
+/// Returns the directory the icons repository is cloned into.
+pub(crate) fn icons_repo_dir() -> Result<PathBuf, SvgIconError> {
+    let cache_dir = util::cache_dir().map_err(|_| SvgIconError::CacheDir)?;
+    Ok(cache_dir.join("material-design-icons"))
+}
+
+/// Returns the ID of the commit currently checked out in the icons repository.
+///
+/// # Errors
+///
+/// Returns an error if the repository hasn't been cloned yet or its HEAD can't be resolved.
+pub fn icons_repo_commit() -> Result<String, SvgIconError> {
+    let repo = Repository::open(icons_repo_dir()?)?;
+    let commit = repo.head()?.peel_to_commit()?;
+    Ok(commit.id().to_string())
+}
+
+/// Clones the icons repository if needed and returns its root directory.
+///
+/// If `rev` is provided, that revision is checked out, fetching from the remote only if it isn't
+/// available locally or `options.update_icons` is set. Otherwise, the main branch is pulled if it
+/// wasn't pulled within the last `update_hours` hours, unless the build is reproducible or updates
+/// are skipped.
+///
+/// [`CacheMode::Offline`] never touches the remote, failing if the repository or revision isn't
+/// available locally. [`CacheMode::Refresh`] always fetches, like `options.update_icons`, unless
+/// the repository was fetched after `options.refreshed_after`.
+fn icons_repo(
+    rev: Option<&str>,
+    update_hours: u64,
+    options: &BuildOptions,
+) -> Result<PathBuf, SvgIconError> {
     let _span = span!(Level::DEBUG, "repo").entered();
 
-    let cache_dir = util::cache_dir().map_err(|_| SvgIconError::CacheDir)?;
-    let repo_dir = cache_dir.join("material-design-icons");
-    let repo_url = "https://github.com/marella/material-design-icons.git";
+    let repo_dir = icons_repo_dir()?;
+    let repo_url = ICONS_REPO_URL;
+    let offline = options.cache == CacheMode::Offline;
+    let update = !offline
+        && (options.update_icons || options.cache == CacheMode::Refresh)
+        && !refreshed(&repo_dir, options);
 
     fs::create_dir_all(repo_dir.clone())?;
-    match Repository::open(repo_dir.clone()) {
-        Ok(repo) => {
+    let (repo, cloned) = match Repository::open(repo_dir.clone()) {
+        Ok(repo) => (repo, false),
+        Err(_) if offline => return Err(SvgIconError::Offline),
+        Err(_) => {
             debug!(
                 repo_url,
                 repo_dir = repo_dir.to_str(),
-                "pulling svg icons repo"
+                "cloning svg icons repo"
             );
-            pull(&repo)?;
+            (Repository::clone(repo_url, repo_dir.clone())?, true)
         }
-        Err(_) => {
+    };
+
+    // Return to the main branch if a revision was previously pinned. Fetching the pin doesn't
+    // update the main branch, so it's pulled unless pulling is skipped altogether.
+    let unpinned = rev.is_none() && repo.head_detached()?;
+    if unpinned {
+        debug!("returning svg icons repo to the main branch");
+        repo.set_head("refs/heads/main")?;
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
+    }
+
+    match rev {
+        Some(rev) => {
+            // Pretend the remote was already fetched, so a missing revision fails right away
+            let mut fetched = offline;
+            if update {
+                fetch_all(&repo)?;
+                fetched = true;
+            }
+            let commit = match repo.revparse_single(rev) {
+                Ok(object) => object.peel_to_commit(),
+                Err(_) if !fetched => {
+                    fetch_all(&repo)?;
+                    repo.revparse_single(rev).and_then(|o| o.peel_to_commit())
+                }
+                Err(e) => Err(e),
+            }
+            .map_err(|e| SvgIconError::RevNotFound(e, rev.into()))?;
+
+            debug!(rev, commit = %commit.id(), "checking out pinned svg icons revision");
+            repo.checkout_tree(
+                commit.as_object(),
+                Some(git2::build::CheckoutBuilder::default().force()),
+            )?;
+            repo.set_head_detached(commit.id())?;
+        }
+        None if cloned => {}
+        None if (options.reproducible || options.skip_icon_update || offline) && !update => {
             debug!(
                 repo_url,
                 repo_dir = repo_dir.to_str(),
-                "cloning svg icons repo"
+                "using svg icons repo without pulling"
             );
-            Repository::clone(repo_url, repo_dir.clone())?;
+        }
+        None if !update && !unpinned && recently_pulled(&repo, update_hours * 3600) => {
+            debug!(
+                repo_url,
+                repo_dir = repo_dir.to_str(),
+                update_hours,
+                "svg icons repo was pulled recently, skipping pull"
+            );
+        }
+        None => {
+            debug!(
+                repo_url,
+                repo_dir = repo_dir.to_str(),
+                "pulling svg icons repo"
+            );
+            pull(&repo)?;
         }
     }
 
     Ok(repo_dir)
 }
 
+/// Predicts what [`icons_repo`] would do with the remote, without touching it. Must make the same
+/// decisions as [`icons_repo`].
+///
+/// # Returns
+///
+/// `"clone"`, `"fetch"`, or `"pull"`, or `None` if the remote wouldn't be contacted.
+pub(crate) fn planned_repo_operation(
+    rev: Option<&str>,
+    update_hours: u64,
+    options: &BuildOptions,
+) -> Option<&'static str> {
+    if options.cache == CacheMode::Offline {
+        return None;
+    }
+    let update = (options.update_icons || options.cache == CacheMode::Refresh)
+        && !icons_repo_dir().is_ok_and(|dir| refreshed(&dir, options));
+    let Some(repo) = icons_repo_dir()
+        .ok()
+        .and_then(|dir| Repository::open(dir).ok())
+    else {
+        return Some("clone");
+    };
+    let unpinned = rev.is_none() && repo.head_detached().unwrap_or(false);
+    match rev {
+        Some(rev) => (update || repo.revparse_single(rev).is_err()).then_some("fetch"),
+        None if (options.reproducible || options.skip_icon_update) && !update => None,
+        None if !update && !unpinned && recently_pulled(&repo, update_hours * 3600) => None,
+        None => Some("pull"),
+    }
+}
+
+/// Checks if the icons repository was fetched from within the last `max_age_secs` seconds, based
+/// on when `FETCH_HEAD` was last written.
+fn recently_pulled(repo: &Repository, max_age_secs: u64) -> bool {
+    fs::metadata(repo.path().join("FETCH_HEAD"))
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|elapsed| elapsed.as_secs() < max_age_secs)
+}
+
+/// Checks if the icons repository was fetched after `options.refreshed_after`, so fetching it again
+/// can be skipped.
+fn refreshed(repo_dir: &Path, options: &BuildOptions) -> bool {
+    options
+        .refreshed_after
+        .is_some_and(|time| util::modified_after(&repo_dir.join(".git").join("FETCH_HEAD"), time))
+}
+
+/// Fetches all branches and tags of the icons repository, without changing the working tree.
+fn fetch_all(repo: &Repository) -> Result<(), git2::Error> {
+    debug!("fetching all svg icons revisions");
+    let mut remote = repo.find_remote("origin")?;
+    let mut fo = git2::FetchOptions::new();
+    fo.download_tags(git2::AutotagOption::All);
+    remote.fetch(&[] as &[&str], Some(&mut fo), None)
+}
+
 /// Locates and loads an icon SVG based on the provided icon name and style.
 ///
 /// # Errors
@@ -140,7 +319,7 @@ fn icons_repo() -> Result<PathBuf, SvgIconError> {
 /// # Returns
 ///
 /// SVG element markup.
-fn load_icon(repo_dir: &Path, name: &str, style: &str) -> Result<String, SvgIconError> {
+pub(crate) fn load_icon(repo_dir: &Path, name: &str, style: &str) -> Result<String, SvgIconError> {
     let svgs_path = repo_dir.join("svg");
     let style_path = svgs_path.join(style);
     let icon_path = style_path.join(format!("{name}.svg"));
@@ -172,6 +351,29 @@ fn to_symbol_def(src: &str, name: &str, style: &str) -> String {
     format!("{add_start}{middle}{add_end}")
 }
 
+/// Removes hardcoded fills from an SVG, so its shapes are filled with the color inherited from the
+/// element using it, e.g. `currentColor`. Fills of `none` are kept, since they mark shapes that
+/// aren't meant to be visible.
+fn strip_fills(src: &str) -> String {
+    let mut out = String::with_capacity(src.len());
+    let mut rest = src;
+    while let Some(start) = rest.find(" fill=\"") {
+        let value_start = start + " fill=\"".len();
+        let Some(value_len) = rest[value_start..].find('"') else {
+            break;
+        };
+        let end = value_start + value_len + 1;
+        let keep = &rest[value_start..end - 1] == "none";
+        out.push_str(&rest[..start]);
+        if keep {
+            out.push_str(&rest[start..end]);
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
 // The following code was adapted from an example written by github.com/zaphar
 // https://github.com/rust-lang/git2-rs/blob/master/examples/pull.rs
 
@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Exposes the files bundled with a theme to templates (see
+//! [`ResourceSet::theme_assets`][crate::resources::ResourceSet::theme_assets]), as data URLs so
+//! the page stays a single file.
+
+use std::collections::BTreeMap;
+
+/// Guesses the MIME type of a file from its extension.
+fn mime_type(name: &str) -> &'static str {
+    let extension = name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "json" => "application/json",
+        "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Encodes each asset as a data URL.
+///
+/// # Returns
+///
+/// The data URLs, keyed by the path of each asset within the theme's `assets` directory.
+pub(crate) fn data_urls(assets: &BTreeMap<String, Vec<u8>>) -> BTreeMap<String, String> {
+    assets
+        .iter()
+        .map(|(name, data)| {
+            let url = format!(
+                "data:{};base64,{}",
+                mime_type(name),
+                data_encoding::BASE64.encode(data)
+            );
+            (name.clone(), url)
+        })
+        .collect()
+}
@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Checks that the theme colors are readable against the background, see `Theme.min_contrast`.
+
+use tracing::warn;
+
+use crate::{
+    config::{Config, Theme},
+    util::color,
+};
+
+use super::{
+    background::DerivedColors,
+    report::{ThemeColorPair, ThemeContrastReport},
+};
+
+/// Text and background colors of the default template's dark theme (`$fg` and `$bg` in the
+/// styles).
+const DARK_COLORS: (&str, &str) = ("#ddd", "#181818");
+
+/// Text and background colors of the default template's light theme (`$fg` and `$bg` in the
+/// styles).
+const LIGHT_COLORS: (&str, &str) = ("#181818", "#ddd");
+
+impl ThemeColorPair {
+    /// Describes the pair of colors in messages.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Accent => "accent",
+        }
+    }
+}
+
+/// Calculates the contrast of the text and accent colors against the background, falling back to
+/// the default template's colors where the theme doesn't replace them. If `Theme.schedule` is
+/// provided, both variants are checked.
+///
+/// # Arguments
+///
+/// * `config` - The config to check the theme of.
+/// * `derived` - Colors derived from the background image, if any, which provide the accent color
+///   if it isn't configured.
+///
+/// # Returns
+///
+/// Each pair of colors with a contrast ratio below `Theme.min_contrast`.
+pub(crate) fn check(config: &Config, derived: Option<&DerivedColors>) -> Vec<ThemeContrastReport> {
+    let theme = &config.theme;
+    let mut variants = vec![theme.dark];
+    if theme.schedule.is_some() {
+        variants.push(!theme.dark);
+    }
+    variants
+        .into_iter()
+        .flat_map(|dark| check_variant(config, derived, dark))
+        .collect()
+}
+
+/// Calculates the contrast of the theme colors of a single variant, see [`check`].
+fn check_variant(
+    config: &Config,
+    derived: Option<&DerivedColors>,
+    dark: bool,
+) -> Vec<ThemeContrastReport> {
+    let theme = &config.theme;
+    let (default_fg, _) = if dark { DARK_COLORS } else { LIGHT_COLORS };
+    let fg = theme.colors.foreground.as_deref().unwrap_or(default_fg);
+    let bg = background(theme, dark);
+    let accent = theme
+        .colors
+        .accent
+        .as_deref()
+        .or(derived.map(|d| d.accent.as_str()));
+
+    let mut pairs = vec![(ThemeColorPair::Text, fg)];
+    pairs.extend(accent.map(|accent| (ThemeColorPair::Accent, accent)));
+
+    let Some(bg_rgb) = parse(bg) else {
+        return Vec::new();
+    };
+    pairs
+        .into_iter()
+        .filter_map(|(pair, fg)| {
+            let contrast = color::contrast_ratio(
+                color::relative_luminance(parse(fg)?),
+                color::relative_luminance(bg_rgb),
+            );
+            (contrast < theme.min_contrast).then(|| ThemeContrastReport {
+                pair,
+                foreground: fg.into(),
+                background: bg.into(),
+                contrast,
+                minimum: theme.min_contrast,
+            })
+        })
+        .collect()
+}
+
+/// Finds the background color of a theme variant, falling back to the default template's.
+pub(crate) fn background(theme: &Theme, dark: bool) -> &str {
+    let (_, default_bg) = if dark { DARK_COLORS } else { LIGHT_COLORS };
+    theme.colors.background.as_deref().unwrap_or(default_bg)
+}
+
+/// Parses a theme color, warning if it can't be checked.
+fn parse(value: &str) -> Option<[u8; 3]> {
+    let rgb = color::parse(value);
+    if rgb.is_none() {
+        warn!(
+            color = value,
+            "theme color isn't an opaque hex or rgb() color, so its contrast can't be checked"
+        );
+    }
+    rgb
+}
@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Switches between the light and dark variants of the theme by time of day, see
+//! `Theme.schedule`.
+
+use serde::Serialize;
+
+use crate::config::{Config, ThemeSchedule};
+
+use super::{phases::Css, BuildError};
+
+/// Script that shows the variant scheduled for the current time, by setting the `data-theme`
+/// attribute and enabling the matching `<style>` element. `SCHEDULE` is replaced with the
+/// [`ScriptSchedule`] as JSON.
+const SCRIPT: &str = r#"(() => {
+    const schedule = SCHEDULE;
+    const apply = () => {
+        const parts = new Intl.DateTimeFormat("en-US", {
+            hour: "numeric",
+            minute: "numeric",
+            hourCycle: "h23",
+            timeZone: schedule.time_zone ?? undefined,
+        }).formatToParts(new Date());
+        const part = (type) => Number(parts.find((p) => p.type === type).value);
+        const now = part("hour") * 60 + part("minute");
+        const light = schedule.light <= schedule.dark
+            ? now >= schedule.light && now < schedule.dark
+            : now >= schedule.light || now < schedule.dark;
+        const theme = light ? "light" : "dark";
+        document.documentElement.dataset.theme = theme;
+        for (const style of document.querySelectorAll("style[data-theme-variant]")) {
+            style.media = style.dataset.themeVariant === theme ? "all" : "not all";
+        }
+    };
+    apply();
+    setInterval(apply, 60 * 1000);
+})();"#;
+
+/// The schedule as provided to [`SCRIPT`], with times in minutes since midnight.
+#[derive(Serialize)]
+struct ScriptSchedule<'a> {
+    /// Time the light variant is shown from.
+    light: u16,
+
+    /// Time the dark variant is shown from.
+    dark: u16,
+
+    /// IANA time zone the times are in, or `None` for the device's local time.
+    time_zone: Option<&'a str>,
+}
+
+/// Parses a time of day in 24-hour "HH:MM" format.
+///
+/// # Returns
+///
+/// The number of minutes since midnight, or `None` if the time is invalid.
+fn minutes(time: &str) -> Option<u16> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    let (hours, minutes) = (hours.parse::<u16>().ok()?, minutes.parse::<u16>().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+/// Checks that the times of a schedule are valid.
+///
+/// # Errors
+///
+/// Returns an error if a time isn't in 24-hour "HH:MM" format.
+pub(crate) fn validate(schedule: &ThemeSchedule) -> Result<(), BuildError> {
+    for time in [&schedule.light, &schedule.dark] {
+        if minutes(time).is_none() {
+            return Err(BuildError::ThemeSchedule(time.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// Creates the HTML that includes the compiled styles in the page. If `Theme.schedule` is provided
+/// and the alternate variant was compiled, both variants are included, followed by the script
+/// switching between them.
+///
+/// # Arguments
+///
+/// * `config` - The config, which provides the schedule.
+/// * `css` - Styles compiled for the configured variant.
+/// * `alternate_css` - Styles compiled for the other variant, if any.
+pub(crate) fn include_styles(config: &Config, css: &Css, alternate_css: Option<&Css>) -> String {
+    let schedule = config.theme.schedule.as_ref().and_then(|schedule| {
+        Some(ScriptSchedule {
+            light: minutes(&schedule.light)?,
+            dark: minutes(&schedule.dark)?,
+            time_zone: schedule.time_zone.as_deref(),
+        })
+    });
+    let (Some(schedule), Some(alternate_css)) = (schedule, alternate_css) else {
+        return format!("<style>{}</style>", css.css);
+    };
+
+    let (variant, alternate) = if config.theme.dark {
+        ("dark", "light")
+    } else {
+        ("light", "dark")
+    };
+    // Escape closing tags, since the time zone is provided by the user
+    let schedule = serde_json::to_string(&schedule)
+        .unwrap_or_else(|_| "null".into())
+        .replace("</", "<\\/");
+    format!(
+        "<style data-theme-variant=\"{variant}\">{}</style>\
+         <style data-theme-variant=\"{alternate}\" media=\"not all\">{}</style>\
+         <script>{}</script>",
+        css.css,
+        alternate_css.css,
+        SCRIPT.replace("SCHEDULE", &schedule)
+    )
+}
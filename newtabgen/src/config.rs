@@ -1,8 +1,56 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 //! Provides structs that define the expected configuration file.
+//!
+//! # Reusing fragments
+//!
+//! Repeated parts of a config, such as a list of links shown on several pages, can be defined once
+//! and reused. Unknown top-level keys are ignored, so a key like `definitions` can hold them.
+//!
+//! In YAML configs, use anchors and aliases. Merge keys (`<<`) are supported too:
+//!
+//! ```yaml
+//! definitions:
+//!   dev_links: &dev_links
+//!     - { name: Crates, url: "https://crates.io/" }
+//! pages:
+//!   - name: Home
+//!     sections:
+//!       - { name: Dev, links: *dev_links }
+//! ```
+//!
+//! In any format, an object containing `$ref` is replaced by the value at the referenced location,
+//! given as a JSON pointer prefixed with `#`. Other keys next to `$ref` override the keys of the
+//! referenced object:
+//!
+//! ```json
+//! {
+//!   "definitions": { "dev": { "name": "Dev", "links": [] } },
+//!   "pages": [
+//!     { "name": "Home", "sections": [{ "$ref": "#/definitions/dev" }] },
+//!     { "name": "Work", "sections": [{ "$ref": "#/definitions/dev", "max_visible": 3 }] }
+//!   ]
+//! }
+//! ```
+//!
+//! # Splitting pages into files
+//!
+//! A page can be defined in a separate file, in any supported format, by replacing it with an
+//! object containing `file`. Paths are relative to the file containing them, and other keys next
+//! to `file` override the keys of the page. References in page files point into the root config.
+//!
+//! ```yaml
+//! pages:
+//!   - name: Home
+//!     sections: []
+//!   - file: pages/dev.yaml
+//!   - { file: pages/work.yaml, weight: -1 }
+//! ```
 
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
 
 use serde::{Deserialize, Serialize};
 
@@ -13,6 +61,19 @@ pub struct Config {
     #[serde(default = "Config::default_title")]
     pub title: String,
 
+    /// Locale used for locale-sensitive operations, such as sorting and formatting numbers. Expects
+    /// a BCP 47 language tag, e.g. "de" or "sv-SE". If not provided, locale-neutral rules are used.
+    #[serde(default)]
+    pub locale: Option<String>,
+
+    /// Order in which sections and links are shown.
+    #[serde(default)]
+    pub sort: Sort,
+
+    /// Rules for rewriting link URLs, applied before site icons are looked up.
+    #[serde(default)]
+    pub rewrite: Rewrite,
+
     /// Theming preferences.
     #[serde(default)]
     pub theme: Theme,
@@ -20,6 +81,81 @@ pub struct Config {
     /// New tab page content.
     #[serde(default)]
     pub pages: Vec<Page>,
+
+    /// Page shown when the new tab page is opened, by name or by its index (starting at 0) in the
+    /// order the tabs are shown. If not provided, the first page is shown.
+    #[serde(default)]
+    pub default_page: Option<DefaultPage>,
+
+    /// Widgets, built alongside the pages.
+    #[serde(default)]
+    pub widgets: Widgets,
+
+    /// Revision (tag, branch or commit) of the material design icons repository to use. If not
+    /// provided, the latest revision of the main branch is used.
+    #[serde(default)]
+    pub icon_repo_rev: Option<String>,
+
+    /// Minimum number of hours between updates of the material design icons repository. Set to
+    /// 0 to update on every build.
+    #[serde(default = "Config::default_icon_repo_update_hours")]
+    pub icon_repo_update_hours: u64,
+
+    /// Web app manifest fields. If provided, the page can be installed as a progressive web app
+    /// and opened offline when hosted. [`build_to_path`][crate::build_to_path] writes a manifest
+    /// and a service worker next to the page.
+    #[serde(default)]
+    pub app: Option<App>,
+
+    /// Indicates if site icons used by only one page should be loaded when that page is first
+    /// shown, instead of up front. Reduces the initial style parsing cost of large configs, but
+    /// requires JavaScript.
+    #[serde(default)]
+    pub lazy_icons: bool,
+
+    /// Icons replacing the detected site icons of specific websites, as a local file path or a
+    /// URL, keyed by link URL or by domain, e.g. "github.com". Domains also match their
+    /// subdomains, and the most specific key is used. Useful for sites whose detected icon is
+    /// missing or doesn't look right, without configuring every link to them.
+    #[serde(default)]
+    pub icon_overrides: BTreeMap<String, String>,
+
+    /// Favicon service asked for the icon of a website when none can be found in the website
+    /// itself. The service learns which websites are linked, so none is used unless provided.
+    #[serde(default)]
+    pub icon_fallback: Option<IconService>,
+
+    /// Indicates if links to websites sharing a registrable domain, such as docs.github.com and
+    /// gist.github.com, should share one icon, fetched for the first of them. Reduces requests
+    /// and output size, but subdomains with their own icons lose them. Links sharing an icon
+    /// override also share the icon.
+    #[serde(default)]
+    pub reuse_domain_icons: bool,
+
+    /// Numbered hints over the first links of the shown page, toggled by pressing and releasing a
+    /// modifier key, so links can be opened from the keyboard by typing their number. If not
+    /// provided, hints are disabled. Requires JavaScript, and isn't supported by the text-only
+    /// template.
+    #[serde(default)]
+    pub hints: Option<Hints>,
+
+    /// Variants of the new tab page built from this config, keyed by name, e.g. "work" and
+    /// "home". Each profile is built to its own file named after it, sharing everything but its
+    /// pages with the rest of the config.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+
+    /// Settings that make links open faster.
+    #[serde(default)]
+    pub performance: Performance,
+
+    /// Settings that change how the page is built, rather than what it shows.
+    #[serde(default)]
+    pub build: Build,
+
+    /// Where and how the built page is served.
+    #[serde(default)]
+    pub output: Output,
 }
 
 impl Config {
@@ -27,25 +163,453 @@ impl Config {
     fn default_title() -> String {
         "New Tab".into()
     }
+
+    /// Default value for `Config.icon_repo_update_hours`
+    fn default_icon_repo_update_hours() -> u64 {
+        24
+    }
+
+    /// Finds the index of the page shown when the new tab page is opened.
+    ///
+    /// # Returns
+    ///
+    /// The index of `Config.default_page`, 0 if it's not provided, or `None` if it doesn't match
+    /// any page.
+    pub fn default_page_index(&self) -> Option<usize> {
+        match &self.default_page {
+            None => Some(0),
+            Some(DefaultPage::Index(index)) => (*index < self.pages.len()).then_some(*index),
+            Some(DefaultPage::Name(name)) => self.pages.iter().position(|p| &p.name == name),
+        }
+    }
+
+    /// Creates the config of a profile, replacing the pages and any other fields the profile
+    /// provides. The created config has no profiles of its own.
+    ///
+    /// # Returns
+    ///
+    /// The config of the profile, or `None` if there's no profile with the provided name.
+    pub fn profile(&self, name: &str) -> Option<Config> {
+        let profile = self.profiles.get(name)?;
+        let mut config = Config {
+            profiles: BTreeMap::new(),
+            ..self.clone()
+        };
+        if let Some(title) = &profile.title {
+            config.title = title.clone();
+        }
+        if !profile.pages.is_empty() {
+            config.pages = profile.pages.clone();
+        }
+        if profile.default_page.is_some() {
+            config.default_page = profile.default_page.clone();
+        }
+        Some(config)
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             title: "New Tab".into(),
+            locale: Default::default(),
+            sort: Default::default(),
+            rewrite: Default::default(),
             theme: Default::default(),
             pages: Default::default(),
+            default_page: Default::default(),
+            widgets: Default::default(),
+            icon_repo_rev: Default::default(),
+            icon_repo_update_hours: Config::default_icon_repo_update_hours(),
+            lazy_icons: Default::default(),
+            icon_overrides: Default::default(),
+            icon_fallback: Default::default(),
+            reuse_domain_icons: Default::default(),
+            hints: Default::default(),
+            app: Default::default(),
+            profiles: Default::default(),
+            performance: Default::default(),
+            build: Default::default(),
+            output: Default::default(),
         }
     }
 }
 
+/// Web app manifest fields, used to install the page as a progressive web app.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct App {
+    /// Name of the app. If not provided, `Config.title` is used.
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Name shown where space is limited, such as under a home screen icon. If not provided, the
+    /// name is used.
+    #[serde(default)]
+    pub short_name: Option<String>,
+
+    /// Icons representing the app, e.g. on the home screen.
+    #[serde(default)]
+    pub icons: Vec<AppIcon>,
+
+    /// Color of the browser UI around the app, as a CSS color.
+    #[serde(default)]
+    pub theme_color: Option<String>,
+
+    /// Color shown while the app is loading, as a CSS color.
+    #[serde(default)]
+    pub background_color: Option<String>,
+}
+
+/// An icon representing the app.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppIcon {
+    /// Icon URL, or path relative to the output file.
+    pub src: String,
+
+    /// Space-separated sizes of the icon, e.g. "192x192" or "any".
+    #[serde(default)]
+    pub sizes: Option<String>,
+
+    /// MIME type of the icon, e.g. "image/png".
+    #[serde(default, rename = "type")]
+    pub mime_type: Option<String>,
+}
+
+/// Quick-access hints, see `Config.hints`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hints {
+    /// Modifier key that shows and hides the hints when pressed and released on its own.
+    #[serde(default)]
+    pub key: HintKey,
+
+    /// Number of links hints are shown for. Hints are numbered from 1, and padded with zeros to the
+    /// same length, so typing e.g. "07" opens the seventh link when there are more than nine.
+    #[serde(default = "Hints::default_count")]
+    pub count: usize,
+}
+
+impl Hints {
+    /// Default value for `Hints.count`
+    fn default_count() -> usize {
+        9
+    }
+}
+
+impl Default for Hints {
+    fn default() -> Self {
+        Self {
+            key: Default::default(),
+            count: Hints::default_count(),
+        }
+    }
+}
+
+/// Modifier key toggling quick-access hints, see `Hints.key`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HintKey {
+    /// The Alt key, or Option on macOS.
+    #[default]
+    Alt,
+
+    /// The Control key.
+    Control,
+
+    /// The Shift key.
+    Shift,
+
+    /// The Meta key, i.e. the Windows or Command key.
+    Meta,
+}
+
+/// A variant of the new tab page, see `Config.profiles`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Profile {
+    /// Title of the new tab page. If not provided, `Config.title` is used.
+    #[serde(default)]
+    pub title: Option<String>,
+
+    /// New tab page content. If not provided, `Config.pages` is used.
+    #[serde(default)]
+    pub pages: Vec<Page>,
+
+    /// Page shown when the new tab page is opened. If not provided, `Config.default_page` is
+    /// used.
+    #[serde(default)]
+    pub default_page: Option<DefaultPage>,
+}
+
+/// Reference to the page shown when the new tab page is opened.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DefaultPage {
+    /// Index of the page, starting at 0, in the order the tabs are shown.
+    Index(usize),
+
+    /// Name of the page.
+    Name(String),
+}
+
+impl fmt::Display for DefaultPage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DefaultPage::Index(index) => write!(f, "index {index}"),
+            DefaultPage::Name(name) => write!(f, "'{name}'"),
+        }
+    }
+}
+
+/// Order in which sections and links are shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    /// Keep the order from the configuration file.
+    #[default]
+    Manual,
+
+    /// Sort by name, using the collation rules of `Config.locale`.
+    Alphabetical,
+
+    /// Like `Alphabetical`, but sequences of digits are compared by their numeric value, so
+    /// "Server 2" comes before "Server 10".
+    Natural,
+}
+
+/// Rules for rewriting link URLs, e.g. to remove tracking parameters or use a privacy-friendly
+/// frontend.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Rewrite {
+    /// Indicates if `http` links should be changed to `https`.
+    #[serde(default)]
+    pub force_https: bool,
+
+    /// Query parameters to remove from links, e.g. "fbclid". A trailing `*` matches any parameter
+    /// starting with the rest of the name, e.g. "utm_*".
+    #[serde(default)]
+    pub strip_params: Vec<String>,
+
+    /// Domains to replace, e.g. "twitter.com" to "nitter.net". A domain also matches its "www."
+    /// subdomain.
+    #[serde(default)]
+    pub domains: HashMap<String, String>,
+}
+
+/// How links are laid out within a section.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// Links side by side, wrapping onto as many rows as needed. Suits dense link farms.
+    Grid,
+
+    /// One link per row, with its description or URL.
+    #[default]
+    List,
+
+    /// One smaller link per row, showing only the icon and name.
+    Compact,
+}
+
+/// How much space is left between and around links, see `Theme.density`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Density {
+    /// Tighter spacing, fitting more links on screen.
+    Compact,
+
+    /// The spacing the templates were designed with.
+    #[default]
+    Comfortable,
+
+    /// Looser spacing, for pages with few links or large screens.
+    Spacious,
+}
+
+impl Density {
+    /// Finds the factor templates multiply their spacing by, exposed to them as `density_scale`.
+    pub fn scale(self) -> f64 {
+        match self {
+            Self::Compact => 0.6,
+            Self::Comfortable => 1.0,
+            Self::Spacious => 1.4,
+        }
+    }
+}
+
+/// Settings that make links open faster.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Performance {
+    /// Indicates if the browser should be hinted to connect to the sites of the most prominent
+    /// links as soon as the page is opened, so they load faster when clicked. Large links of the
+    /// page shown first are hinted first, then the links of its first section.
+    #[serde(default)]
+    pub resource_hints: bool,
+
+    /// Largest number of sites hinted. Browsers limit how many connections are kept open, so
+    /// hinting many sites wastes them.
+    #[serde(default = "Performance::default_max_resource_hints")]
+    pub max_resource_hints: usize,
+}
+
+impl Performance {
+    /// Default value for `Performance.max_resource_hints`
+    fn default_max_resource_hints() -> usize {
+        4
+    }
+}
+
+impl Default for Performance {
+    fn default() -> Self {
+        Self {
+            resource_hints: Default::default(),
+            max_resource_hints: Performance::default_max_resource_hints(),
+        }
+    }
+}
+
+/// Settings that change how the page is built, rather than what it shows.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Build {
+    /// Kind of device the page is laid out for.
+    #[serde(default)]
+    pub layout: BuildLayout,
+
+    /// Indicates if a mobile variant should also be built when building a desktop page to a file.
+    /// The variant is written next to the page, named after it with a `.mobile` suffix, e.g.
+    /// `newtab.mobile.html`.
+    #[serde(default)]
+    pub mobile_variant: bool,
+
+    /// Size site icons are resized to, in pixels. Larger sizes look sharper on high density
+    /// displays, at the cost of a larger output.
+    #[serde(default = "Build::default_icon_size")]
+    pub icon_size: u32,
+
+    /// Indicates if the rendered HTML should be minified.
+    #[serde(default = "Build::default_minify_html")]
+    pub minify_html: bool,
+
+    /// Indicates if the compiled CSS should be minified.
+    #[serde(default = "Build::default_minify_css")]
+    pub minify_css: bool,
+
+    /// Size, in bytes, the output is expected to stay under. Larger output is reported as
+    /// oversized, failing strict builds.
+    #[serde(default)]
+    pub max_output_bytes: Option<usize>,
+
+    /// Largest size, in bytes, of a single `<style>` block in the output. If the build options
+    /// also set a limit, the lower one applies.
+    #[serde(default)]
+    pub max_style_bytes: Option<usize>,
+
+    /// Largest size, in bytes, of a single data URL in the output. If the build options also set
+    /// a limit, the lower one applies.
+    #[serde(default)]
+    pub max_data_url_bytes: Option<usize>,
+}
+
+impl Build {
+    /// Default value for `Build.icon_size`
+    fn default_icon_size() -> u32 {
+        24
+    }
+
+    /// Default value for `Build.minify_html`
+    fn default_minify_html() -> bool {
+        true
+    }
+
+    /// Default value for `Build.minify_css`
+    fn default_minify_css() -> bool {
+        true
+    }
+}
+
+impl Default for Build {
+    fn default() -> Self {
+        Self {
+            layout: Default::default(),
+            mobile_variant: Default::default(),
+            icon_size: Build::default_icon_size(),
+            minify_html: Build::default_minify_html(),
+            minify_css: Build::default_minify_css(),
+            max_output_bytes: Default::default(),
+            max_style_bytes: Default::default(),
+            max_data_url_bytes: Default::default(),
+        }
+    }
+}
+
+/// Where and how the built page is served.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Output {
+    /// URL the page is hosted at, e.g. "https://example.com/newtab/". If it's an HTTPS URL, images
+    /// and other resources loaded over plain HTTP are reported, since browsers block them or mark
+    /// the page as insecure.
+    #[serde(default)]
+    pub base_url: Option<String>,
+
+    /// Indicates if resources loaded over plain HTTP should be embedded in the page when it's
+    /// hosted over HTTPS, where possible. Currently only applies to the background image.
+    #[serde(default)]
+    pub inline_insecure: bool,
+}
+
+/// Favicon services that can provide icons for websites, see `Config.icon_fallback`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IconService {
+    /// DuckDuckGo's favicon service, at `https://icons.duckduckgo.com/ip3/<host>.ico`.
+    DuckDuckGo,
+
+    /// Google's favicon service, at `https://www.google.com/s2/favicons?domain=<host>&sz=64`.
+    Google,
+
+    /// Another favicon service, as a URL where `{host}` is replaced with the website's host, e.g.
+    /// `https://icons.example.com/{host}.png`.
+    Custom(String),
+}
+
+impl IconService {
+    /// Creates the URL of the icon for a host.
+    pub fn icon_url(&self, host: &str) -> String {
+        match self {
+            IconService::DuckDuckGo => format!("https://icons.duckduckgo.com/ip3/{host}.ico"),
+            IconService::Google => {
+                format!("https://www.google.com/s2/favicons?domain={host}&sz=64")
+            }
+            IconService::Custom(url) => url.replace("{host}", host),
+        }
+    }
+}
+
+/// Kinds of devices the page can be laid out for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildLayout {
+    /// Sections side by side, for wide screens.
+    #[default]
+    Desktop,
+
+    /// Sections stacked in a single scrolling column with larger tap targets, for phones and other
+    /// narrow screens.
+    Mobile,
+}
+
 /// Theming preferences.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Theme {
-    /// Indicates if the template's dark theme should be used.
+    /// Indicates if the template's dark theme should be used. If `schedule` is provided, this is
+    /// only the variant shown until the schedule takes effect, or if scripts are disabled.
     #[serde(default = "Theme::default_dark")]
     pub dark: bool,
 
+    /// Switches between the light and dark variants by time of day, for systems that don't switch
+    /// color schemes themselves. Both variants are embedded in the page, with a small script that
+    /// picks one when the page is opened, and again every minute while it stays open.
+    #[serde(default)]
+    pub schedule: Option<ThemeSchedule>,
+
     /// Indicates if icons with low contrast against the template's background should be inverted
     /// preemptively.
     #[serde(default = "Theme::default_invert_low_contrast_icons")]
@@ -59,7 +623,69 @@ pub struct Theme {
     #[serde(default = "Theme::default_font_size")]
     pub font_size: u16,
 
-    /// Any other values provided.
+    /// How much space is left between and around links. Custom templates can scale their own
+    /// spacing by `density_scale`.
+    #[serde(default)]
+    pub density: Density,
+
+    /// Background image, as a local file path or a URL.
+    #[serde(default)]
+    pub background_image: Option<String>,
+
+    /// Indicates if the background image should be embedded in the page. Otherwise, it's
+    /// referenced as provided, so relative paths must be relative to the output file.
+    #[serde(default = "Theme::default_background_embed")]
+    pub background_embed: bool,
+
+    /// Maximum width of the embedded background image, in pixels. Wider images are downscaled.
+    #[serde(default = "Theme::default_background_max_width")]
+    pub background_max_width: u32,
+
+    /// JPEG quality of the embedded background image, from 1 to 100.
+    #[serde(default = "Theme::default_background_quality")]
+    pub background_quality: u8,
+
+    /// Radius to blur the background image by, in pixels.
+    #[serde(default)]
+    pub background_blur: f64,
+
+    /// Amount to dim the background image by, from 0 (unchanged) to 1 (hidden).
+    #[serde(default)]
+    pub background_dim: f64,
+
+    /// Indicates if accent and overlay colors should be derived from the background image and
+    /// exposed to the styles.
+    #[serde(default)]
+    pub derive_colors: bool,
+
+    /// Colors replacing the template's own, as CSS colors.
+    #[serde(default)]
+    pub colors: ThemeColors,
+
+    /// Minimum contrast ratio between the text or accent color and the background, ranging from 1
+    /// to 21. Pairs below it are reported at build time. Defaults to 4.5, the minimum for normal
+    /// text in [WCAG 2.1 level AA][1].
+    ///
+    /// [1]: <https://www.w3.org/TR/WCAG21/#contrast-minimum>
+    #[serde(default = "Theme::default_min_contrast")]
+    pub min_contrast: f32,
+
+    /// How links are laid out in sections that don't specify a layout.
+    #[serde(default)]
+    pub layout: Layout,
+
+    /// Indicates if page icons should be colored with CSS instead of their hardcoded fills, so
+    /// they can be tinted with `icon_color`, the derived accent color, or the color of each page.
+    #[serde(default)]
+    pub tint_icons: bool,
+
+    /// Color to tint page icons with when `tint_icons` is set, as a CSS color. If not provided,
+    /// the accent color is used, see `colors.accent`.
+    #[serde(default)]
+    pub icon_color: Option<String>,
+
+    /// Any other values provided, for use by custom templates. Keys that no template references
+    /// are reported after building, since they are likely typos.
     #[serde(default, flatten)]
     pub custom: HashMap<String, tera::Value>,
 }
@@ -84,20 +710,147 @@ impl Theme {
     fn default_font_size() -> u16 {
         14
     }
+
+    /// Default value for `Theme.background_embed`
+    fn default_background_embed() -> bool {
+        true
+    }
+
+    /// Default value for `Theme.background_max_width`
+    fn default_background_max_width() -> u32 {
+        1920
+    }
+
+    /// Default value for `Theme.background_quality`
+    fn default_background_quality() -> u8 {
+        80
+    }
+
+    /// Default value for `Theme.min_contrast`
+    fn default_min_contrast() -> f32 {
+        4.5
+    }
 }
 
 impl Default for Theme {
     fn default() -> Self {
         Self {
             dark: Theme::default_dark(),
+            schedule: Default::default(),
             invert_low_contrast_icons: Theme::default_invert_low_contrast_icons(),
             font_family: Theme::default_font_family(),
             font_size: Theme::default_font_size(),
+            density: Default::default(),
+            background_image: Default::default(),
+            background_embed: Theme::default_background_embed(),
+            background_max_width: Theme::default_background_max_width(),
+            background_quality: Theme::default_background_quality(),
+            background_blur: Default::default(),
+            background_dim: Default::default(),
+            derive_colors: Default::default(),
+            colors: Default::default(),
+            min_contrast: Theme::default_min_contrast(),
+            layout: Default::default(),
+            tint_icons: Default::default(),
+            icon_color: Default::default(),
             custom: Default::default(),
         }
     }
 }
 
+/// Times of day to switch between the light and dark variants of the theme, see `Theme.schedule`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThemeSchedule {
+    /// Time the light variant is shown from, as "HH:MM" in 24-hour time.
+    #[serde(default = "ThemeSchedule::default_light")]
+    pub light: String,
+
+    /// Time the dark variant is shown from, as "HH:MM" in 24-hour time.
+    #[serde(default = "ThemeSchedule::default_dark")]
+    pub dark: String,
+
+    /// IANA time zone the times are in, e.g. "Europe/Berlin". If not provided, the local time of
+    /// the device showing the page is used.
+    #[serde(default)]
+    pub time_zone: Option<String>,
+}
+
+impl ThemeSchedule {
+    /// Default value for `ThemeSchedule.light`
+    fn default_light() -> String {
+        "07:00".into()
+    }
+
+    /// Default value for `ThemeSchedule.dark`
+    fn default_dark() -> String {
+        "19:00".into()
+    }
+}
+
+impl Default for ThemeSchedule {
+    fn default() -> Self {
+        Self {
+            light: ThemeSchedule::default_light(),
+            dark: ThemeSchedule::default_dark(),
+            time_zone: Default::default(),
+        }
+    }
+}
+
+/// Colors replacing the template's own, as CSS colors. Colors that aren't provided are left as
+/// the template defines them, depending on `Theme.dark`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThemeColors {
+    /// Color of the page background.
+    #[serde(default)]
+    pub background: Option<String>,
+
+    /// Color of text.
+    #[serde(default)]
+    pub foreground: Option<String>,
+
+    /// Color of highlights, such as tinted page icons and focus outlines. If not provided, the
+    /// derived accent color is used if colors are derived (see `Theme.derive_colors`), or the text
+    /// color otherwise.
+    #[serde(default)]
+    pub accent: Option<String>,
+}
+
+/// Widgets, built alongside the pages.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Widgets {
+    /// RSS/Atom feeds, fetched at build time.
+    #[serde(default)]
+    pub feeds: Vec<Feed>,
+}
+
+/// An RSS/Atom feed, fetched at build time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Feed {
+    /// Feed URL.
+    pub url: String,
+
+    /// Display name. If not provided, the title specified by the feed will be used.
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Name of the page to show the feed on. If not provided, the feed will be shown on the first
+    /// page.
+    #[serde(default)]
+    pub page: Option<String>,
+
+    /// Maximum number of items to show, newest first.
+    #[serde(default = "Feed::default_limit")]
+    pub limit: usize,
+}
+
+impl Feed {
+    /// Default value for `Feed.limit`
+    fn default_limit() -> usize {
+        5
+    }
+}
+
 /// New tab page content.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Page {
@@ -116,12 +869,43 @@ pub struct Page {
     #[serde(default = "Page::default_icon_style")]
     pub icon_style: String,
 
+    /// Color to tint the page icon with when `Theme.tint_icons` is set, as a CSS color. If not
+    /// provided, `Theme.icon_color` is used.
+    #[serde(default)]
+    pub color: Option<String>,
+
+    /// Position of the page among the tabs. Pages with a lower weight are shown first, and pages
+    /// with the same weight keep their order from the configuration file.
+    #[serde(default, alias = "order")]
+    pub weight: i32,
+
     /// Sections of a page, containing links.
     #[serde(default)]
     pub sections: Vec<Section>,
+
+    /// Path of an HTML template rendered in place of the sections, for special pages such as
+    /// dashboards or embedded sites. Relative to the file containing the page, and only supported
+    /// in local config files. The template is rendered with the same filters and functions as the
+    /// page template, and is provided the page as `page` and its data as `data`.
+    #[serde(default)]
+    pub template: Option<String>,
+
+    /// Source of the template rendered in place of the sections. Read from `Page.template` when
+    /// the config is loaded, but can be provided inline instead, e.g. in remote configs.
+    #[serde(default)]
+    pub template_src: Option<String>,
+
+    /// Arbitrary data provided to the page's template as `data`.
+    #[serde(default)]
+    pub data: serde_json::Value,
 }
 
 impl Page {
+    /// Returns every link on the page, including those in nested sections.
+    pub fn all_links(&self) -> impl Iterator<Item = &Link> {
+        self.sections.iter().flat_map(Section::all_links)
+    }
+
     /// Default value for `Page.icon`
     fn default_icon() -> String {
         "image_not_supported".into()
@@ -139,9 +923,92 @@ pub struct Section {
     /// Section name.
     pub name: String,
 
+    /// Text shown under the section name, describing what the links are for. Supports markdown.
+    #[serde(default)]
+    pub description: Option<String>,
+
     /// Links in the sections.
     #[serde(default)]
     pub links: Vec<Link>,
+
+    /// Sections nested within this one, shown after its links. Nested sections may contain
+    /// sections of their own.
+    #[serde(default)]
+    pub sections: Vec<Section>,
+
+    /// How links are laid out. If not provided, the layout of the parent section is used, or
+    /// `Theme.layout` for top-level sections.
+    #[serde(default)]
+    pub layout: Option<Layout>,
+
+    /// Maximum number of links to show before the rest are hidden behind a "show more" control.
+    /// If not provided, all links are shown.
+    #[serde(default)]
+    pub max_visible: Option<usize>,
+
+    /// Web pages embedded in the section after its links, such as dashboard panels.
+    #[serde(default)]
+    pub embeds: Vec<Embed>,
+}
+
+impl Section {
+    /// Returns every link in the section, including those in nested sections.
+    pub fn all_links(&self) -> Box<dyn Iterator<Item = &Link> + '_> {
+        Box::new(
+            self.links
+                .iter()
+                .chain(self.sections.iter().flat_map(Section::all_links)),
+        )
+    }
+
+    /// Returns every embed in the section, including those in nested sections.
+    pub fn all_embeds(&self) -> Box<dyn Iterator<Item = &Embed> + '_> {
+        Box::new(
+            self.embeds
+                .iter()
+                .chain(self.sections.iter().flat_map(Section::all_embeds)),
+        )
+    }
+}
+
+/// A web page embedded in a section as a small frame, such as a dashboard panel, see
+/// `Section.embeds`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Embed {
+    /// URL of the embedded page. Must be an HTTP or HTTPS URL.
+    pub url: String,
+
+    /// Describes the embedded page to assistive technology. If not provided, the URL is used.
+    #[serde(default)]
+    pub title: Option<String>,
+
+    /// Width of the frame, in pixels.
+    #[serde(default = "Embed::default_width")]
+    pub width: u32,
+
+    /// Height of the frame, in pixels.
+    #[serde(default = "Embed::default_height")]
+    pub height: u32,
+
+    /// Restrictions to lift from the embedded page, as [sandbox tokens][1] such as
+    /// "allow-scripts". Embedded pages are fully sandboxed by default, so they can't run scripts,
+    /// submit forms, open popups, or access storage.
+    ///
+    /// [1]: <https://developer.mozilla.org/en-US/docs/Web/HTML/Element/iframe#sandbox>
+    #[serde(default)]
+    pub sandbox: Vec<String>,
+}
+
+impl Embed {
+    /// Default value for `Embed.width`
+    fn default_width() -> u32 {
+        400
+    }
+
+    /// Default value for `Embed.height`
+    fn default_height() -> u32 {
+        300
+    }
 }
 
 /// A link to a website.
@@ -152,4 +1019,49 @@ pub struct Link {
 
     /// Website URL.
     pub url: String,
+
+    /// Text describing the link, shown instead of the URL and as a tooltip, e.g. "NAS, rack 2".
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// Short label shown next to the name, e.g. "beta" or "internal".
+    #[serde(default)]
+    pub badge: Option<String>,
+
+    /// JSON endpoint fetched when the new tab page is opened. The result replaces the badge text,
+    /// and the badge is hidden if the result is empty or zero. Useful for counts, like unread
+    /// items.
+    #[serde(default)]
+    pub badge_url: Option<String>,
+
+    /// Dot-separated path to the badge value within the response from `badge_url`, e.g.
+    /// "data.unread". If not provided, the entire response is used.
+    #[serde(default)]
+    pub badge_field: Option<String>,
+
+    /// How prominently the link is shown, so frequently used links stand out without reordering
+    /// them.
+    #[serde(default)]
+    pub size: LinkSize,
+
+    /// Tags grouping related links across pages and sections, e.g. "dev" or "news". Templates
+    /// can show the links of each tag with `links_by_tag()`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// How prominently a link is shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkSize {
+    /// A bigger tile, or an emphasized entry in text-only templates. Spans two columns in the
+    /// grid layout.
+    Large,
+
+    /// The size given by the section's layout.
+    #[default]
+    Normal,
+
+    /// A smaller tile showing only the icon and name.
+    Small,
 }
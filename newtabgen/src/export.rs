@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Exports the links of a resolved config to other tools, such as app launchers.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{config::Config, LinksJson, LinksJsonSection};
+
+/// Formats links can be exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A JSON array of [Raycast quicklinks][1], which can be imported from Raycast's settings.
+    ///
+    /// [1]: <https://manual.raycast.com/quicklinks>
+    Raycast,
+
+    /// [Alfred Script Filter][1] JSON, for a workflow whose script filter prints the file.
+    ///
+    /// [1]: <https://www.alfredapp.com/help/workflows/inputs/script-filter/json/>
+    Alfred,
+}
+
+/// A Raycast quicklink.
+#[derive(Serialize)]
+struct RaycastQuicklink<'a> {
+    /// Display name.
+    name: String,
+
+    /// URL opened by the quicklink.
+    link: &'a str,
+}
+
+/// An item of an Alfred Script Filter.
+#[derive(Serialize)]
+struct AlfredItem<'a> {
+    /// Unique identifier, used by Alfred to learn which items are chosen most often.
+    uid: &'a str,
+
+    /// Display name.
+    title: &'a str,
+
+    /// Text shown under the title.
+    subtitle: String,
+
+    /// URL passed to the workflow's next action.
+    arg: &'a str,
+
+    /// Text filled in when the item is tab-completed.
+    autocomplete: &'a str,
+}
+
+/// The root of an Alfred Script Filter.
+#[derive(Serialize)]
+struct AlfredItems<'a> {
+    /// Each item.
+    items: Vec<AlfredItem<'a>>,
+}
+
+/// A link, and the names of the page and sections containing it.
+struct Entry<'a> {
+    /// Names of the page and sections containing the link, outermost first.
+    path: Vec<&'a str>,
+
+    /// Display name.
+    name: &'a str,
+
+    /// Website URL.
+    url: &'a str,
+}
+
+/// Exports every link in the config.
+///
+/// # Arguments
+///
+/// * `config` - The resolved config, see [`resolve_config`][crate::resolve_config].
+/// * `format` - Format to export the links in.
+///
+/// # Errors
+///
+/// Returns an error if serializing the links fails.
+///
+/// # Returns
+///
+/// The exported links, as pretty-printed JSON.
+pub fn export(config: &Config, format: ExportFormat) -> Result<String, serde_json::Error> {
+    let links = LinksJson::new(config, &BTreeMap::new());
+    let mut entries = Vec::new();
+    for page in &links.pages {
+        for section in &page.sections {
+            collect_entries(section, vec![page.name.as_str()], &mut entries);
+        }
+    }
+
+    match format {
+        ExportFormat::Raycast => {
+            let quicklinks = entries
+                .iter()
+                .map(|e| RaycastQuicklink {
+                    name: format!("{} ({})", e.name, e.path.join(" › ")),
+                    link: e.url,
+                })
+                .collect::<Vec<_>>();
+            serde_json::to_string_pretty(&quicklinks)
+        }
+        ExportFormat::Alfred => {
+            let items = entries
+                .iter()
+                .map(|e| AlfredItem {
+                    uid: e.url,
+                    title: e.name,
+                    subtitle: format!("{} · {}", e.path.join(" › "), e.url),
+                    arg: e.url,
+                    autocomplete: e.name,
+                })
+                .collect::<Vec<_>>();
+            serde_json::to_string_pretty(&AlfredItems { items })
+        }
+    }
+}
+
+/// Collects the links of a section and its nested sections.
+fn collect_entries<'a>(
+    section: &'a LinksJsonSection,
+    mut path: Vec<&'a str>,
+    entries: &mut Vec<Entry<'a>>,
+) {
+    path.push(&section.name);
+    for link in &section.links {
+        entries.push(Entry {
+            path: path.clone(),
+            name: &link.name,
+            url: &link.url,
+        });
+    }
+    for section in &section.sections {
+        collect_entries(section, path.clone(), entries);
+    }
+}
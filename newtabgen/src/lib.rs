@@ -2,17 +2,72 @@
 
 //! Library for newtabgen-cli.
 //! Create static new tab pages from a config file.
+//!
+//! # Features
+//!
+//! These are enabled by default. Disable them with `default-features = false` for a smaller
+//! dependency tree when embedding the library.
+//!
+//! * `feeds` - Fetch RSS/Atom feeds. Without it, configured feeds are reported as errors.
+//! * `markdown` - Render section descriptions as markdown. Without it, they're plain text.
+//! * `remote-icons` - Provide [`RemoteIconFetcher`]. Without it, uncached site icons must come
+//!   from a custom [`IconFetcher`], such as [`LocalIconFetcher`].
 
 #![warn(missing_docs)]
 #![warn(clippy::missing_docs_in_private_items)]
 
+mod page_files;
+mod refs;
+mod rewrite;
+mod sort;
+mod tera_ast;
 mod tera_filters;
 mod tera_functions;
+mod theme_bundle;
 
 pub mod config;
+pub mod export;
+pub mod lint;
 pub mod resources;
+pub mod template_store;
+pub mod update;
 pub mod util;
 
 mod builder;
 pub use builder::build;
+pub use builder::build_profiles;
+pub use builder::build_to_path;
+pub use builder::diagnostic::TemplateDiagnostic;
+#[cfg(feature = "remote-icons")]
+pub use builder::icon_fetcher::RemoteIconFetcher;
+pub use builder::icon_fetcher::{FetchedIcon, IconFetcher, IconSource, LocalIconFetcher};
+pub use builder::links_json::{
+    LinksJson, LinksJsonLink, LinksJsonPage, LinksJsonSection, LINKS_JSON_VERSION,
+};
+pub use builder::mobile_variant_path;
+pub use builder::phases;
+pub use builder::plan::{plan_network, plan_resource, NetworkKind, NetworkOperation};
+pub use builder::progress::{Phase, Progress, ProgressSender};
+pub use builder::provenance::Provenance;
+pub use builder::rate_limit::HostRateLimiter;
+pub use builder::report::{
+    A11yIssueKind, A11yIssueReport, BuildReport, DuplicateLinkReport, LowContrastIconReport,
+    MixedContentKind, MixedContentReport, OversizedKind, OversizedReport, PhaseReport,
+    SiteIconReport, ThemeColorPair, ThemeContrastReport, Warning, WarningKind,
+};
+pub use builder::resolve::{ResolveOptions, ResolvedConfig};
+pub use builder::resolve_config;
+pub use builder::site_icons::SiteIconError;
+pub use builder::template_context;
 pub use builder::BuildError as Error;
+pub use builder::BuildOptions;
+pub use builder::CacheMode;
+pub use builder::Minify;
+pub use builder::Target;
+pub use builder::TeraHook;
+pub use builder::{
+    DEFAULT_ICON_HOST_INTERVAL, DEFAULT_MAX_DATA_URL_BYTES, DEFAULT_MAX_ICON_CONNECTIONS,
+    DEFAULT_MAX_STYLE_BYTES,
+};
+pub use tera;
+pub use tokio_util::sync::CancellationToken;
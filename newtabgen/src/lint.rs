@@ -0,0 +1,186 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Checks custom templates for mistakes without building, such as misspelled variables or
+//! filters.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+};
+
+use serde::Serialize;
+use tera::Value;
+
+use crate::{
+    builder::{
+        phases::{self, Artifacts, Background},
+        pwa::PwaFiles,
+    },
+    config::Config,
+    tera_ast, TeraHook,
+};
+
+/// Kinds of templates, each rendered with a different context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateKind {
+    /// The HTML template, see [`Resources::html`][crate::resources::Resources::html].
+    Html,
+
+    /// The SCSS styles, see [`Resources::scss`][crate::resources::Resources::scss].
+    Scss,
+}
+
+/// Kinds of problems found in a template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintIssueKind {
+    /// A variable that isn't in the template context.
+    UndefinedVariable,
+
+    /// A function that isn't registered.
+    UnknownFunction,
+
+    /// A filter that isn't registered.
+    UnknownFilter,
+
+    /// A test that isn't registered.
+    UnknownTest,
+
+    /// A macro that isn't defined in the template.
+    UnknownMacro,
+}
+
+/// A problem found in a template.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct LintIssue {
+    /// Kind of problem.
+    pub kind: LintIssueKind,
+
+    /// Name of the variable, function, filter, test, or macro.
+    pub name: String,
+}
+
+impl fmt::Display for LintIssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::UndefinedVariable => "undefined variable",
+            Self::UnknownFunction => "unknown function",
+            Self::UnknownFilter => "unknown filter",
+            Self::UnknownTest => "unknown test",
+            Self::UnknownMacro => "unknown macro",
+        })
+    }
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} '{}'", self.kind, self.name)
+    }
+}
+
+/// Checks a template for names that can't be resolved when building, without rendering it.
+///
+/// Variables are checked against the context the template is rendered with. Within the config,
+/// paths are followed as far as the config has objects to follow, so `config.theme.dark` is
+/// checked but `config.pages[0].name` is only checked up to `pages`. Names bound within the
+/// template, such as loop variables, are assumed to be defined wherever they're used.
+///
+/// # Arguments
+///
+/// * `src` - The template to check.
+/// * `kind` - Kind of template, which decides the context it's checked against.
+/// * `config` - The config the template will be built with. Custom theme keys are only known to
+///   be defined if they're in this config.
+/// * `hooks` - Hooks the template will be built with (see
+///   [`BuildOptions::tera_hooks`][crate::BuildOptions::tera_hooks]). The filters and functions
+///   they register are known to be defined.
+///
+/// # Errors
+///
+/// Returns an error if the template can't be parsed.
+///
+/// # Returns
+///
+/// Each problem found, sorted by kind and name.
+pub fn lint_template(
+    src: &str,
+    kind: TemplateKind,
+    config: &Config,
+    hooks: &[TeraHook],
+) -> Result<Vec<LintIssue>, tera::Error> {
+    let usage = tera_ast::usage(src)?;
+    let tera = phases::tera(config, hooks);
+    let context = match kind {
+        TemplateKind::Html => {
+            phases::html_context(config, &Artifacts::default(), &PwaFiles::default())
+        }
+        TemplateKind::Scss => phases::css_context(config, &Background::default(), &BTreeMap::new()),
+    }
+    .into_json();
+
+    let mut issues = Vec::new();
+    let mut issue = |kind, name: &str| {
+        issues.push(LintIssue {
+            kind,
+            name: name.into(),
+        })
+    };
+    for variable in &usage.variables {
+        if !usage.optional_variables.contains(variable)
+            && !is_defined(variable, &context, &usage.locals)
+        {
+            issue(LintIssueKind::UndefinedVariable, variable);
+        }
+    }
+    for function in &usage.functions {
+        if tera.get_function(function).is_err() {
+            issue(LintIssueKind::UnknownFunction, function);
+        }
+    }
+    for filter in &usage.filters {
+        // Handled by the renderer itself, rather than registered
+        let builtin = matches!(filter.as_str(), "default" | "safe");
+        if !builtin && tera.get_filter(filter).is_err() {
+            issue(LintIssueKind::UnknownFilter, filter);
+        }
+    }
+    for test in &usage.tests {
+        if tera.get_tester(test).is_err() {
+            issue(LintIssueKind::UnknownTest, test);
+        }
+    }
+    for call in &usage.macro_calls {
+        let defined = call
+            .strip_prefix("self::")
+            .is_some_and(|name| usage.macro_definitions.contains(name));
+        if !defined {
+            issue(LintIssueKind::UnknownMacro, call);
+        }
+    }
+
+    issues.sort_unstable();
+    Ok(issues)
+}
+
+/// Checks if a variable path resolves in the context. Only the segments before the first
+/// subscript are checked, since subscripts may be variables themselves.
+fn is_defined(path: &str, context: &Value, locals: &HashSet<String>) -> bool {
+    let dotted = path.split('[').next().unwrap_or_default();
+    let first = dotted.split('.').next().unwrap_or_default();
+    if locals.contains(first) || first == "__tera_context" {
+        return true;
+    }
+
+    let mut value = context;
+    for segment in dotted.split('.') {
+        match value {
+            Value::Object(map) => match map.get(segment) {
+                Some(next) => value = next,
+                None => return false,
+            },
+            // Arrays, nulls, and scalars can't be checked any further
+            _ => return true,
+        }
+    }
+    true
+}
@@ -0,0 +1,170 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Resolves pages defined in separate files, keeping configs with many pages readable, and the
+//! templates of pages that provide one (see `Page.template`).
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::resources::{self, ResourceError};
+
+/// Key of a page entry holding the path of the file that defines the page.
+const FILE_KEY: &str = "file";
+
+/// Key of a page holding the path of its template.
+const TEMPLATE_KEY: &str = "template";
+
+/// Key of a page holding the source of its template, which is read from [`TEMPLATE_KEY`].
+const TEMPLATE_SRC_KEY: &str = "template_src";
+
+/// Errors that may occur when resolving page files.
+#[derive(Error, Debug)]
+pub enum PageFileError {
+    /// Occurs when reading a page file fails.
+    #[error("failed to read page file: '{1}' ({0})")]
+    Read(#[source] io::Error, String),
+
+    /// Occurs when reading the template of a page fails.
+    #[error("failed to read page template: '{1}' ({0})")]
+    Template(#[source] io::Error, String),
+
+    /// Occurs when parsing a page file fails.
+    #[error("failed to parse page file: '{1}' ({0})")]
+    Parse(#[source] ResourceError, String),
+
+    /// Occurs when a page file refers back to itself, directly or indirectly.
+    #[error("page file cycle detected: '{0}'")]
+    Cycle(String),
+
+    /// Occurs when the config isn't a local file, so relative paths can't be resolved.
+    #[error("page files are only supported in local config files: '{0}'")]
+    Unsupported(String),
+
+    /// Occurs when a page entry is malformed.
+    #[error("invalid page file entry: {0}")]
+    Invalid(String),
+}
+
+/// Replaces each page entry containing `file`, in `pages` and in the pages of each profile, with
+/// the contents of the file. Paths are relative to the file containing the entry, and page files
+/// may themselves be entries containing `file`. Other keys next to `file` override the keys of the
+/// page, e.g. its `weight`. The template of each page is read relative to the file containing
+/// the page too.
+///
+/// # Arguments
+///
+/// * `value` - The parsed config, before references are resolved.
+/// * `base_dir` - Directory of the config file, or `None` if the config isn't a local file.
+///
+/// # Errors
+///
+/// Returns an error if a page file or template can't be read, if a page file can't be parsed, if
+/// page files form a cycle, or if the config isn't a local file.
+pub(crate) fn resolve(value: &mut Value, base_dir: Option<&Path>) -> Result<(), PageFileError> {
+    let Value::Object(root) = value else {
+        return Ok(());
+    };
+    if let Some(Value::Array(pages)) = root.get_mut("pages") {
+        for page in pages {
+            resolve_page(page, base_dir, &mut Vec::new())?;
+        }
+    }
+    if let Some(Value::Object(profiles)) = root.get_mut("profiles") {
+        let pages = profiles
+            .values_mut()
+            .filter_map(|profile| profile.get_mut("pages"))
+            .filter_map(Value::as_array_mut)
+            .flatten();
+        for page in pages {
+            resolve_page(page, base_dir, &mut Vec::new())?;
+        }
+    }
+    Ok(())
+}
+
+/// Replaces a page entry with the contents of its file, if it has one, and reads its template.
+///
+/// # Arguments
+///
+/// * `page` - The page entry.
+/// * `base_dir` - Directory that the path of the file is relative to.
+/// * `stack` - The page files currently being resolved, used to detect cycles.
+fn resolve_page(
+    page: &mut Value,
+    base_dir: Option<&Path>,
+    stack: &mut Vec<PathBuf>,
+) -> Result<(), PageFileError> {
+    let Value::Object(map) = page else {
+        return Ok(());
+    };
+    resolve_template(map, base_dir)?;
+    let Some(file) = map.remove(FILE_KEY) else {
+        return Ok(());
+    };
+    let Value::String(file) = file else {
+        return Err(PageFileError::Invalid(format!(
+            "'{FILE_KEY}' must be a string"
+        )));
+    };
+    let Some(base_dir) = base_dir else {
+        return Err(PageFileError::Unsupported(file));
+    };
+
+    let path = base_dir.join(&file);
+    let display = path.to_string_lossy().to_string();
+    let path = fs::canonicalize(&path).map_err(|e| PageFileError::Read(e, display.clone()))?;
+    if stack.contains(&path) {
+        return Err(PageFileError::Cycle(display));
+    }
+    let src = fs::read_to_string(&path).map_err(|e| PageFileError::Read(e, display.clone()))?;
+    let mut contents = resources::parse_config(&src, serde_any::guess_format(&path))
+        .map_err(|e| PageFileError::Parse(e, display.clone()))?;
+
+    let dir = path.parent().map(Path::to_path_buf);
+    stack.push(path);
+    resolve_page(&mut contents, dir.as_deref(), stack)?;
+    stack.pop();
+
+    let Value::Object(mut contents) = contents else {
+        return Err(PageFileError::Invalid(format!(
+            "'{display}' must define a page"
+        )));
+    };
+    contents.append(map);
+    *page = Value::Object(contents);
+    Ok(())
+}
+
+/// Reads the template of a page into [`TEMPLATE_SRC_KEY`], if it has one.
+///
+/// # Arguments
+///
+/// * `map` - The page entry.
+/// * `base_dir` - Directory that the path of the template is relative to.
+fn resolve_template(
+    map: &mut Map<String, Value>,
+    base_dir: Option<&Path>,
+) -> Result<(), PageFileError> {
+    let Some(template) = map.get(TEMPLATE_KEY) else {
+        return Ok(());
+    };
+    let Value::String(template) = template else {
+        return Err(PageFileError::Invalid(format!(
+            "'{TEMPLATE_KEY}' must be a string"
+        )));
+    };
+    let Some(base_dir) = base_dir else {
+        return Err(PageFileError::Unsupported(template.clone()));
+    };
+
+    let path = base_dir.join(template);
+    let src = fs::read_to_string(&path)
+        .map_err(|e| PageFileError::Template(e, path.to_string_lossy().to_string()))?;
+    map.insert(TEMPLATE_SRC_KEY.into(), Value::String(src));
+    Ok(())
+}
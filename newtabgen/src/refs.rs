@@ -0,0 +1,117 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Resolves `$ref` references and YAML merge keys within a parsed config, allowing fragments to be
+//! reused in any config format.
+
+use serde_json::Value;
+use thiserror::Error;
+
+/// Errors that may occur when resolving references.
+#[derive(Error, Debug)]
+pub enum RefError {
+    /// Occurs when a reference points to a location that doesn't exist.
+    #[error("reference not found: '{0}'")]
+    NotFound(String),
+
+    /// Occurs when a reference refers back to itself, directly or indirectly.
+    #[error("reference cycle detected: '{0}'")]
+    Cycle(String),
+
+    /// Occurs when a reference or merge key is malformed.
+    #[error("invalid reference: {0}")]
+    Invalid(String),
+}
+
+/// Resolves all references within a config, in place.
+///
+/// * An object containing `$ref` is replaced by the value at the referenced location, given as a
+///   JSON pointer prefixed with `#`, e.g. `#/definitions/links`. If the referenced value is an
+///   object, other keys next to `$ref` override its keys.
+/// * An object containing the YAML merge key `<<` has the keys of the merged object(s) added to
+///   it, unless already present.
+///
+/// # Errors
+///
+/// Returns an error if a reference is malformed, can't be found, or forms a cycle.
+pub(crate) fn resolve(value: &mut Value) -> Result<(), RefError> {
+    let root = value.clone();
+    resolve_value(value, &root, &mut Vec::new())
+}
+
+/// Recursively resolves the references within a value.
+///
+/// # Arguments
+///
+/// * `value` - The value to resolve references in.
+/// * `root` - The unresolved root of the config, which references point into.
+/// * `stack` - The references currently being resolved, used to detect cycles.
+fn resolve_value(value: &mut Value, root: &Value, stack: &mut Vec<String>) -> Result<(), RefError> {
+    match value {
+        Value::Object(map) => {
+            if let Some(merge) = map.remove("<<") {
+                let sources = match merge {
+                    Value::Array(sources) => sources,
+                    source => vec![source],
+                };
+                for mut source in sources {
+                    resolve_value(&mut source, root, stack)?;
+                    let Value::Object(source) = source else {
+                        return Err(RefError::Invalid(
+                            "merge key '<<' requires a mapping".into(),
+                        ));
+                    };
+                    for (key, value) in source {
+                        map.entry(key).or_insert(value);
+                    }
+                }
+            }
+
+            if let Some(reference) = map.remove("$ref") {
+                let Value::String(reference) = reference else {
+                    return Err(RefError::Invalid("'$ref' must be a string".into()));
+                };
+                let Some(pointer) = reference.strip_prefix('#') else {
+                    return Err(RefError::Invalid(format!(
+                        "'{reference}' must start with '#'"
+                    )));
+                };
+                if stack.contains(&reference) {
+                    return Err(RefError::Cycle(reference));
+                }
+                let mut target = root
+                    .pointer(pointer)
+                    .cloned()
+                    .ok_or_else(|| RefError::NotFound(reference.clone()))?;
+
+                stack.push(reference);
+                resolve_value(&mut target, root, stack)?;
+                let reference = stack.pop().unwrap_or_default();
+
+                if map.is_empty() {
+                    *value = target;
+                    return Ok(());
+                }
+                let Value::Object(mut target) = target else {
+                    return Err(RefError::Invalid(format!(
+                        "'{reference}' isn't an object, so other keys can't be added to it"
+                    )));
+                };
+                target.append(map);
+                *value = Value::Object(target);
+            }
+
+            if let Value::Object(map) = value {
+                for value in map.values_mut() {
+                    resolve_value(value, root, stack)?;
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                resolve_value(value, root, stack)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
@@ -2,12 +2,26 @@
 
 //! Handles loading resources needed for building a new tab page.
 
-use resource::{resource, resource_str};
-use tracing::{event, Level};
+use resource::resource_str;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{debug, event, warn, Level};
+use url::Url;
 
-use std::{fs, path::PathBuf, str};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    str,
+    sync::OnceLock,
+};
 
-use crate::config::Config;
+use crate::{
+    builder::CacheMode,
+    config::{BuildLayout, Config},
+    page_files, refs, template_store, theme_bundle, util,
+};
 
 /// Errors that may occur when loading resources.
 #[derive(thiserror::Error, Debug)]
@@ -20,70 +34,847 @@ pub enum ResourceError {
     #[error("failed to parse resource: {0}")]
     Parse(String),
 
+    /// Occurs when loading a page from a separate file fails.
+    #[error("failed to load page from file: {0}")]
+    PageFile(String),
+
+    /// Occurs when resolving a reference within a resource fails.
+    #[error("failed to resolve reference in resource: {0}")]
+    Reference(String),
+
+    /// Occurs when no built-in example has the requested name.
+    #[error("unknown example: '{0}'")]
+    Example(String),
+
+    /// Occurs when no built-in template pack has the requested name.
+    #[error("unknown template pack: '{0}'")]
+    TemplatePack(String),
+
+    /// Occurs when the config has no profile with the requested name.
+    #[error("unknown profile: '{0}'")]
+    Profile(String),
+
+    /// Occurs when downloading a resource fails.
+    #[error("failed to download resource: {0}")]
+    Http(String),
+
     /// Occurs when encoding a resource to UTF-8 fails.
     #[error("UTF-8 conversion failed for resource: {0}")]
     Utf8(String),
+
+    /// Occurs when reading the config from stdin fails.
+    #[error("failed to read config from stdin ({0})")]
+    Stdin(String),
+
+    /// Occurs when loading a theme bundle fails.
+    #[error("failed to load theme: {0}")]
+    Theme(String),
+}
+
+/// Formats a config can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// JSON.
+    Json,
+
+    /// YAML.
+    Yaml,
+
+    /// TOML.
+    Toml,
+}
+
+impl ConfigFormat {
+    /// Converts the format to the corresponding [`serde_any::Format`].
+    fn as_serde_any(self) -> serde_any::Format {
+        match self {
+            Self::Json => serde_any::Format::Json,
+            Self::Yaml => serde_any::Format::Yaml,
+            Self::Toml => serde_any::Format::Toml,
+        }
+    }
+}
+
+/// Config paths that read the config from stdin instead of a file.
+pub const STDIN_PATHS: [&str; 2] = ["-", "/dev/stdin"];
+
+/// The config read from stdin, which can only be read once, but may be loaded several times.
+static STDIN_CONFIG: OnceLock<Result<String, String>> = OnceLock::new();
+
+/// A built-in example config.
+#[derive(Debug, Clone, Copy)]
+pub struct Example {
+    /// Name used to select the example.
+    pub name: &'static str,
+
+    /// What the example shows.
+    pub description: &'static str,
+}
+
+/// Every built-in example config. The first is used if no example is requested.
+pub const EXAMPLES: &[Example] = &[
+    Example {
+        name: "default",
+        description: "A single page filled with placeholder links",
+    },
+    Example {
+        name: "minimal",
+        description: "The smallest useful config, a single section of links",
+    },
+    Example {
+        name: "dev",
+        description: "Multiple pages of developer links, sorted alphabetically",
+    },
+    Example {
+        name: "homelab",
+        description: "Self-hosted services with badges, descriptions, and natural sorting",
+    },
+    Example {
+        name: "kitchen-sink",
+        description: "Every feature at once, including feeds, references, and lazy icons",
+    },
+];
+
+/// Returns the source of a built-in example config, which is always JSON.
+fn example_src(name: &str) -> Option<String> {
+    let src = match name {
+        "default" => resource_str!("example/example.json"),
+        "minimal" => resource_str!("example/minimal.json"),
+        "dev" => resource_str!("example/dev.json"),
+        "homelab" => resource_str!("example/homelab.json"),
+        "kitchen-sink" => resource_str!("example/kitchen-sink.json"),
+        _ => return None,
+    };
+    Some(src.to_string())
+}
+
+/// A built-in template pack, an HTML template and SCSS styles that are meant to be used together.
+#[derive(Debug, Clone, Copy)]
+pub struct TemplatePack {
+    /// Name used to select the template pack.
+    pub name: &'static str,
+
+    /// What the template pack looks like.
+    pub description: &'static str,
+}
+
+/// Every built-in template pack. The first is used if no template pack is requested.
+pub const TEMPLATE_PACKS: &[TemplatePack] = &[
+    TemplatePack {
+        name: "default",
+        description: "Tabbed pages of links with site icons and material design icons",
+    },
+    TemplatePack {
+        name: "portrait",
+        description: "A single column of large links with tabs along the bottom, for tall screens",
+    },
+    TemplatePack {
+        name: "text",
+        description: "Plain text links in the system's monospace font, without icons or scripts",
+    },
+];
+
+/// Returns the HTML template and SCSS styles of a built-in template pack.
+pub(crate) fn template_pack_src(name: &str) -> Option<(String, String)> {
+    let (html, scss) = match name {
+        "default" => (
+            resource_str!("res/index.html"),
+            resource_str!("res/styles.scss"),
+        ),
+        "portrait" => (
+            resource_str!("res/portrait/index.html"),
+            resource_str!("res/portrait/styles.scss"),
+        ),
+        "text" => (
+            resource_str!("res/text/index.html"),
+            resource_str!("res/text/styles.scss"),
+        ),
+        _ => return None,
+    };
+    Some((html.to_string(), scss.to_string()))
+}
+
+/// A source of the resources needed for building a new tab page. Implement this to load
+/// resources from somewhere other than the built-in implementations support.
+pub trait ResourceSet: Send + Sync {
+    /// Loads the config, resolving any references within it (see [`crate::config`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if loading, parsing or resolving references within the [`Config`] fails.
+    fn config(&self) -> Result<Config, ResourceError>;
+
+    /// Loads the HTML template.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if loading the template fails.
+    fn html(&self) -> Result<String, ResourceError>;
+
+    /// Loads the SCSS template.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if loading the template fails.
+    fn scss(&self) -> Result<String, ResourceError>;
+
+    /// Loads the files bundled with the theme, keyed by their path within its `assets` directory.
+    /// Templates can embed them as data URLs, from `theme_assets`. There are none by default.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if loading the files fails.
+    fn theme_assets(&self) -> Result<BTreeMap<String, Vec<u8>>, ResourceError> {
+        Ok(BTreeMap::new())
+    }
+}
+
+/// Name of the file describing a [`ThemeBundle`], which must be at its root.
+const THEME_MANIFEST: &str = "theme.toml";
+
+/// Name of the directory holding the assets of a [`ThemeBundle`].
+const THEME_ASSETS_DIR: &str = "assets/";
+
+/// Describes a [`ThemeBundle`], read from its `theme.toml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ThemeManifest {
+    /// Name of the theme.
+    pub name: String,
+
+    /// Short description of the theme.
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// Author of the theme.
+    #[serde(default)]
+    pub author: Option<String>,
+
+    /// Name of the [built-in template pack][TEMPLATE_PACKS] used for the template or styles the
+    /// theme doesn't provide. Defaults to the first template pack.
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+/// A template and styles distributed together, as a directory or zip archive containing:
+///
+/// * `theme.toml` - The [manifest][ThemeManifest].
+/// * `index.html` - The HTML template, optional.
+/// * `styles.scss` - The SCSS template, optional.
+/// * `assets/` - Files templates can embed, such as fonts and images, optional (see
+///   [`ResourceSet::theme_assets`]).
+#[derive(Debug, Clone, Default)]
+pub struct ThemeBundle {
+    /// Describes the theme.
+    pub manifest: ThemeManifest,
+
+    /// The HTML template, if the theme provides one.
+    pub html: Option<String>,
+
+    /// The SCSS template, if the theme provides one.
+    pub scss: Option<String>,
+
+    /// Files in the `assets` directory, keyed by their path within it.
+    pub assets: BTreeMap<String, Vec<u8>>,
 }
 
-/// Contains paths to resource files.
+impl ThemeBundle {
+    /// Loads a theme bundle from a directory or zip archive.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bundle can't be read, or doesn't contain a valid manifest.
+    pub fn load(path: &Path) -> Result<Self, ResourceError> {
+        let display = path.display();
+        let files = if path.is_dir() {
+            theme_bundle::read_dir(path)
+                .map_err(|e| ResourceError::Theme(format!("{display} ({e})")))?
+        } else {
+            let zip =
+                fs::read(path).map_err(|e| ResourceError::Theme(format!("{display} ({e})")))?;
+            theme_bundle::read_zip(&zip)
+                .map_err(|e| ResourceError::Theme(format!("{display} ({e})")))?
+        };
+        Self::from_files(files, &display.to_string())
+    }
+
+    /// Downloads a theme bundle as a zip archive, such as one provided by a Git host.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - URL of the zip archive.
+    /// * `cache` - How cached downloads are used. Downloads are cached for one hour.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the download fails, the bundle isn't cached while
+    /// [offline][CacheMode::Offline], or the archive doesn't contain a valid manifest.
+    pub async fn fetch(url: &Url, cache: CacheMode) -> Result<Self, ResourceError> {
+        let client = reqwest::Client::new();
+        let zip = download_bytes(&client, url, cache).await?;
+        let files = theme_bundle::read_zip(&zip)
+            .map_err(|e| ResourceError::Theme(format!("{url} ({e})")))?;
+        Self::from_files(files, url.as_str())
+    }
+
+    /// Creates a theme bundle from its files.
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - The files of the bundle, keyed by their path within it.
+    /// * `source` - Where the bundle was loaded from, for error messages.
+    fn from_files(files: BTreeMap<String, Vec<u8>>, source: &str) -> Result<Self, ResourceError> {
+        let mut files = theme_bundle::strip_root(files, THEME_MANIFEST);
+        let text = |files: &mut BTreeMap<String, Vec<u8>>, name: &str| {
+            files
+                .remove(name)
+                .map(|src| utf8(src, format!("{source}/{name}")))
+                .transpose()
+        };
+        let manifest = text(&mut files, THEME_MANIFEST)?
+            .ok_or_else(|| ResourceError::Theme(format!("{source} (no {THEME_MANIFEST} found)")))?;
+        let manifest = serde_any::from_str::<ThemeManifest>(&manifest, serde_any::Format::Toml)
+            .map_err(|e| ResourceError::Theme(format!("{source}/{THEME_MANIFEST} ({e})")))?;
+        let html = text(&mut files, "index.html")?;
+        let scss = text(&mut files, "styles.scss")?;
+        let assets = files
+            .into_iter()
+            .filter_map(|(name, data)| {
+                let name = name.strip_prefix(THEME_ASSETS_DIR)?.to_owned();
+                Some((name, data))
+            })
+            .collect();
+        debug!(name = manifest.name, source, "loaded theme");
+        Ok(Self {
+            manifest,
+            html,
+            scss,
+            assets,
+        })
+    }
+}
+
+/// Loads resources from files, falling back to the [built-in resources][EmbeddedResources] for
+/// any that aren't provided.
 #[derive(Default)]
 pub struct Resources {
-    /// Configuration file path.
+    /// Configuration file path. The config is read from stdin if this is one of [`STDIN_PATHS`].
     pub config: Option<PathBuf>,
 
+    /// Format of the config. If not provided, it's guessed from the file extension, or each
+    /// supported format is tried in turn if that fails.
+    pub config_format: Option<ConfigFormat>,
+
+    /// Name of the [built-in example][EXAMPLES] to use if no configuration file is provided.
+    /// Defaults to the first example.
+    pub example: Option<String>,
+
+    /// Name of the [built-in][TEMPLATE_PACKS] or [installed][template_store] template pack to use
+    /// for the HTML template and SCSS styles that aren't provided as files. Defaults to the first
+    /// built-in template pack.
+    pub template: Option<String>,
+
     /// SCSS template path.
     pub scss: Option<PathBuf>,
 
     /// HTML template path.
     pub html: Option<PathBuf>,
+
+    /// Theme providing the HTML template, SCSS template and theme assets. Templates provided as
+    /// files take precedence over the theme's.
+    pub theme: Option<ThemeBundle>,
 }
 
 impl Resources {
-    /// Loads the configuration file.
+    /// Returns the built-in resources used for anything that isn't provided as a file or by the
+    /// theme.
+    fn embedded(&self) -> EmbeddedResources {
+        let theme_template = self
+            .theme
+            .as_ref()
+            .and_then(|t| t.manifest.template.clone());
+        EmbeddedResources {
+            example: self.example.clone(),
+            template: self.template.clone().or(theme_template),
+        }
+    }
+}
+
+impl ResourceSet for Resources {
+    fn config(&self) -> Result<Config, ResourceError> {
+        match &self.config {
+            // Page files can't be resolved relative to stdin
+            Some(file) if STDIN_PATHS.iter().any(|p| file.as_os_str() == *p) => load_config(
+                &read_stdin_config()?,
+                self.config_format.map(ConfigFormat::as_serde_any),
+                None,
+            ),
+            Some(file) => load_config(
+                &load_override("config".into(), file)?,
+                self.config_format
+                    .map(ConfigFormat::as_serde_any)
+                    .or_else(|| serde_any::guess_format(file)),
+                file.parent(),
+            ),
+            None => self.embedded().config(),
+        }
+    }
+
+    fn html(&self) -> Result<String, ResourceError> {
+        let theme_html = self.theme.as_ref().and_then(|t| t.html.as_ref());
+        match (&self.html, theme_html) {
+            (Some(file), _) => load_override("html".into(), file),
+            (None, Some(html)) => Ok(html.clone()),
+            (None, None) => self.embedded().html(),
+        }
+    }
+
+    fn scss(&self) -> Result<String, ResourceError> {
+        let theme_scss = self.theme.as_ref().and_then(|t| t.scss.as_ref());
+        match (&self.scss, theme_scss) {
+            (Some(file), _) => load_override("css".into(), file),
+            (None, Some(scss)) => Ok(scss.clone()),
+            (None, None) => self.embedded().scss(),
+        }
+    }
+
+    fn theme_assets(&self) -> Result<BTreeMap<String, Vec<u8>>, ResourceError> {
+        Ok(self
+            .theme
+            .as_ref()
+            .map(|t| t.assets.clone())
+            .unwrap_or_default())
+    }
+}
+
+/// Loads a [built-in example][EXAMPLES] config and [template pack][TEMPLATE_PACKS], which are
+/// embedded in the library.
+#[derive(Debug, Clone, Default)]
+pub struct EmbeddedResources {
+    /// Name of the built-in example config. Defaults to the first example.
+    pub example: Option<String>,
+
+    /// Name of the built-in or [installed][template_store] template pack. Defaults to the first
+    /// built-in template pack.
+    pub template: Option<String>,
+}
+
+impl EmbeddedResources {
+    /// Loads the HTML template and SCSS styles of the requested template pack, which may be
+    /// [installed][template_store] rather than built in.
+    fn template_pack(&self) -> Result<(String, String), ResourceError> {
+        let name = self.template.as_deref().unwrap_or(TEMPLATE_PACKS[0].name);
+        match template_pack_src(name) {
+            Some(src) => Ok(src),
+            None => {
+                template_store::load(name)?.ok_or_else(|| ResourceError::TemplatePack(name.into()))
+            }
+        }
+    }
+}
+
+impl ResourceSet for EmbeddedResources {
+    fn config(&self) -> Result<Config, ResourceError> {
+        let name = self.example.as_deref().unwrap_or(EXAMPLES[0].name);
+        let src = example_src(name).ok_or_else(|| ResourceError::Example(name.into()))?;
+        load_config(&src, Some(serde_any::Format::Json), None)
+    }
+
+    fn html(&self) -> Result<String, ResourceError> {
+        Ok(self.template_pack()?.0)
+    }
+
+    fn scss(&self) -> Result<String, ResourceError> {
+        Ok(self.template_pack()?.1)
+    }
+}
+
+/// Holds resources in memory, such as a config provided by a form or test. Templates that aren't
+/// provided fall back to the default [built-in template pack][TEMPLATE_PACKS].
+#[derive(Debug, Clone, Default)]
+pub struct MemoryResources {
+    /// Source of the config, in any supported format.
+    pub config: String,
+
+    /// Source of the HTML template.
+    pub html: Option<String>,
+
+    /// Source of the SCSS template.
+    pub scss: Option<String>,
+}
+
+impl ResourceSet for MemoryResources {
+    fn config(&self) -> Result<Config, ResourceError> {
+        load_config(&self.config, None, None)
+    }
+
+    fn html(&self) -> Result<String, ResourceError> {
+        match &self.html {
+            Some(src) => Ok(src.clone()),
+            None => EmbeddedResources::default().html(),
+        }
+    }
+
+    fn scss(&self) -> Result<String, ResourceError> {
+        match &self.scss {
+            Some(src) => Ok(src.clone()),
+            None => EmbeddedResources::default().scss(),
+        }
+    }
+}
+
+/// Resources downloaded over HTTP. Everything is downloaded up front by
+/// [`HttpResources::fetch`], so building never waits on the network for resources.
+#[derive(Debug, Clone)]
+pub struct HttpResources {
+    /// The downloaded resources.
+    resources: MemoryResources,
+
+    /// Format of the config, guessed from the URL's file extension.
+    config_format: Option<serde_any::Format>,
+}
+
+impl HttpResources {
+    /// Downloads the config, and templates if provided. Templates that aren't provided fall back to
+    /// the default [built-in template pack][TEMPLATE_PACKS].
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - URL of the config. Its format is guessed from the file extension, if any.
+    /// * `html` - URL of the HTML template.
+    /// * `scss` - URL of the SCSS template.
+    /// * `cache` - How cached downloads are used. Downloads are cached for one hour.
     ///
     /// # Errors
     ///
-    /// Returns an error if loading or parsing the [`Config`] fails.
-    pub fn config(&self) -> Result<Config, ResourceError> {
-        let src = match &self.config {
-            Some(file) => load_override("config".into(), file),
-            None => Ok(resource_str!("example/example.json").to_string()),
-        }?;
-        let config = serde_any::from_str_any::<Config>(src.as_str())
-            .map_err(|_| ResourceError::Parse("config".into()))?;
-        event!(Level::DEBUG, "parsed config");
+    /// Returns an error if a download fails, the server responds with an error status, or a
+    /// resource isn't cached while [offline][CacheMode::Offline].
+    pub async fn fetch(
+        config: &Url,
+        html: Option<&Url>,
+        scss: Option<&Url>,
+        cache: CacheMode,
+    ) -> Result<Self, ResourceError> {
+        let client = reqwest::Client::new();
+        let (config_src, html, scss) = tokio::try_join!(
+            download(&client, config, cache),
+            async {
+                match html {
+                    Some(url) => download(&client, url, cache).await.map(Some),
+                    None => Ok(None),
+                }
+            },
+            async {
+                match scss {
+                    Some(url) => download(&client, url, cache).await.map(Some),
+                    None => Ok(None),
+                }
+            },
+        )?;
+        Ok(Self {
+            resources: MemoryResources {
+                config: config_src,
+                html,
+                scss,
+            },
+            config_format: serde_any::guess_format(Path::new(config.path())),
+        })
+    }
+}
+
+impl ResourceSet for HttpResources {
+    fn config(&self) -> Result<Config, ResourceError> {
+        load_config(&self.resources.config, self.config_format, None)
+    }
+
+    fn html(&self) -> Result<String, ResourceError> {
+        self.resources.html()
+    }
+
+    fn scss(&self) -> Result<String, ResourceError> {
+        self.resources.scss()
+    }
+}
+
+/// Wraps another [`ResourceSet`], replacing its config with the config of one of its profiles
+/// (see [`Config::profile`]).
+#[derive(Debug, Clone)]
+pub struct ProfileResources<R> {
+    /// The wrapped resources.
+    pub resources: R,
+
+    /// Name of the profile.
+    pub profile: String,
+}
+
+impl<R: ResourceSet> ResourceSet for ProfileResources<R> {
+    fn config(&self) -> Result<Config, ResourceError> {
+        self.resources
+            .config()?
+            .profile(&self.profile)
+            .ok_or_else(|| ResourceError::Profile(self.profile.clone()))
+    }
+
+    fn html(&self) -> Result<String, ResourceError> {
+        self.resources.html()
+    }
+
+    fn scss(&self) -> Result<String, ResourceError> {
+        self.resources.scss()
+    }
+    fn theme_assets(&self) -> Result<BTreeMap<String, Vec<u8>>, ResourceError> {
+        self.resources.theme_assets()
+    }
+}
+
+/// Wraps another [`ResourceSet`], replacing the layout its config is built for (see
+/// `Build.layout`).
+#[derive(Debug, Clone)]
+pub struct LayoutResources<R> {
+    /// The wrapped resources.
+    pub resources: R,
+
+    /// Layout to build for. If not provided, the config's layout is kept.
+    pub layout: Option<BuildLayout>,
+}
+
+impl<R: ResourceSet> ResourceSet for LayoutResources<R> {
+    fn config(&self) -> Result<Config, ResourceError> {
+        let mut config = self.resources.config()?;
+        if let Some(layout) = self.layout {
+            config.build.layout = layout;
+        }
         Ok(config)
     }
 
-    /// Loads the SCSS template.
+    fn html(&self) -> Result<String, ResourceError> {
+        self.resources.html()
+    }
+
+    fn scss(&self) -> Result<String, ResourceError> {
+        self.resources.scss()
+    }
+    fn theme_assets(&self) -> Result<BTreeMap<String, Vec<u8>>, ResourceError> {
+        self.resources.theme_assets()
+    }
+}
+
+/// Wraps another [`ResourceSet`], replacing its templates with ones downloaded over HTTP, so
+/// templates shared online can be used without downloading them by hand. Everything is downloaded
+/// up front by [`RemoteTemplates::fetch`], and cached like [`HttpResources`].
+#[derive(Debug, Clone)]
+pub struct RemoteTemplates<R> {
+    /// The wrapped resources.
+    pub resources: R,
+
+    /// The downloaded HTML template, if any.
+    html: Option<String>,
+
+    /// The downloaded SCSS template, if any.
+    scss: Option<String>,
+}
+
+impl<R> RemoteTemplates<R> {
+    /// Downloads the provided templates. Templates that aren't provided are taken from the wrapped
+    /// resources.
+    ///
+    /// # Arguments
+    ///
+    /// * `resources` - The wrapped resources.
+    /// * `html` - URL of the HTML template.
+    /// * `scss` - URL of the SCSS template.
+    /// * `cache` - How cached downloads are used. Downloads are cached for one hour.
     ///
     /// # Errors
     ///
-    /// Returns an error if loading the file fails.
-    pub fn scss(&self) -> Result<String, ResourceError> {
+    /// Returns an error if a download fails, the server responds with an error status, or a
+    /// template isn't cached while [offline][CacheMode::Offline].
+    pub async fn fetch(
+        resources: R,
+        html: Option<&Url>,
+        scss: Option<&Url>,
+        cache: CacheMode,
+    ) -> Result<Self, ResourceError> {
+        let client = reqwest::Client::new();
+        let (html, scss) = tokio::try_join!(
+            async {
+                match html {
+                    Some(url) => download(&client, url, cache).await.map(Some),
+                    None => Ok(None),
+                }
+            },
+            async {
+                match scss {
+                    Some(url) => download(&client, url, cache).await.map(Some),
+                    None => Ok(None),
+                }
+            },
+        )?;
+        Ok(Self {
+            resources,
+            html,
+            scss,
+        })
+    }
+}
+
+impl<R: ResourceSet> ResourceSet for RemoteTemplates<R> {
+    fn config(&self) -> Result<Config, ResourceError> {
+        self.resources.config()
+    }
+
+    fn html(&self) -> Result<String, ResourceError> {
+        match &self.html {
+            Some(html) => Ok(html.clone()),
+            None => self.resources.html(),
+        }
+    }
+
+    fn scss(&self) -> Result<String, ResourceError> {
         match &self.scss {
-            Some(file) => load_override("css".into(), file),
-            None => resource!("res/styles.scss", |src: &[u8]| utf8(
-                src.to_vec(),
-                "html".into()
-            )),
+            Some(scss) => Ok(scss.clone()),
+            None => self.resources.scss(),
         }
     }
+    fn theme_assets(&self) -> Result<BTreeMap<String, Vec<u8>>, ResourceError> {
+        self.resources.theme_assets()
+    }
+}
 
-    /// Loads the HTML template.
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if loading the file fails.
-    pub fn html(&self) -> Result<String, ResourceError> {
-        match &self.html {
-            Some(file) => load_override("html".into(), file),
-            None => resource!("res/index.html", |src: &[u8]| utf8(
-                src.to_vec(),
-                "html".into()
-            )),
+/// Parses a path given for a resource as an HTTP or HTTPS URL, for resources that may be given as
+/// either.
+///
+/// # Returns
+///
+/// The URL, or `None` if the path isn't an HTTP or HTTPS URL.
+pub fn remote_url(path: &Path) -> Option<Url> {
+    let url = Url::parse(path.to_str()?).ok()?;
+    matches!(url.scheme(), "http" | "https").then_some(url)
+}
+
+impl<R: ResourceSet> ResourceSet for &R {
+    fn config(&self) -> Result<Config, ResourceError> {
+        (**self).config()
+    }
+
+    fn html(&self) -> Result<String, ResourceError> {
+        (**self).html()
+    }
+
+    fn scss(&self) -> Result<String, ResourceError> {
+        (**self).scss()
+    }
+    fn theme_assets(&self) -> Result<BTreeMap<String, Vec<u8>>, ResourceError> {
+        (**self).theme_assets()
+    }
+}
+
+/// Downloads a resource as text, see [`download_bytes`].
+async fn download(
+    client: &reqwest::Client,
+    url: &Url,
+    cache: CacheMode,
+) -> Result<String, ResourceError> {
+    utf8(download_bytes(client, url, cache).await?, url.to_string())
+}
+
+/// Checks if [`download_bytes`] would download a resource instead of reading it from the cache,
+/// without changing the cache.
+pub(crate) fn needs_download(url: &Url, cache: CacheMode) -> bool {
+    match cache {
+        CacheMode::Offline => false,
+        CacheMode::Refresh => true,
+        CacheMode::PreferCache => util::cache_subdir("resources")
+            .map(|dir| dir.join(util::sha1_base32(url.as_str().as_bytes())))
+            .map_or(true, |path| {
+                !path.exists() || util::cache_expired(&path, 3600)
+            }),
+    }
+}
+
+/// Downloads a resource, reading it from the cache if possible. Cached resources expire after one
+/// hour, unless `cache` is [`CacheMode::Offline`]. [`CacheMode::Refresh`] ignores cached
+/// resources.
+pub(crate) async fn download_bytes(
+    client: &reqwest::Client,
+    url: &Url,
+    cache: CacheMode,
+) -> Result<Vec<u8>, ResourceError> {
+    let path = util::cache_subdir("resources")
+        .map_err(|e| ResourceError::Http(format!("{url} ({e})")))?
+        .join(util::sha1_base32(url.as_str().as_bytes()));
+    let fresh = match cache {
+        CacheMode::Offline => true,
+        CacheMode::PreferCache => !util::cache_expired(&path, 3600),
+        CacheMode::Refresh => false,
+    };
+    if fresh {
+        if let Ok(bytes) = tokio::fs::read(&path).await {
+            debug!(path = path.to_str(), "reading cached resource");
+            return Ok(bytes);
         }
     }
+    if cache == CacheMode::Offline {
+        return Err(ResourceError::Http(format!(
+            "{url} (not cached, and downloading is disabled while offline)"
+        )));
+    }
+
+    debug!(url = url.as_str(), "downloading resource");
+    let res = client
+        .get(url.clone())
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .map_err(|e| ResourceError::Http(format!("{url} ({e})")))?;
+    let bytes = res
+        .bytes()
+        .await
+        .map_err(|e| ResourceError::Http(format!("{url} ({e})")))?
+        .to_vec();
+    // Replace rather than overwrite, so the creation time used for expiry is reset
+    let _ = tokio::fs::remove_file(&path).await;
+    if tokio::fs::write(&path, &bytes).await.is_err() {
+        warn!(path = path.to_str(), "failed to write resource to cache");
+    }
+    Ok(bytes)
+}
+
+/// Parses a config and resolves any page files and references within it.
+///
+/// If the format is unknown, each supported format is tried in turn. Page files are resolved
+/// relative to `base_dir`, and aren't supported if it's not provided.
+fn load_config(
+    src: &str,
+    format: Option<serde_any::Format>,
+    base_dir: Option<&Path>,
+) -> Result<Config, ResourceError> {
+    let mut value = parse_config(src, format)?;
+    page_files::resolve(&mut value, base_dir)
+        .map_err(|e| ResourceError::PageFile(e.to_string()))?;
+    refs::resolve(&mut value).map_err(|e| ResourceError::Reference(format!("config ({e})")))?;
+    let config = serde_json::from_value::<Config>(value)
+        .map_err(|_| ResourceError::Parse("config".into()))?;
+    event!(Level::DEBUG, "parsed config");
+    Ok(config)
+}
+
+/// Parses a config into an untyped value, so references can be resolved before deserializing.
+///
+/// If the format is unknown, each supported format is tried in turn. Only documents that parse into
+/// a mapping are accepted, since some formats will happily parse other formats as a plain string.
+pub(crate) fn parse_config(
+    src: &str,
+    format: Option<serde_any::Format>,
+) -> Result<Value, ResourceError> {
+    let formats = match format {
+        Some(format) => vec![format],
+        None => serde_any::supported_formats(),
+    };
+    formats
+        .into_iter()
+        .filter_map(|format| serde_any::from_str::<Value>(src, format).ok())
+        .find(Value::is_object)
+        .ok_or_else(|| ResourceError::Parse("config".into()))
 }
 
 /// Attempts to encode the provided bytes to UTF-8.
@@ -93,6 +884,21 @@ fn utf8(v: Vec<u8>, resource_name: String) -> Result<String, ResourceError> {
         .to_string())
 }
 
+/// Reads the config from stdin the first time it's needed, returning the same config afterwards.
+fn read_stdin_config() -> Result<String, ResourceError> {
+    STDIN_CONFIG
+        .get_or_init(|| {
+            debug!("reading config from stdin");
+            let mut src = String::new();
+            io::stdin()
+                .read_to_string(&mut src)
+                .map(|_| src)
+                .map_err(|e| e.to_string())
+        })
+        .clone()
+        .map_err(ResourceError::Stdin)
+}
+
 /// Attempts to load an override file.
 fn load_override(resource_name: String, file: &PathBuf) -> Result<String, ResourceError> {
     let src = fs::read(file).map_err(|_| ResourceError::Override(resource_name.clone()))?;
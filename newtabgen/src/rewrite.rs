@@ -0,0 +1,104 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Rewrites link URLs according to `Config.rewrite`.
+
+use thiserror::Error;
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::config::{Config, Rewrite, Section};
+
+/// Errors that may occur when rewriting link URLs.
+#[derive(Error, Debug)]
+pub enum RewriteError {
+    /// Occurs when a domain mapping replaces a host with one that isn't valid.
+    #[error("invalid replacement domain: '{0}'")]
+    Domain(String),
+}
+
+/// Rewrites the URL of each link, if any rewrite rules are configured. Rewriting happens before
+/// site icons are looked up, so icons are fetched for the rewritten URLs.
+///
+/// URLs that can't be parsed are left untouched.
+///
+/// # Errors
+///
+/// Returns an error if a domain mapping produces an invalid host.
+pub(crate) fn rewrite_config(config: &mut Config) -> Result<(), RewriteError> {
+    let rules = &config.rewrite;
+    if !rules.force_https && rules.strip_params.is_empty() && rules.domains.is_empty() {
+        return Ok(());
+    }
+
+    for page in &mut config.pages {
+        for section in &mut page.sections {
+            rewrite_section(section, rules)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Rewrites the URL of each link in a section and its nested sections.
+fn rewrite_section(section: &mut Section, rules: &Rewrite) -> Result<(), RewriteError> {
+    for link in &mut section.links {
+        let mut url = match Url::parse(&link.url) {
+            Ok(url) => url,
+            Err(_) => {
+                warn!(url = link.url, "failed to parse link url, not rewriting it");
+                continue;
+            }
+        };
+        rewrite_url(&mut url, rules)?;
+        if url.as_str() != link.url {
+            debug!(from = link.url, to = url.as_str(), "rewrote link url");
+            link.url = url.into();
+        }
+    }
+    for section in &mut section.sections {
+        rewrite_section(section, rules)?;
+    }
+    Ok(())
+}
+
+/// Applies the rewrite rules to a single URL. Domains are mapped first, so the other rules also
+/// apply to the replacement domain.
+fn rewrite_url(url: &mut Url, rules: &Rewrite) -> Result<(), RewriteError> {
+    if let Some(host) = url.host_str() {
+        let bare = host.strip_prefix("www.").unwrap_or(host);
+        let replacement = rules.domains.get(host).or_else(|| rules.domains.get(bare));
+        if let Some(replacement) = replacement {
+            url.set_host(Some(replacement))
+                .map_err(|_| RewriteError::Domain(replacement.clone()))?;
+        }
+    }
+
+    if rules.force_https && url.scheme() == "http" {
+        // Only fails when switching between special and non-special schemes
+        let _ = url.set_scheme("https");
+    }
+
+    if !rules.strip_params.is_empty() && url.query().is_some() {
+        let kept = url
+            .query_pairs()
+            .filter(|(key, _)| !rules.strip_params.iter().any(|p| param_matches(p, key)))
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect::<Vec<_>>();
+        if kept.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(kept);
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks if a query parameter name matches a pattern. A pattern ending with `*` matches every
+/// name starting with the rest of the pattern, e.g. `utm_*` matches `utm_source`.
+fn param_matches(pattern: &str, key: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => key.starts_with(prefix),
+        None => key == pattern,
+    }
+}
@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Sorts config content according to `Config.sort` and `Page.weight`.
+
+use icu_collator::{Collator, CollatorError, CollatorOptions, Numeric};
+use icu_locid::Locale;
+use thiserror::Error;
+use tracing::debug;
+
+use crate::config::{Config, Section, Sort};
+
+/// Errors that may occur when sorting config content.
+#[derive(Error, Debug)]
+pub enum SortError {
+    /// Occurs when `Config.locale` isn't a valid language tag.
+    #[error("invalid locale: '{0}'")]
+    Locale(String),
+
+    /// Occurs when no collation rules are available for the locale.
+    #[error("failed to load collation rules ({0})")]
+    Collator(CollatorError),
+}
+
+/// Sorts the pages by weight, then the sections of each page and the links of each section,
+/// including nested sections, if requested by the config.
+///
+/// # Errors
+///
+/// Returns an error if the configured locale is invalid or unsupported.
+pub(crate) fn sort_config(config: &mut Config) -> Result<(), SortError> {
+    config.pages.sort_by_key(|p| p.weight);
+
+    if config.sort == Sort::Manual {
+        return Ok(());
+    }
+
+    let collator = collator(config.locale.as_deref(), config.sort == Sort::Natural)?;
+    debug!(sort = ?config.sort, locale = config.locale, "sorting config");
+    for page in &mut config.pages {
+        sort_sections(&mut page.sections, &collator);
+    }
+
+    Ok(())
+}
+
+/// Sorts sections by name, then the links and nested sections of each section.
+fn sort_sections(sections: &mut [Section], collator: &Collator) {
+    sections.sort_by(|a, b| collator.compare(&a.name, &b.name));
+    for section in sections {
+        section
+            .links
+            .sort_by(|a, b| collator.compare(&a.name, &b.name));
+        sort_sections(&mut section.sections, collator);
+    }
+}
+
+/// Creates a collator for the provided BCP 47 language tag, or a locale-neutral collator if no tag
+/// is provided. If `numeric` is true, sequences of digits are compared by their numeric value.
+fn collator(locale: Option<&str>, numeric: bool) -> Result<Collator, SortError> {
+    let locale = match locale {
+        Some(tag) => tag
+            .parse::<Locale>()
+            .map_err(|_| SortError::Locale(tag.into()))?,
+        None => Locale::UND,
+    };
+    let mut options = CollatorOptions::new();
+    if numeric {
+        options.numeric = Some(Numeric::On);
+    }
+    Collator::try_new(&(&locale).into(), options).map_err(SortError::Collator)
+}
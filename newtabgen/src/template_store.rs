@@ -0,0 +1,406 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Installs template packs shared as archives, so they can be selected by name like the
+//! [built-in template packs][crate::resources::TEMPLATE_PACKS].
+//!
+//! A template pack is a zip archive or directory containing:
+//!
+//! * `pack.toml` - The [manifest][PackManifest], optional.
+//! * `index.html` - The HTML template, optional.
+//! * `styles.scss` - The SCSS template, optional.
+//!
+//! Templates the pack doesn't provide are taken from the first built-in template pack. Installed
+//! packs are kept in the `templates` directory of the user's data directory, one directory each.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tracing::{debug, info};
+use url::Url;
+
+use crate::{
+    builder::CacheMode,
+    resources::{self, ResourceError, TEMPLATE_PACKS},
+    theme_bundle, util,
+};
+
+/// Name of the file describing a template pack, which must be at its root.
+const PACK_MANIFEST: &str = "pack.toml";
+
+/// Files of a template pack that are installed. Anything else in the archive is ignored.
+const PACK_FILES: &[&str] = &[PACK_MANIFEST, "index.html", "styles.scss"];
+
+/// Errors that may occur when managing installed template packs.
+#[derive(Error, Debug)]
+pub enum TemplateStoreError {
+    /// Occurs when the directory holding installed template packs can't be used.
+    #[error("failed to access template packs @ {1} ({0})")]
+    Io(#[source] io::Error, PathBuf),
+
+    /// Occurs when downloading a template pack fails.
+    #[error(transparent)]
+    Download(#[from] ResourceError),
+
+    /// Occurs when a template pack archive is malformed or has none of the expected files.
+    #[error("invalid template pack: {0}")]
+    Invalid(String),
+
+    /// Occurs when the checksum of a template pack archive doesn't match the expected one.
+    #[error("checksum mismatch for {archive} (expected sha256 {expected}, got {actual})")]
+    Checksum {
+        /// Where the archive was loaded from.
+        archive: String,
+
+        /// The expected checksum.
+        expected: String,
+
+        /// The checksum of the archive.
+        actual: String,
+    },
+
+    /// Occurs when a downloaded template pack has no checksum to verify it against.
+    #[error("no checksum for {0} (provide one, or publish it as {0}.sha256)")]
+    NoChecksum(Url),
+
+    /// Occurs when a template pack name can't be used.
+    #[error("invalid template pack name '{0}' (use lowercase letters, digits, '-' and '_')")]
+    Name(String),
+
+    /// Occurs when a template pack would replace a built-in one.
+    #[error("'{0}' is a built-in template pack")]
+    BuiltIn(String),
+
+    /// Occurs when installing a template pack that's already installed without replacing it.
+    #[error("template pack '{0}' is already installed")]
+    Exists(String),
+
+    /// Occurs when no installed template pack has the requested name.
+    #[error("template pack '{0}' isn't installed")]
+    NotInstalled(String),
+}
+
+/// Describes a template pack, read from its `pack.toml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PackManifest {
+    /// Name the template pack is installed as, unless another is requested. Defaults to the name
+    /// of the archive or directory.
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// What the template pack looks like.
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// A template pack installed with [`install`].
+#[derive(Debug, Clone)]
+pub struct InstalledPack {
+    /// Name used to select the template pack.
+    pub name: String,
+
+    /// Describes the template pack.
+    pub manifest: PackManifest,
+
+    /// Directory the template pack is installed in.
+    pub path: PathBuf,
+}
+
+/// Finds the directory holding installed template packs, creating it if needed.
+///
+/// # Errors
+///
+/// Returns an error if a suitable directory can't be found or created.
+pub fn dir() -> Result<PathBuf, TemplateStoreError> {
+    util::data_subdir("templates")
+        .map_err(|e| TemplateStoreError::Io(e, PathBuf::from("templates")))
+}
+
+/// Lists the installed template packs, sorted by name.
+///
+/// # Errors
+///
+/// Returns an error if the directory holding installed template packs can't be read.
+pub fn list() -> Result<Vec<InstalledPack>, TemplateStoreError> {
+    let dir = dir()?;
+    let entries = fs::read_dir(&dir).map_err(|e| TemplateStoreError::Io(e, dir.clone()))?;
+    let mut packs = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        // Skips partial installs, which start with a dot
+        if entry.path().is_dir() && valid_name(&name) {
+            packs.push(InstalledPack {
+                manifest: read_manifest(&entry.path()),
+                path: entry.path(),
+                name,
+            });
+        }
+    }
+    packs.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(packs)
+}
+
+/// Installs a template pack from a zip archive, a directory, or the URL of a zip archive.
+///
+/// Archives are verified against the expected SHA-256 checksum, if provided. Downloaded archives
+/// must be verified: if no checksum is provided, it's downloaded from the URL of the archive with
+/// `.sha256` appended, as commonly published alongside releases.
+///
+/// # Arguments
+///
+/// * `source` - Path or URL of the template pack.
+/// * `sha256` - Expected SHA-256 checksum of the archive, as hexadecimal.
+/// * `name` - Name to install the template pack as, instead of the name in its manifest.
+/// * `replace` - Indicates if an installed template pack with the same name should be replaced.
+///
+/// # Errors
+///
+/// Returns an error if the template pack can't be loaded, fails verification, has an unusable
+/// name, or can't be written to the directory holding installed template packs.
+pub async fn install(
+    source: &Path,
+    sha256: Option<&str>,
+    name: Option<&str>,
+    replace: bool,
+) -> Result<InstalledPack, TemplateStoreError> {
+    let display = source.display().to_string();
+    let (files, stem) = if let Some(url) = resources::remote_url(source) {
+        let client = reqwest::Client::new();
+        let zip = resources::download_bytes(&client, &url, CacheMode::Refresh).await?;
+        let expected = match sha256 {
+            Some(sha256) => sha256.to_owned(),
+            None => published_checksum(&client, &url).await?,
+        };
+        verify(&zip, &expected, &display)?;
+        let stem = url
+            .path_segments()
+            .and_then(|mut s| s.next_back())
+            .map(file_stem);
+        (read_zip(&zip, &display)?, stem)
+    } else if source.is_dir() {
+        let files = theme_bundle::read_dir(source)
+            .map_err(|e| TemplateStoreError::Io(e, source.to_path_buf()))?;
+        (
+            files,
+            source.file_name().map(|n| n.to_string_lossy().into()),
+        )
+    } else {
+        let zip = fs::read(source).map_err(|e| TemplateStoreError::Io(e, source.to_path_buf()))?;
+        match sha256 {
+            Some(expected) => verify(&zip, expected, &display)?,
+            None => info!(sha256 = hex_sha256(&zip), "installing unverified archive"),
+        }
+        let stem = source.file_name().map(|n| file_stem(&n.to_string_lossy()));
+        (read_zip(&zip, &display)?, stem)
+    };
+
+    let files = PACK_FILES.iter().fold(files, |files, marker| {
+        theme_bundle::strip_root(files, marker)
+    });
+    let files = files
+        .into_iter()
+        .filter(|(path, _)| PACK_FILES.contains(&path.as_str()))
+        .collect::<theme_bundle::Files>();
+    if !files.contains_key("index.html") && !files.contains_key("styles.scss") {
+        return Err(TemplateStoreError::Invalid(format!(
+            "{display} (no index.html or styles.scss found)"
+        )));
+    }
+    let manifest = match files.get(PACK_MANIFEST) {
+        Some(src) => parse_manifest(src)
+            .map_err(|e| TemplateStoreError::Invalid(format!("{display}/{PACK_MANIFEST} ({e})")))?,
+        None => PackManifest::default(),
+    };
+
+    let name = name
+        .map(str::to_owned)
+        .or_else(|| manifest.name.clone())
+        .or(stem)
+        .unwrap_or_default();
+    if !valid_name(&name) {
+        return Err(TemplateStoreError::Name(name));
+    }
+    if resources::template_pack_src(&name).is_some() {
+        return Err(TemplateStoreError::BuiltIn(name));
+    }
+
+    let dir = dir()?;
+    let path = dir.join(&name);
+    if path.exists() && !replace {
+        return Err(TemplateStoreError::Exists(name));
+    }
+    // Written next to the destination first, so a failed install leaves nothing behind
+    let partial = dir.join(format!(".{name}.partial"));
+    let write = || -> io::Result<()> {
+        let _ = fs::remove_dir_all(&partial);
+        fs::create_dir_all(&partial)?;
+        for (file, data) in &files {
+            fs::write(partial.join(file), data)?;
+        }
+        if path.exists() {
+            fs::remove_dir_all(&path)?;
+        }
+        fs::rename(&partial, &path)
+    };
+    write().map_err(|e| {
+        let _ = fs::remove_dir_all(&partial);
+        TemplateStoreError::Io(e, path.clone())
+    })?;
+    debug!(name, path = path.to_str(), "installed template pack");
+    Ok(InstalledPack {
+        name,
+        manifest,
+        path,
+    })
+}
+
+/// Removes an installed template pack.
+///
+/// # Errors
+///
+/// Returns an error if the template pack isn't installed or can't be removed.
+pub fn remove(name: &str) -> Result<(), TemplateStoreError> {
+    if !valid_name(name) {
+        return Err(TemplateStoreError::NotInstalled(name.into()));
+    }
+    let path = dir()?.join(name);
+    if !path.is_dir() {
+        return Err(TemplateStoreError::NotInstalled(name.into()));
+    }
+    fs::remove_dir_all(&path).map_err(|e| TemplateStoreError::Io(e, path))
+}
+
+/// Loads the HTML template and SCSS styles of an installed template pack. Templates the pack
+/// doesn't provide are taken from the first built-in template pack.
+///
+/// # Errors
+///
+/// Returns an error if the template pack is installed but can't be read.
+///
+/// # Returns
+///
+/// The templates, or `None` if no template pack with the provided name is installed.
+pub(crate) fn load(name: &str) -> Result<Option<(String, String)>, ResourceError> {
+    if !valid_name(name) {
+        return Ok(None);
+    }
+    let Ok(dir) = dir() else {
+        return Ok(None);
+    };
+    let path = dir.join(name);
+    if !path.is_dir() {
+        return Ok(None);
+    }
+    let (default_html, default_scss) = resources::template_pack_src(TEMPLATE_PACKS[0].name)
+        .expect("first template pack is built in");
+    let read = |file: &str, default: String| match fs::read_to_string(path.join(file)) {
+        Ok(src) => Ok(src),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(default),
+        Err(e) => Err(ResourceError::TemplatePack(format!(
+            "{name} ({}: {e})",
+            path.join(file).display()
+        ))),
+    };
+    debug!(
+        name,
+        path = path.to_str(),
+        "loading installed template pack"
+    );
+    Ok(Some((
+        read("index.html", default_html)?,
+        read("styles.scss", default_scss)?,
+    )))
+}
+
+/// Downloads the checksum published alongside an archive, as `<url>.sha256`. The file may hold
+/// just the checksum, or the output of `sha256sum`.
+///
+/// # Errors
+///
+/// Returns an error if no checksum is published.
+async fn published_checksum(
+    client: &reqwest::Client,
+    url: &Url,
+) -> Result<String, TemplateStoreError> {
+    let no_checksum = || TemplateStoreError::NoChecksum(url.clone());
+    let checksum_url = Url::parse(&format!("{url}.sha256")).map_err(|_| no_checksum())?;
+    let bytes = resources::download_bytes(client, &checksum_url, CacheMode::Refresh)
+        .await
+        .map_err(|_| no_checksum())?;
+    String::from_utf8_lossy(&bytes)
+        .split_whitespace()
+        .next()
+        .map(str::to_owned)
+        .ok_or_else(no_checksum)
+}
+
+/// Checks that the SHA-256 checksum of an archive matches the expected one.
+///
+/// # Errors
+///
+/// Returns an error if the checksums don't match.
+fn verify(archive: &[u8], expected: &str, source: &str) -> Result<(), TemplateStoreError> {
+    let actual = hex_sha256(archive);
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        return Err(TemplateStoreError::Checksum {
+            archive: source.into(),
+            expected: expected.trim().to_ascii_lowercase(),
+            actual,
+        });
+    }
+    debug!(source, sha256 = actual, "verified template pack");
+    Ok(())
+}
+
+/// Computes the SHA-256 checksum of some bytes, as lowercase hexadecimal.
+fn hex_sha256(bytes: &[u8]) -> String {
+    data_encoding::HEXLOWER.encode(&Sha256::digest(bytes))
+}
+
+/// Reads the files of a zip archive.
+///
+/// # Errors
+///
+/// Returns an error if the archive is malformed.
+fn read_zip(zip: &[u8], source: &str) -> Result<theme_bundle::Files, TemplateStoreError> {
+    theme_bundle::read_zip(zip).map_err(|e| TemplateStoreError::Invalid(format!("{source} ({e})")))
+}
+
+/// Reads the manifest of an installed template pack, or an empty one if it has none or it can't
+/// be read.
+fn read_manifest(dir: &Path) -> PackManifest {
+    fs::read(dir.join(PACK_MANIFEST))
+        .ok()
+        .and_then(|src| parse_manifest(&src).ok())
+        .unwrap_or_default()
+}
+
+/// Parses a template pack manifest.
+///
+/// # Errors
+///
+/// Returns an error describing the problem if the manifest isn't valid.
+fn parse_manifest(src: &[u8]) -> Result<PackManifest, String> {
+    let src = std::str::from_utf8(src).map_err(|e| e.to_string())?;
+    serde_any::from_str(src, serde_any::Format::Toml).map_err(|e| e.to_string())
+}
+
+/// Removes the extension of an archive name, e.g. `dark-pack.zip` becomes `dark-pack`.
+fn file_stem(file_name: &str) -> String {
+    file_name
+        .strip_suffix(".zip")
+        .unwrap_or(file_name)
+        .to_owned()
+}
+
+/// Checks if a template pack name is usable as a directory name everywhere.
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
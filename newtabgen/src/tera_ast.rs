@@ -0,0 +1,189 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Inspects the syntax tree of Tera templates.
+
+use std::collections::{BTreeSet, HashSet};
+
+use tera::{
+    ast::{Expr, ExprVal, FunctionCall, Node},
+    Template,
+};
+
+/// Everything a template uses, collected from its syntax tree.
+#[derive(Debug, Default)]
+pub(crate) struct Usage {
+    /// Each variable path looked up, e.g. `config.theme["accent"]`.
+    pub variables: BTreeSet<String>,
+
+    /// Variable paths that may be undefined without failing to render, since they have a
+    /// `default` filter or are checked with the `defined` or `undefined` tests.
+    pub optional_variables: HashSet<String>,
+
+    /// Each string literal, since they may be used as keys.
+    pub strings: BTreeSet<String>,
+
+    /// Names bound within the template by loops, `set` tags, and macro arguments.
+    pub locals: HashSet<String>,
+
+    /// Names of the functions called.
+    pub functions: BTreeSet<String>,
+
+    /// Names of the filters applied.
+    pub filters: BTreeSet<String>,
+
+    /// Names of the tests used with `is`.
+    pub tests: BTreeSet<String>,
+
+    /// Macros called, as `namespace::name`.
+    pub macro_calls: BTreeSet<String>,
+
+    /// Names of the macros defined.
+    pub macro_definitions: HashSet<String>,
+}
+
+/// Collects everything a template uses.
+///
+/// # Errors
+///
+/// Returns an error if the template can't be parsed.
+pub(crate) fn usage(src: &str) -> Result<Usage, tera::Error> {
+    let template = Template::new("usage", None, src)?;
+    let mut usage = Usage::default();
+    usage.visit_nodes(&template.ast);
+    Ok(usage)
+}
+
+/// Collects every name a template may use to look up a value. Variable paths are split into their
+/// segments, so `config.theme["accent"]` yields `config`, `theme`, and `accent`. String literals
+/// are included too, since they may be used as keys, along with the names of the functions called.
+///
+/// # Errors
+///
+/// Returns an error if the template can't be parsed.
+pub(crate) fn referenced_names(src: &str) -> Result<HashSet<String>, tera::Error> {
+    let usage = usage(src)?;
+    let mut names = usage.strings.into_iter().collect::<HashSet<String>>();
+    names.extend(usage.functions);
+    for variable in &usage.variables {
+        names.extend(path_segments(variable).map(str::to_owned));
+    }
+    Ok(names)
+}
+
+/// Splits a variable path into its segments, e.g. `a.b["c"]` into `a`, `b`, and `c`.
+fn path_segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split(['.', '[', ']', '"', '\''])
+        .filter(|s| !s.is_empty())
+}
+
+impl Usage {
+    /// Collects what each node uses.
+    fn visit_nodes(&mut self, nodes: &[Node]) {
+        for node in nodes {
+            match node {
+                Node::VariableBlock(_, expr) => self.visit_expr(expr),
+                Node::MacroDefinition(_, def, _) => {
+                    self.macro_definitions.insert(def.name.clone());
+                    self.locals.extend(def.args.keys().cloned());
+                    def.args.values().flatten().for_each(|e| self.visit_expr(e));
+                    self.visit_nodes(&def.body);
+                }
+                Node::Set(_, set) => {
+                    self.locals.insert(set.key.clone());
+                    self.visit_expr(&set.value);
+                }
+                Node::FilterSection(_, section, _) => {
+                    self.filters.insert(section.filter.name.clone());
+                    self.visit_call(&section.filter);
+                    self.visit_nodes(&section.body);
+                }
+                Node::Block(_, block, _) => self.visit_nodes(&block.body),
+                Node::Forloop(_, forloop, _) => {
+                    self.locals.insert("loop".into());
+                    self.locals.insert(forloop.value.clone());
+                    self.locals.extend(forloop.key.clone());
+                    self.visit_expr(&forloop.container);
+                    self.visit_nodes(&forloop.body);
+                    if let Some(body) = &forloop.empty_body {
+                        self.visit_nodes(body);
+                    }
+                }
+                Node::If(if_node, _) => {
+                    for (_, expr, body) in &if_node.conditions {
+                        self.visit_expr(expr);
+                        self.visit_nodes(body);
+                    }
+                    if let Some((_, body)) = &if_node.otherwise {
+                        self.visit_nodes(body);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Collects what an expression and its filters use.
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let ExprVal::Ident(ident) = &expr.val {
+            if expr.filters.iter().any(|f| f.name == "default") {
+                self.optional_variables.insert(ident.clone());
+            }
+        }
+        self.visit_val(&expr.val);
+        for filter in &expr.filters {
+            self.filters.insert(filter.name.clone());
+            self.visit_call(filter);
+        }
+    }
+
+    /// Collects what the arguments of a function or filter call use.
+    fn visit_call(&mut self, call: &FunctionCall) {
+        for arg in call.args.values() {
+            self.visit_expr(arg);
+        }
+    }
+
+    /// Collects what an expression value uses.
+    fn visit_val(&mut self, val: &ExprVal) {
+        match val {
+            ExprVal::String(s) => {
+                self.strings.insert(s.clone());
+            }
+            ExprVal::Ident(ident) => {
+                self.variables.insert(ident.clone());
+            }
+            ExprVal::Math(math) => {
+                self.visit_expr(&math.lhs);
+                self.visit_expr(&math.rhs);
+            }
+            ExprVal::Logic(logic) => {
+                self.visit_expr(&logic.lhs);
+                self.visit_expr(&logic.rhs);
+            }
+            ExprVal::Test(test) => {
+                self.tests.insert(test.name.clone());
+                self.variables.insert(test.ident.clone());
+                if matches!(test.name.as_str(), "defined" | "undefined") {
+                    self.optional_variables.insert(test.ident.clone());
+                }
+                test.args.iter().for_each(|e| self.visit_expr(e));
+            }
+            ExprVal::MacroCall(call) => {
+                self.macro_calls
+                    .insert(format!("{}::{}", call.namespace, call.name));
+                call.args.values().for_each(|e| self.visit_expr(e));
+            }
+            ExprVal::FunctionCall(call) => {
+                self.functions.insert(call.name.clone());
+                self.visit_call(call);
+            }
+            ExprVal::Array(items) => items.iter().for_each(|e| self.visit_expr(e)),
+            ExprVal::StringConcat(concat) => concat.values.iter().for_each(|v| self.visit_val(v)),
+            ExprVal::In(in_expr) => {
+                self.visit_expr(&in_expr.lhs);
+                self.visit_expr(&in_expr.rhs);
+            }
+            ExprVal::Int(_) | ExprVal::Float(_) | ExprVal::Bool(_) => {}
+        }
+    }
+}
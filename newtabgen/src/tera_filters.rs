@@ -7,3 +7,27 @@ pub use site_icon::SiteIcon;
 
 mod hash;
 pub use hash::Hash;
+
+mod markdown;
+pub use markdown::Markdown;
+
+mod slugify;
+pub use slugify::Slugify;
+
+mod format_number;
+pub use format_number::FormatNumber;
+
+mod format_bytes;
+pub use format_bytes::FormatBytes;
+
+mod format_duration;
+pub use format_duration::FormatDuration;
+
+mod contrast_color;
+pub use contrast_color::ContrastColor;
+
+mod truncate_middle;
+pub use truncate_middle::TruncateMiddle;
+
+mod url_parts;
+pub use url_parts::{UrlHost, UrlOrigin, UrlPath};
@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides the `contrast_color` Tera filter.
+
+use std::collections::HashMap;
+
+use tera::{to_value, Filter};
+
+use crate::util::color;
+
+/// Text color filter for use in Tera templates. Chooses whichever of a dark and a light color
+/// contrasts more with a background color, so text stays readable over colors from the config or
+/// theme.
+///
+/// Colors may be in any notation accepted by `ThemeColors`, such as `#rrggbb` or `rgb()`, but must
+/// be opaque. The dark and light colors default to black and white, and can be changed with the
+/// optional `dark` and `light` arguments. The chosen color is returned as provided.
+///
+/// # Example
+///
+/// ```html
+/// {% if page.color %}
+/// <span style="background: {{ page.color }}; color: {{ page.color | contrast_color }}">
+///     {{ page.name }}
+/// </span>
+/// {% endif %}
+/// <span style="color: {{ "#336699" | contrast_color(dark = "#222", light = "#eee") }}">
+/// ```
+pub struct ContrastColor;
+
+impl Filter for ContrastColor {
+    fn filter(
+        &self,
+        value: &tera::Value,
+        args: &HashMap<String, tera::Value>,
+    ) -> tera::Result<tera::Value> {
+        let background = value
+            .as_str()
+            .ok_or_else(|| tera::Error::msg("tried to find contrast color of non-string"))?;
+        let background = luminance(background)?;
+        let dark = color_arg(args, "dark")?.unwrap_or("#000000");
+        let light = color_arg(args, "light")?.unwrap_or("#ffffff");
+
+        let dark_contrast = color::contrast_ratio(background, luminance(dark)?);
+        let light_contrast = color::contrast_ratio(background, luminance(light)?);
+        let output = if dark_contrast >= light_contrast {
+            dark
+        } else {
+            light
+        };
+        to_value(output)
+            .map_err(|_| tera::Error::msg("finding contrast color produced invalid value"))
+    }
+
+    fn is_safe(&self) -> bool {
+        true
+    }
+}
+
+/// Reads an optional color argument.
+///
+/// # Errors
+///
+/// Returns an error if the argument isn't a string.
+fn color_arg<'a>(
+    args: &'a HashMap<String, tera::Value>,
+    name: &str,
+) -> tera::Result<Option<&'a str>> {
+    match args.get(name) {
+        Some(value) => value
+            .as_str()
+            .map(Some)
+            .ok_or_else(|| tera::Error::msg(format!("expected '{name}' to be a color string"))),
+        None => Ok(None),
+    }
+}
+
+/// Parses a color and calculates its relative luminance.
+///
+/// # Errors
+///
+/// Returns an error if the color can't be parsed or isn't opaque.
+fn luminance(value: &str) -> tera::Result<f32> {
+    color::parse(value)
+        .map(color::relative_luminance)
+        .ok_or_else(|| tera::Error::msg(format!("'{value}' isn't an opaque color")))
+}
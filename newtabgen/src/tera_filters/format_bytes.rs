@@ -0,0 +1,59 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides the `format_bytes` Tera filter.
+
+use std::collections::HashMap;
+
+use icu_locid::Locale;
+use tera::{to_value, Filter};
+
+use super::format_number::{decimals_arg, format_decimal};
+
+/// Binary size units, each 1024 times the previous.
+const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+/// Size formatting filter for use in Tera templates. Formats a number of bytes in the largest
+/// fitting binary unit, using the number formatting of `Config.locale`.
+///
+/// Sizes are rounded to one fractional digit, which can be changed with the optional `decimals`
+/// argument.
+///
+/// # Example
+///
+/// ```html
+/// <span>{{ 1536 | format_bytes }}</span>                  <!-- 1.5 KiB -->
+/// <span>{{ 5000000 | format_bytes(decimals = 2) }}</span> <!-- 4.77 MiB -->
+/// ```
+pub struct FormatBytes(pub Locale);
+
+impl Filter for FormatBytes {
+    fn filter(
+        &self,
+        value: &tera::Value,
+        args: &HashMap<String, tera::Value>,
+    ) -> tera::Result<tera::Value> {
+        let mut size = value
+            .as_f64()
+            .ok_or_else(|| tera::Error::msg("tried to format non-number as bytes"))?;
+        let decimals = decimals_arg(args)?.unwrap_or(1);
+
+        let mut unit = 0;
+        while size.abs() >= 1024f64 && unit < UNITS.len() - 1 {
+            size /= 1024f64;
+            unit += 1;
+        }
+        // Bytes can't be split
+        let decimals = if unit == 0 { 0 } else { decimals };
+
+        let output = format!(
+            "{} {}",
+            format_decimal(&self.0, size, decimals, false)?,
+            UNITS[unit]
+        );
+        to_value(output).map_err(|_| tera::Error::msg("formatting bytes produced invalid value"))
+    }
+
+    fn is_safe(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,77 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides the `format_duration` Tera filter.
+
+use std::collections::HashMap;
+
+use icu_locid::Locale;
+use tera::{to_value, Filter};
+
+use super::format_number::format_decimal;
+
+/// Duration formatting filter for use in Tera templates. Formats a duration in milliseconds, such
+/// as `BuildReport.elapsed_ms`, using the number formatting of `Config.locale`.
+///
+/// Durations under a minute are shown in a single unit, longer durations are split into two. The
+/// optional `unit` argument accepts durations in seconds instead, with `unit = "s"`.
+///
+/// # Example
+///
+/// ```html
+/// <span>{{ 350 | format_duration }}</span>             <!-- 350 ms -->
+/// <span>{{ 1250 | format_duration }}</span>            <!-- 1.3 s -->
+/// <span>{{ 3725 | format_duration(unit = "s") }}</span> <!-- 1 h 2 min -->
+/// ```
+pub struct FormatDuration(pub Locale);
+
+impl Filter for FormatDuration {
+    fn filter(
+        &self,
+        value: &tera::Value,
+        args: &HashMap<String, tera::Value>,
+    ) -> tera::Result<tera::Value> {
+        let value = value
+            .as_f64()
+            .ok_or_else(|| tera::Error::msg("tried to format non-number as duration"))?;
+        let ms = match args.get("unit").and_then(|u| u.as_str()) {
+            None | Some("ms") => value,
+            Some("s") => value * 1000f64,
+            Some(unit) => {
+                return Err(tera::Error::msg(format!(
+                    "unsupported duration unit '{unit}', expected 'ms' or 's'"
+                )))
+            }
+        };
+
+        let number = |n: f64, decimals: u8| format_decimal(&self.0, n, decimals, false);
+        let output = if ms < 1000f64 {
+            format!("{} ms", number(ms, 0)?)
+        } else if ms < 60_000f64 {
+            format!("{} s", number(ms / 1000f64, 1)?)
+        } else {
+            let secs = (ms / 1000f64).round();
+            let (big, small, units) = if secs < 3600f64 {
+                ((secs / 60f64).floor(), secs % 60f64, ["min", "s"])
+            } else {
+                let mins = (secs / 60f64).floor();
+                ((mins / 60f64).floor(), mins % 60f64, ["h", "min"])
+            };
+            if small == 0f64 {
+                format!("{} {}", number(big, 0)?, units[0])
+            } else {
+                format!(
+                    "{} {} {} {}",
+                    number(big, 0)?,
+                    units[0],
+                    number(small, 0)?,
+                    units[1]
+                )
+            }
+        };
+        to_value(output).map_err(|_| tera::Error::msg("formatting duration produced invalid value"))
+    }
+
+    fn is_safe(&self) -> bool {
+        true
+    }
+}
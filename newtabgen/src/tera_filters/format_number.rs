@@ -0,0 +1,92 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides the `format_number` Tera filter, along with locale-aware number formatting shared by
+//! the other formatting filters.
+
+use std::collections::HashMap;
+
+use fixed_decimal::{FixedDecimal, FloatPrecision};
+use icu_decimal::FixedDecimalFormatter;
+use icu_locid::Locale;
+use tera::{to_value, Filter};
+
+/// Number formatting filter for use in Tera templates. Formats a number using the decimal
+/// separator and digit grouping of `Config.locale`.
+///
+/// Numbers are rounded to two fractional digits, dropping trailing zeros. The optional `decimals`
+/// argument instead rounds to exactly that many fractional digits.
+///
+/// # Example
+///
+/// ```html
+/// <span>{{ 1234567.891 | format_number }}</span>              <!-- 1,234,567.89 -->
+/// <span>{{ 1234.5 | format_number(decimals = 2) }}</span>     <!-- 1,234.50 -->
+/// ```
+pub struct FormatNumber(pub Locale);
+
+impl Filter for FormatNumber {
+    fn filter(
+        &self,
+        value: &tera::Value,
+        args: &HashMap<String, tera::Value>,
+    ) -> tera::Result<tera::Value> {
+        let number = value
+            .as_f64()
+            .ok_or_else(|| tera::Error::msg("tried to format non-number as number"))?;
+        let output = match decimals_arg(args)? {
+            Some(decimals) => format_decimal(&self.0, number, decimals, true)?,
+            None => format_decimal(&self.0, number, 2, false)?,
+        };
+        to_value(output).map_err(|_| tera::Error::msg("formatting number produced invalid value"))
+    }
+
+    fn is_safe(&self) -> bool {
+        true
+    }
+}
+
+/// Reads the optional `decimals` argument of a formatting filter.
+pub(crate) fn decimals_arg(args: &HashMap<String, tera::Value>) -> tera::Result<Option<u8>> {
+    args.get("decimals")
+        .map(|v| {
+            v.as_u64()
+                .and_then(|d| u8::try_from(d).ok())
+                .ok_or_else(|| tera::Error::msg("`decimals` must be a small positive integer"))
+        })
+        .transpose()
+}
+
+/// Formats a number using the decimal separator and digit grouping of a locale.
+///
+/// # Arguments
+///
+/// * `locale` - The locale to format the number for.
+/// * `value` - The number to format.
+/// * `decimals` - Number of fractional digits to round to.
+/// * `pad` - Indicates if exactly `decimals` fractional digits should be shown, keeping trailing
+///   zeros.
+///
+/// # Errors
+///
+/// Returns an error if the number isn't finite, or if formatting rules can't be loaded for the
+/// locale.
+pub(crate) fn format_decimal(
+    locale: &Locale,
+    value: f64,
+    decimals: u8,
+    pad: bool,
+) -> tera::Result<String> {
+    let mut decimal = FixedDecimal::try_from_f64(value, FloatPrecision::Floating)
+        .map_err(|_| tera::Error::msg(format!("can't format number '{value}'")))?;
+    let position = -i16::from(decimals);
+    decimal.half_expand(position);
+    if pad {
+        decimal.pad_end(position);
+    } else {
+        decimal.trim_end();
+    }
+
+    let formatter = FixedDecimalFormatter::try_new(&locale.into(), Default::default())
+        .map_err(|e| tera::Error::msg(format!("failed to load number formatting rules ({e})")))?;
+    Ok(formatter.format_to_string(&decimal))
+}
@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides the `markdown` Tera filter.
+
+use std::collections::HashMap;
+
+#[cfg(feature = "markdown")]
+use pulldown_cmark::{html, Options, Parser};
+use tera::{to_value, Filter};
+
+/// Markdown filter for use in Tera templates. Renders a markdown string into HTML.
+///
+/// Without the `markdown` feature, the string is escaped and rendered as a plain paragraph.
+pub struct Markdown;
+
+impl Filter for Markdown {
+    fn filter(
+        &self,
+        value: &tera::Value,
+        _args: &HashMap<String, tera::Value>,
+    ) -> tera::Result<tera::Value> {
+        match value.as_str() {
+            Some(v) => {
+                let output = render(v);
+                to_value(output)
+                    .map_err(|_| tera::Error::msg("rendering markdown produced invalid value"))
+            }
+            None => Err(tera::Error::msg("tried to render non-string as markdown")),
+        }
+    }
+
+    fn is_safe(&self) -> bool {
+        true
+    }
+}
+
+/// Renders markdown into HTML.
+#[cfg(feature = "markdown")]
+fn render(markdown: &str) -> String {
+    let mut output = String::new();
+    html::push_html(&mut output, Parser::new_ext(markdown, Options::all()));
+    output
+}
+
+/// Renders markdown as an escaped plain paragraph, for when markdown support is disabled.
+#[cfg(not(feature = "markdown"))]
+fn render(markdown: &str) -> String {
+    format!("<p>{}</p>", tera::escape_html(markdown))
+}
@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides the `slugify` Tera filter.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+use tera::{to_value, Filter};
+
+/// Slugs handed out while rendering a template.
+#[derive(Default)]
+struct Slugs {
+    /// The slug of each string slugified so far.
+    by_input: HashMap<String, String>,
+
+    /// Every slug handed out so far.
+    taken: HashSet<String>,
+}
+
+/// Slugify filter for use in Tera templates. Turns a string into an identifier that can be used
+/// in URLs and as a CSS selector without escaping, e.g. `Work & Play` becomes `work-play`.
+///
+/// Letters are transliterated to lowercase ASCII, and runs of anything but letters and digits
+/// become a single `-`. Slugs starting with a digit are prefixed with `n-`, and empty slugs become
+/// `slug`.
+///
+/// Slugs are unique within a template: the same string always has the same slug, but different
+/// strings that would have the same slug are told apart with a numbered suffix, e.g. `work-2`.
+/// Pass `unique = false` to skip this.
+///
+/// # Example
+///
+/// ```html
+/// <section id="{{ section.name | slugify }}">
+/// <a href="#{{ section.name | slugify }}">
+/// ```
+#[derive(Default)]
+pub struct Slugify(Mutex<Slugs>);
+
+impl Filter for Slugify {
+    fn filter(
+        &self,
+        value: &tera::Value,
+        args: &HashMap<String, tera::Value>,
+    ) -> tera::Result<tera::Value> {
+        let input = value
+            .as_str()
+            .ok_or_else(|| tera::Error::msg("tried to slugify non-string"))?;
+        let unique = match args.get("unique") {
+            Some(unique) => unique
+                .as_bool()
+                .ok_or_else(|| tera::Error::msg("expected 'unique' to be a boolean"))?,
+            None => true,
+        };
+
+        let base = slug(input);
+        let output = if unique {
+            let mut slugs = self.0.lock().unwrap_or_else(|e| e.into_inner());
+            match slugs.by_input.get(input) {
+                Some(slug) => slug.clone(),
+                None => {
+                    let mut slug = base.clone();
+                    let mut n = 2;
+                    while slugs.taken.contains(&slug) {
+                        slug = format!("{base}-{n}");
+                        n += 1;
+                    }
+                    slugs.taken.insert(slug.clone());
+                    slugs.by_input.insert(input.to_owned(), slug.clone());
+                    slug
+                }
+            }
+        } else {
+            base
+        };
+        to_value(output).map_err(|_| tera::Error::msg("slugifying produced invalid value"))
+    }
+
+    fn is_safe(&self) -> bool {
+        true
+    }
+}
+
+/// Turns a string into a slug, without checking if it's unique.
+fn slug(input: &str) -> String {
+    let slug = slug::slugify(input);
+    if slug.is_empty() {
+        "slug".into()
+    } else if slug.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("n-{slug}")
+    } else {
+        slug
+    }
+}
@@ -0,0 +1,58 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides the `truncate_middle` Tera filter.
+
+use std::collections::HashMap;
+
+use tera::{to_value, Filter};
+
+/// Marks where text was removed.
+const ELLIPSIS: char = '…';
+
+/// Middle truncation filter for use in Tera templates. Shortens a string to at most `len`
+/// characters by replacing its middle with an ellipsis, keeping the start and end, which tell
+/// long names and URLs apart better than the start alone, e.g.
+/// `verylongsubdomain.example.com` becomes `verylongsubd…example.com` with `len = 24`.
+///
+/// Strings that already fit are returned unchanged. The start keeps the extra character when the
+/// remaining length is odd.
+///
+/// # Example
+///
+/// ```html
+/// <span title="{{ link.url }}">{{ link.url | truncate_middle(len = 32) }}</span>
+/// ```
+pub struct TruncateMiddle;
+
+impl Filter for TruncateMiddle {
+    fn filter(
+        &self,
+        value: &tera::Value,
+        args: &HashMap<String, tera::Value>,
+    ) -> tera::Result<tera::Value> {
+        let input = value
+            .as_str()
+            .ok_or_else(|| tera::Error::msg("tried to truncate non-string"))?;
+        let len = args
+            .get("len")
+            .ok_or_else(|| tera::Error::msg("expected 'len' argument"))?
+            .as_u64()
+            .filter(|len| *len > 0)
+            .ok_or_else(|| tera::Error::msg("expected 'len' to be a positive integer"))?
+            as usize;
+
+        let chars = input.chars().collect::<Vec<_>>();
+        let output = if chars.len() <= len {
+            input.to_owned()
+        } else {
+            let kept = len - 1;
+            let start = kept - kept / 2;
+            let end = kept / 2;
+            let mut output = chars[..start].iter().collect::<String>();
+            output.push(ELLIPSIS);
+            output.extend(&chars[chars.len() - end..]);
+            output
+        };
+        to_value(output).map_err(|_| tera::Error::msg("truncating produced invalid value"))
+    }
+}
@@ -0,0 +1,103 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides the `url_host`, `url_path`, and `url_origin` Tera filters.
+
+use std::collections::HashMap;
+
+use tera::{to_value, Filter};
+use url::Url;
+
+/// Host filter for use in Tera templates. Returns the host of a URL, e.g. `github.com` for
+/// `https://github.com/fr33zing`, or an empty string if the URL has none, like `mailto:` links.
+///
+/// Pass `strip_www = true` to remove a leading `www.`.
+///
+/// # Example
+///
+/// ```html
+/// <mark class="host">{{ link.url | url_host(strip_www = true) }}</mark>
+/// ```
+pub struct UrlHost;
+
+impl Filter for UrlHost {
+    fn filter(
+        &self,
+        value: &tera::Value,
+        args: &HashMap<String, tera::Value>,
+    ) -> tera::Result<tera::Value> {
+        let url = parse(value, "url_host")?;
+        let strip_www = match args.get("strip_www") {
+            Some(strip_www) => strip_www
+                .as_bool()
+                .ok_or_else(|| tera::Error::msg("expected 'strip_www' to be a boolean"))?,
+            None => false,
+        };
+        let host = url.host_str().unwrap_or_default();
+        let host = match host.strip_prefix("www.") {
+            Some(stripped) if strip_www => stripped,
+            _ => host,
+        };
+        to_value(host).map_err(|_| tera::Error::msg("url_host produced invalid value"))
+    }
+}
+
+/// Path filter for use in Tera templates. Returns the path of a URL, e.g. `/fr33zing` for
+/// `https://github.com/fr33zing?tab=repositories`, without the query or fragment.
+///
+/// # Example
+///
+/// ```html
+/// <span>{{ link.url | url_path }}</span>
+/// ```
+pub struct UrlPath;
+
+impl Filter for UrlPath {
+    fn filter(
+        &self,
+        value: &tera::Value,
+        _args: &HashMap<String, tera::Value>,
+    ) -> tera::Result<tera::Value> {
+        let url = parse(value, "url_path")?;
+        to_value(url.path()).map_err(|_| tera::Error::msg("url_path produced invalid value"))
+    }
+}
+
+/// Origin filter for use in Tera templates. Returns the scheme, host, and port of a URL, e.g.
+/// `https://github.com` for `https://github.com/fr33zing`, or an empty string if the URL has no
+/// meaningful origin, like `mailto:` links.
+///
+/// # Example
+///
+/// ```html
+/// <link rel="preconnect" href="{{ link.url | url_origin }}" />
+/// ```
+pub struct UrlOrigin;
+
+impl Filter for UrlOrigin {
+    fn filter(
+        &self,
+        value: &tera::Value,
+        _args: &HashMap<String, tera::Value>,
+    ) -> tera::Result<tera::Value> {
+        let url = parse(value, "url_origin")?;
+        let origin = url.origin();
+        let origin = if origin.is_tuple() {
+            origin.ascii_serialization()
+        } else {
+            String::new()
+        };
+        to_value(origin).map_err(|_| tera::Error::msg("url_origin produced invalid value"))
+    }
+}
+
+/// Parses the value a URL filter was applied to.
+///
+/// # Errors
+///
+/// Returns an error naming the filter if the value isn't a string or a valid absolute URL.
+fn parse(value: &tera::Value, filter: &str) -> tera::Result<Url> {
+    let url = value
+        .as_str()
+        .ok_or_else(|| tera::Error::msg(format!("tried to apply {filter} to non-string")))?;
+    Url::parse(url).map_err(|e| tera::Error::msg(format!("{filter}: invalid url '{url}' ({e})")))
+}
@@ -5,8 +5,22 @@
 mod len;
 pub use len::Len;
 
+mod build_info;
+pub use build_info::BuildInfo;
+
 mod count_links_in_page;
 pub use count_links_in_page::CountLinksInPage;
 
+mod link_groups;
+
+mod links_by_domain;
+pub use links_by_domain::LinksByDomain;
+
+mod links_by_tag;
+pub use links_by_tag::LinksByTag;
+
 mod svg_icon_href;
 pub use svg_icon_href::SvgIconHref;
+
+mod material_svg;
+pub use material_svg::MaterialSvg;
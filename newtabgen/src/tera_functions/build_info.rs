@@ -0,0 +1,76 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides the `build_info` Tera function.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+use tera::{to_value, Error, Result, Value};
+
+use crate::{builder::provenance, config::Config};
+
+/// Build metadata function for use in Tera templates. Returns an object describing the build:
+///
+/// * `version` - Version of newtabgen that built the page.
+/// * `built_at` - When the page was built, as a Unix timestamp that can be formatted with the
+///   `date` filter. `null` in reproducible builds, so the output doesn't change between builds.
+/// * `config_hash` - Hash of the parsed configuration, as recorded in provenance comments.
+/// * `page_icons` - Number of different material design icons used by pages.
+/// * `site_icons` - Number of site icons built. `null` in the SCSS template, which is rendered
+///   before they're embedded.
+///
+/// # Example
+///
+/// ```html
+/// {% set build = build_info() %}
+/// <footer>
+///     Generated by newtabgen v{{ build.version }}
+///     {% if build.built_at %}on {{ build.built_at | date(format="%Y-%m-%d") }}{% endif %}
+/// </footer>
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildInfo {
+    /// Version of newtabgen that built the page.
+    pub version: &'static str,
+
+    /// When the page was built, in seconds since the Unix epoch, unless the build is reproducible.
+    pub built_at: Option<u64>,
+
+    /// Hash of the parsed configuration.
+    pub config_hash: Option<String>,
+
+    /// Number of different material design icons used by pages.
+    pub page_icons: usize,
+
+    /// Number of site icons built, if known yet.
+    pub site_icons: Option<usize>,
+}
+
+impl BuildInfo {
+    /// Describes a build of the provided config, without a timestamp or site icon count.
+    pub fn new(config: &Config) -> Self {
+        let page_icons = config
+            .pages
+            .iter()
+            .map(|p| (&p.icon, &p.icon_style))
+            .collect::<HashSet<_>>()
+            .len();
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            built_at: None,
+            config_hash: provenance::config_hash(config).ok(),
+            page_icons,
+            site_icons: None,
+        }
+    }
+}
+
+impl tera::Function for BuildInfo {
+    fn call(&self, _args: &HashMap<String, Value>) -> Result<Value> {
+        to_value(self).map_err(|_| Error::msg("build_info produced invalid value"))
+    }
+
+    fn is_safe(&self) -> bool {
+        true
+    }
+}
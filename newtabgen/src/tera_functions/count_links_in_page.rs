@@ -9,7 +9,7 @@ use tera::{to_value, Error, Result, Value};
 use crate::config::Config;
 
 /// Link counting function for use in Tera templates. Returns the number of links in all sections of
-/// the provided page.
+/// the provided page, including nested sections.
 ///
 /// # Example
 ///
@@ -35,9 +35,8 @@ impl tera::Function for CountLinksInPage {
                     .iter()
                     .find(|p| p.name == page_name)
                     .ok_or_else(|| Error::msg("page not found"))?
-                    .sections
-                    .iter()
-                    .fold(0, |sum, val| sum + val.links.len());
+                    .all_links()
+                    .count();
                 to_value(n).map_err(|_| Error::msg("count_links produced invalid value"))
             }
             None => Err(Error::msg("`page_name` must be a str")),
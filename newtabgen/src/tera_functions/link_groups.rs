@@ -0,0 +1,100 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Groups links across pages for the `links_by_domain` and `links_by_tag` Tera functions.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use tera::{to_value, Error, Map, Result, Value};
+
+use crate::config::{Config, Link, Section};
+
+/// A link, with the page and section it's in.
+#[derive(Clone, Copy, Serialize)]
+pub(super) struct LinkEntry<'a> {
+    /// Name of the page the link is in.
+    pub page: &'a str,
+
+    /// Name of the section the link is in, the innermost one if sections are nested.
+    pub section: &'a str,
+
+    /// The link.
+    pub link: &'a Link,
+}
+
+/// Finds every link in the config, or only in the page named by the optional `page` argument.
+///
+/// # Errors
+///
+/// Returns an error if `page` isn't a string or doesn't name a page.
+pub(super) fn entries<'a>(
+    config: &'a Config,
+    args: &HashMap<String, Value>,
+) -> Result<Vec<LinkEntry<'a>>> {
+    let page_name = match args.get("page") {
+        Some(page) => Some(
+            page.as_str()
+                .ok_or_else(|| Error::msg("`page` must be a str"))?,
+        ),
+        None => None,
+    };
+    let pages = config
+        .pages
+        .iter()
+        .filter(|p| page_name.is_none_or(|name| p.name == name))
+        .collect::<Vec<_>>();
+    if let (Some(_), true) = (page_name, pages.is_empty()) {
+        return Err(Error::msg("page not found"));
+    }
+
+    let mut entries = Vec::new();
+    for page in pages {
+        let mut stack = page.sections.iter().rev().collect::<Vec<&Section>>();
+        while let Some(section) = stack.pop() {
+            entries.extend(section.links.iter().map(|link| LinkEntry {
+                page: &page.name,
+                section: &section.name,
+                link,
+            }));
+            stack.extend(section.sections.iter().rev());
+        }
+    }
+    Ok(entries)
+}
+
+/// Converts groups of links, each with the key they share such as a domain or tag, to the
+/// value returned to templates, keeping at most as many groups as the optional `limit` argument.
+///
+/// # Arguments
+///
+/// * `name` - Name the key of each group is provided to templates as, e.g. "domain".
+/// * `grouped` - Each key with its links, in the order the groups are returned.
+/// * `args` - Arguments of the function call.
+///
+/// # Errors
+///
+/// Returns an error if `limit` isn't a positive integer.
+pub(super) fn to_groups(
+    name: &'static str,
+    grouped: Vec<(String, Vec<LinkEntry>)>,
+    args: &HashMap<String, Value>,
+) -> Result<Value> {
+    let limit = match args.get("limit") {
+        Some(limit) => limit
+            .as_u64()
+            .ok_or_else(|| Error::msg("`limit` must be a positive integer"))?
+            as usize,
+        None => usize::MAX,
+    };
+    let mut groups = Vec::new();
+    for (key, links) in grouped.into_iter().take(limit) {
+        let mut group = Map::new();
+        group.insert(name.into(), key.into());
+        group.insert("count".into(), links.len().into());
+        let links = to_value(links)
+            .map_err(|_| Error::msg(format!("links_by_{name} produced invalid value")))?;
+        group.insert("links".into(), links);
+        groups.push(Value::Object(group));
+    }
+    Ok(Value::Array(groups))
+}
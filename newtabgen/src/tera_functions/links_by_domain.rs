@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides the `links_by_domain` Tera function.
+
+use std::collections::HashMap;
+
+use tera::{Result, Value};
+use url::Url;
+
+use super::link_groups::{self, LinkEntry};
+use crate::config::Config;
+
+/// Link grouping function for use in Tera templates. Returns the links of every page grouped by
+/// the domain they link to, ignoring a leading `www.`, with the domains linked to most first.
+/// Links without a domain, such as `mailto:` links, are left out.
+///
+/// Each group has a `domain`, the `count` of its links, and the `links` themselves, each with the
+/// `page` and `section` it's in. Pass `page` to only group the links of one page, and `limit` to
+/// only return the first groups.
+///
+/// # Example
+///
+/// ```html
+/// <ol>
+///     {% for group in links_by_domain(limit = 5) %}
+///         <li>{{ group.domain }} ({{ group.count }} links)</li>
+///     {% endfor %}
+/// </ol>
+/// ```
+pub struct LinksByDomain(pub Config);
+
+impl tera::Function for LinksByDomain {
+    fn call(&self, args: &HashMap<String, Value>) -> Result<Value> {
+        let mut grouped: Vec<(String, Vec<LinkEntry>)> = Vec::new();
+        for entry in link_groups::entries(&self.0, args)? {
+            let Some(domain) = domain(&entry.link.url) else {
+                continue;
+            };
+            match grouped.iter_mut().find(|(d, _)| *d == domain) {
+                Some((_, links)) => links.push(entry),
+                None => grouped.push((domain, vec![entry])),
+            }
+        }
+        // Stable, so domains linked to equally often stay in the order they're first linked to
+        grouped.sort_by_key(|(_, links)| std::cmp::Reverse(links.len()));
+        link_groups::to_groups("domain", grouped, args)
+    }
+}
+
+/// Finds the domain of a URL, without a leading `www.`.
+fn domain(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?.to_ascii_lowercase();
+    Some(host.strip_prefix("www.").map(str::to_owned).unwrap_or(host))
+}
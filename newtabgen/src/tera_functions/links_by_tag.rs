@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides the `links_by_tag` Tera function.
+
+use std::collections::{BTreeMap, HashMap};
+
+use tera::{Result, Value};
+
+use super::link_groups::{self, LinkEntry};
+use crate::config::Config;
+
+/// Link grouping function for use in Tera templates. Returns the links of every page grouped by
+/// their tags (see `Link.tags`), in alphabetical order of tags. Links with several tags are in
+/// each of their groups, and links without tags are left out.
+///
+/// Each group has a `tag`, the `count` of its links, and the `links` themselves, each with the
+/// `page` and `section` it's in. Pass `page` to only group the links of one page, and `limit` to
+/// only return the first groups.
+///
+/// # Example
+///
+/// ```html
+/// {% for group in links_by_tag() %}
+///     <h2>{{ group.tag }}</h2>
+///     {% for entry in group.links %}
+///         <a href="{{ entry.link.url }}">{{ entry.link.name }}</a>
+///     {% endfor %}
+/// {% endfor %}
+/// ```
+pub struct LinksByTag(pub Config);
+
+impl tera::Function for LinksByTag {
+    fn call(&self, args: &HashMap<String, Value>) -> Result<Value> {
+        let mut grouped: BTreeMap<String, Vec<LinkEntry>> = BTreeMap::new();
+        for entry in link_groups::entries(&self.0, args)? {
+            for tag in &entry.link.tags {
+                grouped.entry(tag.clone()).or_default().push(entry);
+            }
+        }
+        link_groups::to_groups("tag", grouped.into_iter().collect(), args)
+    }
+}
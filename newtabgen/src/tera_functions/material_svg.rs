@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Provides the `material_svg` Tera function.
+
+use std::collections::HashMap;
+
+use tera::{to_value, Error, Result, Value};
+
+use crate::builder::svg_icons::{icons_repo_dir, load_icon};
+
+/// Material SVG function for use in Tera templates. Converts an icon reference (name and style) to
+/// the full markup of the icon's `<svg>` element, for contexts where `<use href>` doesn't work.
+///
+/// # Example
+///
+/// ```scss
+/// .icon {
+///     mask-image: url("data:image/svg+xml,{{ material_svg(icon = "home", style = "outlined") | urlencode_strict }}");
+/// }
+/// ```
+pub struct MaterialSvg;
+
+impl tera::Function for MaterialSvg {
+    fn call(&self, args: &HashMap<String, Value>) -> Result<Value> {
+        let icon = args
+            .get("icon")
+            .ok_or_else(|| Error::msg("material_svg requires argument `icon`"))?
+            .as_str()
+            .ok_or_else(|| Error::msg("`icon` must be a string"))?;
+
+        let style = args
+            .get("style")
+            .ok_or_else(|| Error::msg("material_svg requires argument `style`"))?
+            .as_str()
+            .ok_or_else(|| Error::msg("`style` must be a string"))?;
+
+        let svg = icons_repo_dir()
+            .and_then(|dir| load_icon(&dir, icon, style))
+            .map_err(|e| Error::msg(format!("material_svg failed to load icon ({e})")))?;
+        to_value(svg.trim()).map_err(|_| Error::msg("material_svg produced invalid value"))
+    }
+}
@@ -0,0 +1,120 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Reads the files of theme bundles (see [`ThemeBundle`][crate::resources::ThemeBundle]), from a
+//! directory or a zip archive.
+//!
+//! Decompressed files are limited in size, and entries whose paths would escape the bundle are
+//! rejected, since archives may be downloaded from anywhere.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Cursor, Read},
+    path::{Component, Path},
+};
+
+use zip::ZipArchive;
+
+/// Largest size of a single file in a zip archive once decompressed, in bytes.
+const MAX_FILE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Largest size of all files in a zip archive once decompressed, in bytes.
+const MAX_ARCHIVE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Files of a theme bundle, keyed by their path within it, separated by `/`.
+pub(crate) type Files = BTreeMap<String, Vec<u8>>;
+
+/// Reads every file in a directory and its subdirectories.
+///
+/// # Errors
+///
+/// Returns an error if a directory or file can't be read.
+pub(crate) fn read_dir(dir: &Path) -> io::Result<Files> {
+    let mut files = Files::new();
+    let mut stack = vec![(dir.to_path_buf(), String::new())];
+    while let Some((dir, prefix)) = stack.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let name = format!("{prefix}{}", entry.file_name().to_string_lossy());
+            if entry.file_type()?.is_dir() {
+                stack.push((entry.path(), format!("{name}/")));
+            } else {
+                files.insert(name, fs::read(entry.path())?);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Reads every file in a zip archive.
+///
+/// # Errors
+///
+/// Returns an error describing the problem if the archive is malformed or unsupported, contains a
+/// path outside of the archive, or decompresses to more than [`MAX_FILE_BYTES`] per file or
+/// [`MAX_ARCHIVE_BYTES`] in total.
+pub(crate) fn read_zip(zip: &[u8]) -> Result<Files, String> {
+    let mut archive = ZipArchive::new(Cursor::new(zip)).map_err(|e| e.to_string())?;
+    let mut files = Files::new();
+    let mut total = 0;
+    for i in 0..archive.len() {
+        let file = archive.by_index(i).map_err(|e| e.to_string())?;
+        if file.is_dir() {
+            continue;
+        }
+        let Some(path) = file.enclosed_name() else {
+            return Err(format!("unsafe path in archive ({})", file.name()));
+        };
+        let name = path
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(part) => Some(part.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+
+        // The sizes in the archive can't be trusted, so stop reading past the limit
+        let mut data = Vec::new();
+        file.take(MAX_FILE_BYTES + 1)
+            .read_to_end(&mut data)
+            .map_err(|e| format!("failed to decompress {name} ({e})"))?;
+        total += data.len() as u64;
+        if data.len() as u64 > MAX_FILE_BYTES {
+            return Err(format!(
+                "{name} is larger than {} MiB once decompressed",
+                MAX_FILE_BYTES / 1024 / 1024
+            ));
+        }
+        if total > MAX_ARCHIVE_BYTES {
+            return Err(format!(
+                "archive is larger than {} MiB once decompressed",
+                MAX_ARCHIVE_BYTES / 1024 / 1024
+            ));
+        }
+        files.insert(name, data);
+    }
+    Ok(files)
+}
+
+/// Removes the directory wrapping every file, if `marker` isn't at the root but is in that
+/// directory. Archives downloaded from Git hosts wrap everything in a directory named after the
+/// repository.
+pub(crate) fn strip_root(files: Files, marker: &str) -> Files {
+    if files.contains_key(marker) {
+        return files;
+    }
+    let Some((root, _)) = files.keys().find_map(|name| name.split_once('/')) else {
+        return files;
+    };
+    let prefix = format!("{root}/");
+    let wrapped = files.keys().all(|name| name.starts_with(&prefix))
+        && files.contains_key(&format!("{prefix}{marker}"));
+    if !wrapped {
+        return files;
+    }
+    files
+        .into_iter()
+        .map(|(name, data)| (name[prefix.len()..].to_owned(), data))
+        .collect()
+}
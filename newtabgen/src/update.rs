@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Checks whether a newer release has been published, so users of long-running scheduled builds
+//! learn about fixes they're missing. Nothing is ever downloaded or installed besides the release
+//! metadata.
+//!
+//! Release metadata is read from the crates.io API over HTTPS, and cached like other downloaded
+//! resources, so checking on every scheduled build queries it at most once an hour.
+
+use semver::Version;
+use serde::Deserialize;
+use thiserror::Error;
+use url::Url;
+
+use crate::{
+    builder::CacheMode,
+    resources::{self, ResourceError},
+};
+
+/// Where release metadata of a crate is queried, followed by the name of the crate.
+pub const RELEASE_FEED: &str = "https://crates.io/api/v1/crates/";
+
+/// Errors that may occur when checking for a newer release.
+#[derive(Error, Debug)]
+pub enum UpdateError {
+    /// Occurs when downloading the release metadata fails.
+    #[error(transparent)]
+    Download(#[from] ResourceError),
+
+    /// Occurs when the release metadata can't be parsed.
+    #[error("failed to parse release metadata: {0}")]
+    Parse(String),
+
+    /// Occurs when a version can't be parsed.
+    #[error("invalid version '{1}' ({0})")]
+    Version(#[source] semver::Error, String),
+}
+
+/// Release metadata of a crate, as returned by [`RELEASE_FEED`].
+#[derive(Deserialize)]
+struct CrateResponse {
+    /// Metadata of the crate itself.
+    #[serde(rename = "crate")]
+    krate: CrateMetadata,
+}
+
+/// Metadata of a crate, only the parts needed to find the latest release.
+#[derive(Deserialize)]
+struct CrateMetadata {
+    /// Latest version that isn't a pre-release or yanked, if any.
+    max_stable_version: Option<String>,
+
+    /// Latest version, which may be a pre-release.
+    max_version: String,
+}
+
+/// A newer release than the running one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Update {
+    /// Name of the crate.
+    pub crate_name: String,
+
+    /// The running version.
+    pub current: Version,
+
+    /// The latest published version.
+    pub latest: Version,
+}
+
+impl Update {
+    /// Describes how to upgrade to the latest release with cargo. Users who installed a packaged
+    /// version should upgrade through their package manager instead.
+    pub fn instructions(&self) -> String {
+        format!(
+            "newtabgen {} is available (running {}), upgrade with: cargo install {} --locked",
+            self.latest, self.current, self.crate_name
+        )
+    }
+}
+
+/// Finds the latest stable release of a crate, or the latest pre-release if it has no stable
+/// releases.
+///
+/// # Arguments
+///
+/// * `crate_name` - Name of the crate on crates.io, e.g. `newtabgen-cli`.
+/// * `cache` - How cached release metadata is used.
+///
+/// # Errors
+///
+/// Returns an error if the release metadata can't be downloaded or parsed.
+pub async fn latest_release(crate_name: &str, cache: CacheMode) -> Result<Version, UpdateError> {
+    let url = Url::parse(RELEASE_FEED)
+        .and_then(|feed| feed.join(crate_name))
+        .map_err(|e| UpdateError::Parse(e.to_string()))?;
+    // crates.io rejects requests without a user agent identifying the client
+    let client = reqwest::Client::builder()
+        .user_agent("newtabgen (checking for updates) github.com/fr33zing/newtabgen")
+        .build()
+        .map_err(|e| ResourceError::Http(format!("{url} ({e})")))?;
+    let bytes = resources::download_bytes(&client, &url, cache).await?;
+    let response: CrateResponse =
+        serde_json::from_slice(&bytes).map_err(|e| UpdateError::Parse(e.to_string()))?;
+    let latest = response
+        .krate
+        .max_stable_version
+        .unwrap_or(response.krate.max_version);
+    Version::parse(&latest).map_err(|e| UpdateError::Version(e, latest))
+}
+
+/// Checks if a newer release of a crate than the running one has been published.
+///
+/// # Arguments
+///
+/// * `crate_name` - Name of the crate on crates.io, e.g. `newtabgen-cli`.
+/// * `current` - The running version, usually `env!("CARGO_PKG_VERSION")`.
+/// * `cache` - How cached release metadata is used.
+///
+/// # Errors
+///
+/// Returns an error if the release metadata can't be downloaded or parsed, or `current` isn't a
+/// valid version.
+///
+/// # Returns
+///
+/// The newer release, or `None` if the running version is the latest.
+pub async fn check(
+    crate_name: &str,
+    current: &str,
+    cache: CacheMode,
+) -> Result<Option<Update>, UpdateError> {
+    let current =
+        Version::parse(current).map_err(|e| UpdateError::Version(e, current.to_owned()))?;
+    let latest = latest_release(crate_name, cache).await?;
+    Ok((latest > current).then(|| Update {
+        crate_name: crate_name.to_owned(),
+        current,
+        latest,
+    }))
+}
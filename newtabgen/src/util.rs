@@ -2,7 +2,14 @@
 
 //! Utility functions.
 
-use std::{env, fs, io, path::PathBuf};
+pub mod color;
+pub mod domain;
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
 use sha1::{Digest, Sha1};
 
@@ -38,6 +45,68 @@ pub fn cache_subdir(subdir: &str) -> Result<PathBuf, io::Error> {
     Ok(cache_dir)
 }
 
+/// Finds a suitable directory for data that should persist, unlike the cache. The data directory
+/// and the requested subdirectory will be created if needed.
+///
+/// # Errors
+///
+/// Returns an error if a suitable data directory cannot be found.
+pub fn data_subdir(subdir: &str) -> Result<PathBuf, io::Error> {
+    let dir = match dirs::data_dir() {
+        Some(d) => d,
+        None => env::current_dir()?,
+    };
+    let data_dir = dir.join("newtabgen").join(subdir);
+    fs::create_dir_all(&data_dir)?;
+    Ok(data_dir)
+}
+
+/// Checks if a cached file was created at least `max_age_secs` seconds ago.
+///
+/// May not work on all platforms (due to the use of [`std::fs::Metadata`]). Files are never
+/// considered expired if this is the case.
+pub fn cache_expired(path: &Path, max_age_secs: u64) -> bool {
+    let Ok(metadata) = fs::metadata(path) else {
+        return false;
+    };
+    let Ok(created) = metadata.created() else {
+        return false;
+    };
+    let Ok(elapsed) = created.elapsed() else {
+        return false;
+    };
+    elapsed.as_secs() >= max_age_secs
+}
+
+/// Checks if a cached file was last used at least `max_age_secs` seconds ago, going by its
+/// modification time, which is updated by [`touch`] whenever the file is read from the cache.
+/// Files whose modification time can't be read are never considered unused.
+pub fn cache_unused(path: &Path, max_age_secs: u64) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .is_ok_and(|modified| {
+            modified
+                .elapsed()
+                .is_ok_and(|elapsed| elapsed.as_secs() >= max_age_secs)
+        })
+}
+
+/// Marks a cached file as used by setting its modification time to now, see [`cache_unused`].
+/// Failures are ignored, since they only cause the file to be removed from the cache earlier.
+pub fn touch(path: &Path) {
+    if let Ok(file) = fs::File::options().append(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+/// Checks if a file was last modified after the provided time. Files that don't exist, or whose
+/// modification time can't be read, are never considered modified.
+pub fn modified_after(path: &Path, time: SystemTime) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .is_ok_and(|modified| modified > time)
+}
+
 /// Returns a base32-encoded SHA1 hash of the provided bytes.
 pub fn sha1_base32(bytes: &[u8]) -> String {
     let mut hasher = Sha1::new();
@@ -0,0 +1,156 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Parses, converts, and measures the contrast between colors.
+
+/// Parses an opaque CSS color in hex (`#rgb`, `#rrggbb`, and their alpha variants), `rgb()`, or
+/// `rgba()` notation, or one of a few common keywords.
+///
+/// # Returns
+///
+/// The red, green, and blue channels, or `None` if the color can't be parsed or isn't fully
+/// opaque, since the contrast of a translucent color depends on what's behind it.
+pub fn parse(color: &str) -> Option<[u8; 3]> {
+    let color = color.trim().to_ascii_lowercase();
+    if let Some(hex) = color.strip_prefix('#') {
+        return parse_hex(hex);
+    }
+    if let Some(args) = color
+        .strip_prefix("rgba(")
+        .or_else(|| color.strip_prefix("rgb("))
+    {
+        return parse_rgb(args.strip_suffix(')')?);
+    }
+    match color.as_str() {
+        "black" => Some([0, 0, 0]),
+        "white" => Some([255, 255, 255]),
+        _ => None,
+    }
+}
+
+/// Parses the digits of a CSS hex color.
+fn parse_hex(hex: &str) -> Option<[u8; 3]> {
+    let digits = match hex.len() {
+        3 | 4 => hex.chars().flat_map(|c| [c, c]).collect::<String>(),
+        6 | 8 => hex.into(),
+        _ => return None,
+    };
+    let bytes = hex::decode(digits).ok()?;
+    if bytes.get(3).is_some_and(|a| *a != 255) {
+        return None;
+    }
+    Some([bytes[0], bytes[1], bytes[2]])
+}
+
+/// Parses the arguments of a CSS `rgb()` or `rgba()` function, separated by commas or spaces.
+fn parse_rgb(args: &str) -> Option<[u8; 3]> {
+    let args = args
+        .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+        .filter(|a| !a.is_empty())
+        .collect::<Vec<_>>();
+    let channel = |arg: &str| -> Option<u8> {
+        let value = match arg.strip_suffix('%') {
+            Some(percent) => percent.parse::<f32>().ok()? * 2.55,
+            None => arg.parse::<f32>().ok()?,
+        };
+        Some(value.round().clamp(0.0, 255.0) as u8)
+    };
+    match args.as_slice() {
+        [r, g, b] => Some([channel(r)?, channel(g)?, channel(b)?]),
+        [r, g, b, a] => {
+            let alpha = match a.strip_suffix('%') {
+                Some(percent) => percent.parse::<f32>().ok()? / 100.0,
+                None => a.parse::<f32>().ok()?,
+            };
+            (alpha >= 1.0).then_some([channel(r)?, channel(g)?, channel(b)?])
+        }
+        _ => None,
+    }
+}
+
+/// Calculates the [relative luminance][1] of a color.
+///
+/// # Returns
+///
+/// The luminance, ranging from 0 for black to 1 for white.
+///
+/// [1]: <https://www.w3.org/TR/WCAG21/#dfn-relative-luminance>
+pub fn relative_luminance([r, g, b]: [u8; 3]) -> f32 {
+    let linear = |c: u8| {
+        let c = f32::from(c) / 255f32;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+/// Calculates the [contrast ratio][1] between two relative luminances.
+///
+/// # Returns
+///
+/// The contrast ratio, ranging from 1 to 21.
+///
+/// [1]: <https://www.w3.org/TR/WCAG21/#dfn-contrast-ratio>
+pub fn contrast_ratio(a: f32, b: f32) -> f32 {
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Formats an RGB color with channels ranging from 0 to 1 as a CSS hex color.
+pub fn to_hex(rgb: [f32; 3]) -> String {
+    let [r, g, b] = rgb.map(|c| (c.clamp(0f32, 1f32) * 255f32).round() as u8);
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Converts an RGB color to HSL. All channels range from 0 to 1.
+pub fn rgb_to_hsl([r, g, b]: [f32; 3]) -> (f32, f32, f32) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2f32;
+    if max == min {
+        return (0f32, 0f32, l);
+    }
+
+    let d = max - min;
+    let s = if l > 0.5 {
+        d / (2f32 - max - min)
+    } else {
+        d / (max + min)
+    };
+    let h = if max == r {
+        (g - b) / d + if g < b { 6f32 } else { 0f32 }
+    } else if max == g {
+        (b - r) / d + 2f32
+    } else {
+        (r - g) / d + 4f32
+    };
+    (h / 6f32, s, l)
+}
+
+/// Converts an HSL color to RGB. All channels range from 0 to 1.
+pub fn hsl_to_rgb(h: f32, s: f32, l: f32) -> [f32; 3] {
+    if s == 0f32 {
+        return [l; 3];
+    }
+
+    let q = if l < 0.5 {
+        l * (1f32 + s)
+    } else {
+        l + s - l * s
+    };
+    let p = 2f32 * l - q;
+    let hue = |t: f32| {
+        let t = t.rem_euclid(1f32);
+        if t < 1f32 / 6f32 {
+            p + (q - p) * 6f32 * t
+        } else if t < 0.5 {
+            q
+        } else if t < 2f32 / 3f32 {
+            p + (q - p) * (2f32 / 3f32 - t) * 6f32
+        } else {
+            p
+        }
+    };
+    [hue(h + 1f32 / 3f32), hue(h), hue(h - 1f32 / 3f32)]
+}
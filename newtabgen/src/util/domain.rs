@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Finds the registrable domain of URLs, using the [public suffix list][1].
+//!
+//! [1]: <https://publicsuffix.org>
+
+use std::sync::OnceLock;
+
+use publicsuffix::{List, Psl};
+use resource::resource_str;
+use tracing::warn;
+use url::{Host, Url};
+
+/// Returns the parsed public suffix list, parsing it on first use.
+fn list() -> Option<&'static List> {
+    static LIST: OnceLock<Option<List>> = OnceLock::new();
+    LIST.get_or_init(|| {
+        resource_str!("res/public_suffix_list.dat")
+            .parse()
+            .map_err(|e| {
+                warn!(
+                    error = format!("{}", e),
+                    "failed to parse public suffix list"
+                )
+            })
+            .ok()
+    })
+    .as_ref()
+}
+
+/// Finds the registrable domain of a URL: its public suffix and the label before it, e.g.
+/// `github.com` for `https://docs.github.com/en`, or `example.co.uk` for
+/// `https://www.example.co.uk`. Sites hosted under a shared suffix like `github.io` each have
+/// their own registrable domain.
+///
+/// # Returns
+///
+/// The registrable domain, or `None` if the URL can't be parsed, its host is an IP address, or
+/// it has no registrable domain, like `localhost`.
+pub fn registrable_domain(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let Some(Host::Domain(host)) = url.host() else {
+        return None;
+    };
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    let domain = list()?.domain(host.as_bytes())?;
+    String::from_utf8(domain.as_bytes().to_vec()).ok()
+}
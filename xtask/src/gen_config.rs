@@ -33,6 +33,11 @@ fn gen_pages(rng: &mut ThreadRng) -> Vec<Page> {
             sections: gen_sections(rng),
             icon: random_svg_icon(),
             icon_style: "outlined".into(),
+            color: None,
+            weight: 0,
+            template: None,
+            template_src: None,
+            data: Default::default(),
         });
     }
     vec
@@ -45,6 +50,11 @@ fn gen_sections(rng: &mut ThreadRng) -> Vec<Section> {
         vec.push(Section {
             name: lipsum_words(rng.gen_range(1..10)),
             links: gen_links(rng),
+            sections: Vec::new(),
+            embeds: Vec::new(),
+            description: None,
+            layout: None,
+            max_visible: None,
         });
     }
     vec
@@ -69,6 +79,12 @@ fn gen_links(rng: &mut ThreadRng) -> Vec<Link> {
         vec.push(Link {
             name: lipsum_words(rng.gen_range(1..10)),
             url: (*urls.choose(rng).unwrap()).into(),
+            description: None,
+            badge: None,
+            badge_url: None,
+            badge_field: None,
+            size: Default::default(),
+            tags: Vec::new(),
         });
     }
     vec